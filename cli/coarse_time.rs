@@ -0,0 +1,95 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+//! Reduced time precision for programs without `--allow-hrtime`, as a defense
+//! against timing side channels. Every time exposed to JavaScript is clamped
+//! to a multiple of the resolution given with `--timer-resolution` (2ms by
+//! default), see `State::timer_resolution()`.
+//!
+//! Plain rounding would leak the exact time at every rounding edge, so the
+//! point within each interval from which a time is rounded up instead of down
+//! is chosen at random, but consistently for the whole process. Coarsened
+//! times therefore never go backwards.
+//! See https://developer.mozilla.org/en-US/docs/Web/API/Performance/now#Reduced_time_precision
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_RESOLUTION: Duration = Duration::from_millis(2);
+
+lazy_static! {
+  static ref JITTER_SECRET: u64 = rand::random();
+}
+
+/// Clamps `time` to a multiple of `resolution`, or returns it unchanged if
+/// `resolution` is `None`.
+pub fn coarsen(time: Duration, resolution: Option<Duration>) -> Duration {
+  let resolution = match resolution {
+    Some(resolution) if resolution.as_nanos() > 1 => resolution.as_nanos(),
+    _ => return time,
+  };
+  let nanos = time.as_nanos();
+  let interval = nanos / resolution;
+  let offset = nanos % resolution;
+  let clamped = if offset > 0 && offset >= rounding_edge(interval, resolution) {
+    (interval + 1) * resolution
+  } else {
+    interval * resolution
+  };
+  Duration::new(
+    (clamped / 1_000_000_000) as u64,
+    (clamped % 1_000_000_000) as u32,
+  )
+}
+
+/// Returns the current time in milliseconds since the UNIX epoch, like
+/// `Date.now()`, clamped to `resolution`.
+pub fn unix_time_millis(resolution: Option<Duration>) -> f64 {
+  let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+  let now = coarsen(now, resolution);
+  now.as_secs() as f64 * 1e3 + f64::from(now.subsec_nanos()) / 1e6
+}
+
+/// The offset within the interval from which times are rounded up, between 1
+/// and `resolution - 1` nanoseconds.
+fn rounding_edge(interval: u128, resolution: u128) -> u128 {
+  let mut hasher = DefaultHasher::new();
+  (*JITTER_SECRET, interval).hash(&mut hasher);
+  1 + u128::from(hasher.finish()) % (resolution - 1)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn coarsen_to_resolution() {
+    let resolution = Some(Duration::from_millis(2));
+    let mut last = Duration::from_secs(0);
+    for micros in 0..20_000 {
+      let time = Duration::from_micros(1_000_000 + micros * 7);
+      let coarse = coarsen(time, resolution);
+      assert_eq!(coarse.as_nanos() % 2_000_000, 0);
+      assert!(coarse >= last);
+      assert!(coarse <= time + Duration::from_millis(2));
+      assert!(coarse + Duration::from_millis(2) >= time);
+      last = coarse;
+    }
+    // Exact multiples of the resolution are kept as is.
+    let time = Duration::from_secs(5);
+    assert_eq!(coarsen(time, resolution), time);
+    let time = Duration::from_nanos(1_234_567);
+    assert_eq!(coarsen(time, None), time);
+  }
+
+  #[test]
+  fn jitter_rounding_edges() {
+    // Times in the middle of an interval aren't always rounded the same way.
+    let resolution = Some(Duration::from_millis(1));
+    let rounded_up = (0..100u64)
+      .filter(|ms| {
+        let time = Duration::from_micros(ms * 1000 + 500);
+        coarsen(time, resolution) > time
+      })
+      .count();
+    assert!(rounded_up > 0 && rounded_up < 100);
+  }
+}
@@ -79,6 +79,7 @@ impl SourceFileFetcher {
     no_remote: bool,
     cached_only: bool,
     ca_file: Option<String>,
+    proxy: Option<String>,
   ) -> Result<Self, ErrBox> {
     let file_fetcher = Self {
       http_cache,
@@ -87,7 +88,7 @@ impl SourceFileFetcher {
       use_disk_cache,
       no_remote,
       cached_only,
-      http_client: create_http_client(ca_file)?,
+      http_client: create_http_client(ca_file, proxy)?,
     };
 
     Ok(file_fetcher)
@@ -520,6 +521,22 @@ fn map_file_extension(path: &Path) -> msg::MediaType {
   }
 }
 
+/// The content type of a local file, derived from its extension in the same
+/// way as the media type of local modules.
+pub fn map_file_content_type(path: &Path) -> &'static str {
+  match map_file_extension(path) {
+    msg::MediaType::TypeScript | msg::MediaType::TSX => {
+      "application/typescript"
+    }
+    msg::MediaType::JavaScript | msg::MediaType::JSX => {
+      "application/javascript"
+    }
+    msg::MediaType::Json => "application/json",
+    msg::MediaType::Wasm => "application/wasm",
+    msg::MediaType::Unknown => "application/octet-stream",
+  }
+}
+
 // convert a ContentType string into a enumerated MediaType
 fn map_content_type(path: &Path, content_type: Option<&str>) -> msg::MediaType {
   match content_type {
@@ -667,6 +684,7 @@ mod tests {
       false,
       false,
       None,
+      None,
     )
     .expect("setup fail")
   }
@@ -1,5 +1,6 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 use std::env::current_dir;
+use std::ffi::OsStr;
 use std::fs::OpenOptions;
 use std::io::Write;
 use std::path::{Component, Path, PathBuf};
@@ -91,6 +92,114 @@ pub fn resolve_from_cwd(path: &Path) -> Result<PathBuf, ErrBox> {
   Ok(normalize_path(&resolved_path))
 }
 
+/// Resolves the symlinks in `path` like `fs::canonicalize()`, but also works
+/// for paths that don't exist yet: the longest existing ancestor is
+/// canonicalized and the remaining components are appended as given. A
+/// dangling symlink is resolved to its target, which is where writing through
+/// it would create a file. Used by the permission checks, so that a symlink
+/// inside an allowed directory can't grant access to what it points to.
+pub fn resolve_real_path(path: &Path) -> PathBuf {
+  resolve_real_path_inner(&normalize_path(path), 0)
+}
+
+/// Like `resolve_real_path()`, but leaves the last component as is. For
+/// operations on a directory entry itself, e.g. renaming a symlink rather
+/// than its target.
+pub fn resolve_real_parent(path: &Path) -> PathBuf {
+  let path = normalize_path(path);
+  match (path.parent(), path.file_name()) {
+    (Some(parent), Some(file_name)) => {
+      resolve_real_path_inner(parent, 0).join(file_name)
+    }
+    _ => resolve_real_path_inner(&path, 0),
+  }
+}
+
+/// The number of symlinks followed before giving up, like `ELOOP`.
+const MAX_SYMLINK_DEPTH: usize = 40;
+
+fn resolve_real_path_inner(path: &Path, depth: usize) -> PathBuf {
+  if let Ok(real_path) = path.canonicalize() {
+    return strip_verbatim_prefix(real_path);
+  }
+  let (parent, file_name) = match (path.parent(), path.file_name()) {
+    (Some(parent), Some(file_name)) => (parent, file_name),
+    _ => return path.to_owned(),
+  };
+  let real_parent = resolve_real_path_inner(parent, depth);
+  if depth < MAX_SYMLINK_DEPTH {
+    if let Ok(target) = std::fs::read_link(path) {
+      let target = normalize_path(&real_parent.join(target));
+      return resolve_real_path_inner(&target, depth + 1);
+    }
+  }
+  real_parent.join(file_name)
+}
+
+/// `fs::canonicalize()` returns extended-length paths on Windows, e.g.
+/// `\\?\C:\foo`, which never match paths given by the user.
+#[cfg(windows)]
+fn strip_verbatim_prefix(path: PathBuf) -> PathBuf {
+  match path.to_str() {
+    Some(s) if s.starts_with(r"\\?\") && !s.starts_with(r"\\?\UNC\") => {
+      PathBuf::from(&s[4..])
+    }
+    _ => path,
+  }
+}
+
+#[cfg(not(windows))]
+fn strip_verbatim_prefix(path: PathBuf) -> PathBuf {
+  path
+}
+
+/// Resolves the program of a subprocess the way the OS would when spawning
+/// it: names containing a path separator are resolved against `cwd`, bare
+/// names are looked up in the directories listed in `path_env`. Returns `None`
+/// if no executable could be found.
+pub fn resolve_program(
+  program: &str,
+  cwd: &Path,
+  path_env: Option<&OsStr>,
+) -> Option<PathBuf> {
+  let program_path = Path::new(program);
+  if program_path.is_absolute() || program_path.components().count() > 1 {
+    return find_executable(&normalize_path(&cwd.join(program_path)));
+  }
+  std::env::split_paths(path_env?)
+    .filter(|dir| !dir.as_os_str().is_empty())
+    .find_map(|dir| {
+      find_executable(&normalize_path(&cwd.join(dir).join(program)))
+    })
+}
+
+#[cfg(unix)]
+fn find_executable(path: &Path) -> Option<PathBuf> {
+  use std::os::unix::fs::PermissionsExt;
+  let metadata = std::fs::metadata(path).ok()?;
+  if metadata.is_file() && metadata.permissions().mode() & 0o111 != 0 {
+    Some(path.to_owned())
+  } else {
+    None
+  }
+}
+
+#[cfg(not(unix))]
+fn find_executable(path: &Path) -> Option<PathBuf> {
+  if path.is_file() {
+    return Some(path.to_owned());
+  }
+  if path.extension().is_none() {
+    for ext in &["exe", "com", "bat", "cmd"] {
+      let candidate = path.with_extension(ext);
+      if candidate.is_file() {
+        return Some(candidate);
+      }
+    }
+  }
+  None
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -137,6 +246,71 @@ mod tests {
     let expected = Path::new("/a");
     assert_eq!(resolve_from_cwd(expected).unwrap(), expected);
   }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_resolve_real_path() {
+    use std::os::unix::fs::symlink;
+    let temp_dir = tempfile::tempdir().unwrap();
+    let root = temp_dir.path().canonicalize().unwrap();
+    let dir = root.join("dir");
+    let outside = root.join("outside");
+    std::fs::create_dir(&dir).unwrap();
+    std::fs::create_dir(&outside).unwrap();
+    symlink(&outside, dir.join("link")).unwrap();
+    symlink(outside.join("missing"), dir.join("dangling")).unwrap();
+
+    assert_eq!(resolve_real_path(&dir), dir);
+    assert_eq!(resolve_real_path(&dir.join("link")), outside);
+    assert_eq!(
+      resolve_real_path(&dir.join("link/new/file")),
+      outside.join("new/file")
+    );
+    assert_eq!(resolve_real_path(&dir.join("new")), dir.join("new"));
+    assert_eq!(
+      resolve_real_path(&dir.join("dangling")),
+      outside.join("missing")
+    );
+    assert_eq!(resolve_real_parent(&dir.join("link")), dir.join("link"));
+    assert_eq!(
+      resolve_real_parent(&dir.join("link/file")),
+      outside.join("file")
+    );
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_resolve_program() {
+    use std::os::unix::fs::PermissionsExt;
+    let temp_dir = tempfile::TempDir::new().expect("tempdir fail");
+    let bin_dir = temp_dir.path().join("bin");
+    std::fs::create_dir(&bin_dir).unwrap();
+    let exe = bin_dir.join("tool");
+    std::fs::write(&exe, "#!/bin/sh\n").unwrap();
+    std::fs::set_permissions(&exe, std::fs::Permissions::from_mode(0o755))
+      .unwrap();
+    let not_exe = bin_dir.join("data");
+    std::fs::write(&not_exe, "").unwrap();
+
+    let path_env = std::env::join_paths(vec![
+      PathBuf::from("/does/not/exist"),
+      bin_dir.clone(),
+    ])
+    .unwrap();
+    let cwd = temp_dir.path();
+    assert_eq!(
+      resolve_program("tool", cwd, Some(&path_env)),
+      Some(exe.clone())
+    );
+    assert_eq!(resolve_program("tool", cwd, None), None);
+    assert_eq!(resolve_program("data", cwd, Some(&path_env)), None);
+    assert_eq!(resolve_program("missing", cwd, Some(&path_env)), None);
+    assert_eq!(resolve_program("./bin/tool", cwd, None), Some(exe.clone()));
+    assert_eq!(
+      resolve_program(exe.to_str().unwrap(), Path::new("/"), None),
+      Some(exe)
+    );
+  }
 }
 
 pub fn files_in_subtree<F>(root: PathBuf, filter: F) -> Vec<PathBuf>
@@ -9,9 +9,13 @@ use crate::deno_dir;
 use crate::file_fetcher::SourceFileFetcher;
 use crate::flags;
 use crate::http_cache;
+use crate::http_util::create_http_client;
 use crate::lockfile::Lockfile;
 use crate::msg;
+use crate::permission_policy::PermissionPolicy;
+use crate::permission_store::PermissionStore;
 use crate::permissions::DenoPermissions;
+use crate::permissions::PermissionAudit;
 use deno_core::ErrBox;
 use deno_core::ModuleSpecifier;
 use std::env;
@@ -35,6 +39,9 @@ pub struct GlobalStateInner {
   pub permissions: DenoPermissions,
   pub dir: deno_dir::DenoDir,
   pub file_fetcher: SourceFileFetcher,
+  /// The client of `fetch` requests that don't ask for their own, shared so
+  /// that they reuse connections.
+  pub http_client: reqwest::Client,
   pub js_compiler: JsCompiler,
   pub json_compiler: JsonCompiler,
   pub ts_compiler: TsCompiler,
@@ -65,8 +72,12 @@ impl GlobalState {
       flags.no_remote,
       flags.cached_only,
       flags.ca_file.clone(),
+      flags.proxy.clone(),
     )?;
 
+    let http_client =
+      create_http_client(flags.ca_file.clone(), flags.proxy.clone())?;
+
     let ts_compiler = TsCompiler::new(
       file_fetcher.clone(),
       dir.gen_cache.clone(),
@@ -81,11 +92,28 @@ impl GlobalState {
       None
     };
 
+    let mut permissions = DenoPermissions::from_flags(&flags);
+    if let Some(store) = PermissionStore::from_flags(&flags, &dir.root) {
+      match store.load() {
+        Ok(Some(grants)) => permissions.add_grants(&grants),
+        Ok(None) => {}
+        Err(e) => eprintln!("Failed to load stored permissions: {}", e),
+      }
+      permissions.store = Some(store);
+    }
+    if let Some(audit_path) = &flags.permission_audit {
+      permissions.audit = Some(PermissionAudit::open(audit_path)?);
+    }
+    if let Some(policy_path) = &flags.permission_policy {
+      permissions.policy = Some(Arc::new(PermissionPolicy::load(policy_path)?));
+    }
+
     let inner = GlobalStateInner {
       dir,
-      permissions: DenoPermissions::from_flags(&flags),
+      permissions,
       flags,
       file_fetcher,
+      http_client,
       ts_compiler,
       js_compiler: JsCompiler {},
       json_compiler: JsonCompiler {},
@@ -163,6 +163,10 @@ pub fn install(
     executable_args.push("--cert".to_string());
     executable_args.push(ca_file)
   }
+  if let Some(proxy) = flags.proxy {
+    executable_args.push("--proxy".to_string());
+    executable_args.push(proxy)
+  }
   if let Some(log_level) = flags.log_level {
     if log_level == Level::Error {
       executable_args.push("--quiet".to_string());
@@ -16,6 +16,21 @@ pub static COMPILER_SNAPSHOT_MAP: &[u8] =
 pub static COMPILER_SNAPSHOT_DTS: &[u8] =
   include_bytes!(concat!(env!("OUT_DIR"), "/COMPILER_SNAPSHOT.d.ts"));
 
+lazy_static! {
+  /// The names of the scripts the snapshots were built from, see `build.rs`
+  /// and `deno_typescript::mksnapshot_bundle()`. Their frames belong to the
+  /// runtime rather than to a module.
+  pub static ref RUNTIME_SCRIPT_NAMES: Vec<String> = {
+    let out_dir = std::path::Path::new(env!("OUT_DIR"));
+    vec![
+      "system_loader.js".to_string(),
+      "typescript.js".to_string(),
+      out_dir.join("CLI_SNAPSHOT.js").to_string_lossy().to_string(),
+      out_dir.join("COMPILER_SNAPSHOT.js").to_string_lossy().to_string(),
+    ]
+  };
+}
+
 pub static DENO_NS_LIB: &str = include_str!("js/lib.deno.ns.d.ts");
 pub static SHARED_GLOBALS_LIB: &str =
   include_str!("js/lib.deno.shared_globals.d.ts");
@@ -73,9 +73,22 @@ export {
   listen,
   DatagramConn,
   Listener,
+  TcpListener,
   Conn,
+  TcpConn,
   ShutdownMode,
   shutdown,
+  PeerCredentials,
+  getPeerCredentials,
+  ReceivedResource,
+  ReceivedMessage,
+  sendResources,
+  receiveResources,
+  resolveDns,
+  RecordType,
+  ResolveDnsOptions,
+  MXRecord,
+  SRVRecord,
 } from "./net.ts";
 export {
   dir,
@@ -106,8 +119,34 @@ export { resources, close } from "./ops/resources.ts";
 export { signal, signals, Signal, SignalStream } from "./signals.ts";
 export { FileInfo, statSync, lstatSync, stat, lstat } from "./ops/fs/stat.ts";
 export { symlinkSync, symlink } from "./ops/fs/symlink.ts";
-export { connectTLS, listenTLS, startTLS } from "./tls.ts";
+export {
+  connectTLS,
+  listenTLS,
+  startTLS,
+  startTLSServer,
+  TLSConn,
+  TLSHandshakeInfo,
+  TLSPeerCertificate,
+  TLSListener,
+} from "./tls.ts";
+export {
+  serveHttp,
+  HttpConn,
+  HttpRequest,
+  HttpResponse,
+  RequestEvent,
+} from "./http.ts";
+export {
+  createHttpClient,
+  CreateHttpClientOptions,
+  HttpClient,
+} from "./http_client.ts";
 export { truncateSync, truncate } from "./ops/fs/truncate.ts";
+export {
+  upgradeWebSocket,
+  UpgradeWebSocketOptions,
+  WebSocketUpgrade,
+} from "./web/websocket.ts";
 export { isatty, setRaw } from "./ops/tty.ts";
 export { umask } from "./ops/fs/umask.ts";
 export { utimeSync, utime } from "./ops/fs/utime.ts";
@@ -20,6 +20,7 @@ import * as timers from "./web/timers.ts";
 import * as url from "./web/url.ts";
 import * as urlSearchParams from "./web/url_search_params.ts";
 import * as workers from "./web/workers.ts";
+import * as webSocket from "./web/websocket.ts";
 import * as performanceUtil from "./web/performance.ts";
 import * as request from "./web/request.ts";
 import * as streams from "./web/streams/mod.ts";
@@ -228,6 +229,8 @@ export const windowOrWorkerGlobalScopeProperties = {
   Response: nonEnumerable(fetchTypes.Response),
   performance: writable(new performanceUtil.Performance()),
   Worker: nonEnumerable(workers.WorkerImpl),
+  CloseEvent: nonEnumerable(webSocket.CloseEvent),
+  WebSocket: nonEnumerable(webSocket.WebSocketImpl),
 };
 
 // eslint-disable-next-line @typescript-eslint/no-explicit-any
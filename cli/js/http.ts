@@ -0,0 +1,140 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+import { errors } from "./errors.ts";
+import { EOF, Reader, Closer, copy } from "./io.ts";
+import { read, write } from "./ops/io.ts";
+import { close } from "./ops/resources.ts";
+import * as httpOps from "./ops/http.ts";
+import { Conn } from "./net.ts";
+import { TextEncoder } from "./web/text_encoding.ts";
+
+export interface HttpRequest {
+  readonly method: string;
+  readonly url: string;
+  readonly headers: Headers;
+  readonly body: (Reader & Closer) | null;
+}
+
+export interface HttpResponse {
+  status?: number;
+  headers?: Headers | Array<[string, string]> | Record<string, string>;
+  body?: Uint8Array | string | Reader;
+}
+
+export interface RequestEvent {
+  readonly request: HttpRequest;
+  respondWith(response: HttpResponse): Promise<void>;
+}
+
+export interface HttpConn extends AsyncIterable<RequestEvent> {
+  readonly rid: number;
+
+  nextRequest(): Promise<RequestEvent | null>;
+
+  close(): void;
+
+  [Symbol.asyncIterator](): AsyncIterableIterator<RequestEvent>;
+}
+
+class HttpBodyReader implements Reader, Closer {
+  constructor(readonly rid: number) {}
+
+  read(p: Uint8Array): Promise<number | EOF> {
+    return read(this.rid, p);
+  }
+
+  close(): void {
+    close(this.rid);
+  }
+}
+
+class HttpBodyWriter {
+  constructor(readonly rid: number) {}
+
+  write(p: Uint8Array): Promise<number> {
+    return write(this.rid, p);
+  }
+}
+
+class RequestEventImpl implements RequestEvent {
+  #rid: number;
+  #responded = false;
+
+  constructor(rid: number, readonly request: HttpRequest) {
+    this.#rid = rid;
+  }
+
+  async respondWith({
+    status = 200,
+    headers = [],
+    body,
+  }: HttpResponse): Promise<void> {
+    if (this.#responded) {
+      throw new errors.Http("Response already sent");
+    }
+    this.#responded = true;
+    const args = {
+      rid: this.#rid,
+      status,
+      headers: [...new Headers(headers)],
+      stream: false,
+    };
+    if (body === undefined || body instanceof Uint8Array) {
+      httpOps.respond(args, body);
+    } else if (typeof body === "string") {
+      httpOps.respond(args, new TextEncoder().encode(body));
+    } else {
+      args.stream = true;
+      const { bodyRid } = httpOps.respond(args);
+      try {
+        await copy(new HttpBodyWriter(bodyRid!), body);
+      } finally {
+        close(bodyRid!);
+      }
+    }
+  }
+}
+
+export class HttpConnImpl implements HttpConn {
+  constructor(readonly rid: number) {}
+
+  async nextRequest(): Promise<RequestEvent | null> {
+    const res = await httpOps.nextRequest(this.rid);
+    if (res === null) {
+      return null;
+    }
+    const request: HttpRequest = {
+      method: res.method,
+      url: res.url,
+      headers: new Headers(res.headers),
+      body: res.bodyRid === null ? null : new HttpBodyReader(res.bodyRid),
+    };
+    return new RequestEventImpl(res.rid, request);
+  }
+
+  close(): void {
+    close(this.rid);
+  }
+
+  async *[Symbol.asyncIterator](): AsyncIterableIterator<RequestEvent> {
+    while (true) {
+      let requestEvent;
+      try {
+        requestEvent = await this.nextRequest();
+      } catch (error) {
+        if (error instanceof errors.BadResource) {
+          break;
+        }
+        throw error;
+      }
+      if (requestEvent === null) {
+        break;
+      }
+      yield requestEvent;
+    }
+  }
+}
+
+export function serveHttp(conn: Conn): HttpConn {
+  const res = httpOps.startHttp(conn.rid);
+  return new HttpConnImpl(res.rid);
+}
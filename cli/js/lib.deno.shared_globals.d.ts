@@ -707,9 +707,21 @@ interface RequestInit {
    */
   referrerPolicy?: ReferrerPolicy;
   /**
-   * An AbortSignal to set request's signal.
+   * An AbortSignal to set request's signal. Aborting it rejects the pending
+   * fetch, or reads of the response body, with an `AbortError`.
    */
   signal?: AbortSignal | null;
+  /**
+   * Deno specific: the number of milliseconds after which the request is
+   * cancelled, including reading the response body. The fetch, or the read,
+   * is then rejected with `Deno.errors.TimedOut`.
+   */
+  timeout?: number;
+  /**
+   * Deno specific: a client created with `Deno.createHttpClient()` to send
+   * the request with, instead of the default one.
+   */
+  client?: Deno.HttpClient;
   /**
    * Can only be null. Used to disassociate request from any Window.
    */
@@ -849,7 +861,15 @@ declare const Response: {
   redirect(url: string, status?: number): Response;
 };
 
-/** Fetch a resource from the network. */
+/** Fetch a resource from the network.
+ *
+ * A `ReadableStream` request body is sent as it is read, without buffering
+ * it in memory. So is a `Deno.Reader` body, such as a `Deno.File` or a
+ * `Deno.Conn`, which is closed once it has been sent. A streaming body can't
+ * be sent again, so following a redirect fails with a `TypeError`.
+ *
+ * `file:` URLs are read from disk, which requires `allow-read`, and `data:`
+ * URLs respond with their decoded payload. Both only support `GET`. */
 declare function fetch(
   input: Request | URL | string,
   init?: RequestInit
@@ -1087,9 +1107,18 @@ declare class Worker extends EventTarget {
        * Set to `true` to make `Deno` namespace and all of its methods
        * available to worker thread.
        *
-       * Currently worker inherits permissions from main thread (permissions
-       * given using `--allow-*` flags).
-       * Configurable permissions are on the roadmap to be implemented.
+       * By default the worker inherits the permissions of the thread that
+       * creates it. Pass an object with `permissions` instead of `true` to
+       * run the worker with fewer permissions. Each permission is either
+       * `true` or a whitelist in the syntax of the corresponding `--allow-*`
+       * flag; omitted permissions are denied and can't be requested by the
+       * worker. Asking for a permission the creating thread doesn't have
+       * throws `Deno.errors.PermissionDenied`.
+       *
+       *    new Worker("./plugin.ts", {
+       *      type: "module",
+       *      deno: { permissions: { read: ["./data"], net: ["deno.land"] } },
+       *    });
        *
        * Example:
        *    // mod.ts
@@ -1119,7 +1148,19 @@ declare class Worker extends EventTarget {
        *    hello world2
        *
        */
-      deno?: boolean;
+      deno?:
+        | boolean
+        | {
+            permissions?: {
+              read?: boolean | string[];
+              write?: boolean | string[];
+              net?: boolean | string[];
+              env?: boolean | string[];
+              run?: boolean | string[];
+              plugin?: boolean;
+              hrtime?: boolean;
+            };
+          };
     }
   );
   postMessage(message: any, transfer: ArrayBuffer[]): void;
@@ -1127,10 +1168,66 @@ declare class Worker extends EventTarget {
   terminate(): void;
 }
 
+interface CloseEventInit extends EventInit {
+  wasClean?: boolean;
+  code?: number;
+  reason?: string;
+}
+
+declare class CloseEvent extends Event {
+  readonly wasClean: boolean;
+  readonly code: number;
+  readonly reason: string;
+  constructor(type: string, eventInitDict?: CloseEventInit);
+}
+
+type BinaryType = "blob" | "arraybuffer";
+
+/** A WebSocket client, connected to a `ws:` or `wss:` URL. Requires
+ * `allow-net` for the URL. `wss:` connections trust the CA certificates
+ * given with `--cert`, in addition to the built-in ones.
+ *
+ *       const socket = new WebSocket("wss://echo.websocket.org");
+ *       socket.onopen = (): void => socket.send("hello");
+ *       socket.onmessage = (e): void => {
+ *         console.log(e.data);
+ *         socket.close();
+ *       };
+ */
+declare class WebSocket extends EventTarget {
+  constructor(url: string, protocols?: string | string[]);
+  static readonly CONNECTING: number;
+  static readonly OPEN: number;
+  static readonly CLOSING: number;
+  static readonly CLOSED: number;
+  readonly CONNECTING: number;
+  readonly OPEN: number;
+  readonly CLOSING: number;
+  readonly CLOSED: number;
+  readonly url: string;
+  readonly readyState: number;
+  readonly protocol: string;
+  readonly extensions: string;
+  /** The number of bytes of data that were passed to `send()` but not sent
+   * yet. */
+  readonly bufferedAmount: number;
+  /** How binary messages are received. Defaults to `"blob"`. */
+  binaryType: BinaryType;
+  onopen: ((e: Event) => void) | null;
+  onmessage: ((e: MessageEvent) => void) | null;
+  onerror: ((e: Event) => void) | null;
+  onclose: ((e: CloseEvent) => void) | null;
+  send(data: string | ArrayBufferLike | Blob | ArrayBufferView): void;
+  /** Starts the closing handshake. `code` must be 1000 or between 3000 and
+   * 4999, and `reason` at most 123 bytes of UTF-8. */
+  close(code?: number, reason?: string): void;
+}
+
 declare namespace performance {
   /** Returns a current time from Deno's start in milliseconds.
    *
-   * Use the flag --allow-hrtime return a precise value.
+   * Use the flag --allow-hrtime return a precise value. Otherwise the value is
+   * clamped to the resolution given with --timer-resolution, 2ms by default.
    *
    *       const t = performance.now();
    *       console.log(`${t} ms since start!`);
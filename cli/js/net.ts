@@ -1,11 +1,19 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 import { errors } from "./errors.ts";
+import { File } from "./files.ts";
 import { EOF, Reader, Writer, Closer } from "./io.ts";
 import { read, write } from "./ops/io.ts";
 import { close } from "./ops/resources.ts";
 import * as netOps from "./ops/net.ts";
-import { Addr } from "./ops/net.ts";
-export { ShutdownMode, shutdown, NetAddr, UnixAddr } from "./ops/net.ts";
+import { Addr, ListenSocketOptions } from "./ops/net.ts";
+export {
+  ShutdownMode,
+  shutdown,
+  NetAddr,
+  UnixAddr,
+  PeerCredentials,
+  getPeerCredentials,
+} from "./ops/net.ts";
 
 export interface DatagramConn extends AsyncIterable<[Uint8Array, Addr]> {
   receive(p?: Uint8Array): Promise<[Uint8Array, Addr]>;
@@ -16,6 +24,18 @@ export interface DatagramConn extends AsyncIterable<[Uint8Array, Addr]> {
 
   addr: Addr;
 
+  setBroadcast(broadcast?: boolean): void;
+
+  setTTL(ttl: number): void;
+
+  setMulticastTTL(ttl: number): void;
+
+  setMulticastLoopback(loopback?: boolean): void;
+
+  joinMulticast(multiaddr: string, networkInterface?: string | number): void;
+
+  leaveMulticast(multiaddr: string, networkInterface?: string | number): void;
+
   [Symbol.asyncIterator](): AsyncIterableIterator<[Uint8Array, Addr]>;
 }
 
@@ -55,6 +75,31 @@ export class ConnImpl implements Conn {
   closeWrite(): void {
     netOps.shutdown(this.rid, netOps.ShutdownMode.Write);
   }
+
+}
+
+export class TcpConnImpl extends ConnImpl implements TcpConn {
+  setNoDelay(noDelay = true): void {
+    netOps.setTCPOption(this.rid, { noDelay });
+  }
+
+  setKeepAlive(keepAlive = true, idleTime = 7200000, interval?: number): void {
+    netOps.setTCPOption(this.rid, {
+      keepAlive: keepAlive ? { idleTime, interval } : null,
+    });
+  }
+
+  setSendBufferSize(size: number): void {
+    netOps.setTCPOption(this.rid, { sendBufferSize: size });
+  }
+
+  setRecvBufferSize(size: number): void {
+    netOps.setTCPOption(this.rid, { recvBufferSize: size });
+  }
+
+  setTTL(ttl: number): void {
+    netOps.setTCPOption(this.rid, { ttl });
+  }
 }
 
 export class ListenerImpl implements Listener {
@@ -62,6 +107,9 @@ export class ListenerImpl implements Listener {
 
   async accept(): Promise<Conn> {
     const res = await netOps.accept(this.rid, this.addr.transport);
+    if (this.addr.transport === "tcp") {
+      return new TcpConnImpl(res.rid, res.remoteAddr, res.localAddr);
+    }
     return new ConnImpl(res.rid, res.remoteAddr, res.localAddr);
   }
 
@@ -83,6 +131,12 @@ export class ListenerImpl implements Listener {
   }
 }
 
+export interface TcpListener extends Listener {
+  accept(): Promise<TcpConn>;
+
+  [Symbol.asyncIterator](): AsyncIterableIterator<TcpConn>;
+}
+
 export class DatagramImpl implements DatagramConn {
   constructor(
     readonly rid: number,
@@ -112,6 +166,38 @@ export class DatagramImpl implements DatagramConn {
     close(this.rid);
   }
 
+  setBroadcast(broadcast = true): void {
+    netOps.setUDPOption(this.rid, { broadcast });
+  }
+
+  setTTL(ttl: number): void {
+    netOps.setUDPOption(this.rid, { ttl });
+  }
+
+  setMulticastTTL(ttl: number): void {
+    netOps.setUDPOption(this.rid, { multicastTtl: ttl });
+  }
+
+  setMulticastLoopback(loopback = true): void {
+    netOps.setUDPOption(this.rid, { multicastLoopback: loopback });
+  }
+
+  joinMulticast(multiaddr: string, networkInterface?: string | number): void {
+    netOps.joinMulticast(
+      this.rid,
+      multiaddr,
+      networkInterface === undefined ? undefined : String(networkInterface)
+    );
+  }
+
+  leaveMulticast(multiaddr: string, networkInterface?: string | number): void {
+    netOps.leaveMulticast(
+      this.rid,
+      multiaddr,
+      networkInterface === undefined ? undefined : String(networkInterface)
+    );
+  }
+
   async *[Symbol.asyncIterator](): AsyncIterableIterator<[Uint8Array, Addr]> {
     while (true) {
       try {
@@ -134,7 +220,15 @@ export interface Conn extends Reader, Writer, Closer {
   closeWrite(): void;
 }
 
-export interface ListenOptions {
+export interface TcpConn extends Conn {
+  setNoDelay(noDelay?: boolean): void;
+  setKeepAlive(keepAlive?: boolean, idleTime?: number, interval?: number): void;
+  setSendBufferSize(size: number): void;
+  setRecvBufferSize(size: number): void;
+  setTTL(ttl: number): void;
+}
+
+export interface ListenOptions extends ListenSocketOptions {
   port: number;
   hostname?: string;
   transport?: "tcp" | "udp";
@@ -147,7 +241,7 @@ export interface UnixListenOptions {
 
 export function listen(
   options: ListenOptions & { transport?: "tcp" }
-): Listener;
+): TcpListener;
 export function listen(
   options: UnixListenOptions & { transport: "unix" }
 ): Listener;
@@ -193,21 +287,139 @@ export interface UnixConnectOptions {
   address: string;
 }
 export async function connect(options: UnixConnectOptions): Promise<Conn>;
-export async function connect(options: ConnectOptions): Promise<Conn>;
+export async function connect(options: ConnectOptions): Promise<TcpConn>;
+export async function connect(
+  options: ConnectOptions | UnixConnectOptions
+): Promise<Conn>;
 export async function connect(
   options: ConnectOptions | UnixConnectOptions
 ): Promise<Conn> {
-  let res;
-
   if (options.transport === "unix") {
-    res = await netOps.connect(options);
-  } else {
-    res = await netOps.connect({
-      transport: "tcp",
-      hostname: "127.0.0.1",
-      ...options,
-    });
+    const res = await netOps.connect(options);
+    return new ConnImpl(res.rid, res.remoteAddr!, res.localAddr!);
   }
 
-  return new ConnImpl(res.rid, res.remoteAddr!, res.localAddr!);
+  const res = await netOps.connect({
+    transport: "tcp",
+    hostname: "127.0.0.1",
+    ...options,
+  });
+  return new TcpConnImpl(res.rid, res.remoteAddr!, res.localAddr!);
+}
+
+export type RecordType = "A" | "AAAA" | "CNAME" | "MX" | "NS" | "SRV" | "TXT";
+
+export interface ResolveDnsOptions {
+  nameServer?: {
+    ipAddr: string;
+    port?: number;
+  };
+}
+
+export interface MXRecord {
+  preference: number;
+  exchange: string;
+}
+
+export interface SRVRecord {
+  priority: number;
+  weight: number;
+  port: number;
+  target: string;
+}
+
+export function resolveDns(
+  query: string,
+  recordType: "A" | "AAAA" | "CNAME" | "NS",
+  options?: ResolveDnsOptions
+): Promise<string[]>;
+export function resolveDns(
+  query: string,
+  recordType: "MX",
+  options?: ResolveDnsOptions
+): Promise<MXRecord[]>;
+export function resolveDns(
+  query: string,
+  recordType: "SRV",
+  options?: ResolveDnsOptions
+): Promise<SRVRecord[]>;
+export function resolveDns(
+  query: string,
+  recordType: "TXT",
+  options?: ResolveDnsOptions
+): Promise<string[][]>;
+export function resolveDns(
+  query: string,
+  recordType: RecordType,
+  options: ResolveDnsOptions = {}
+): Promise<unknown[]> {
+  return netOps.resolveDns({
+    query,
+    recordType,
+    nameServer: options.nameServer,
+  });
+}
+
+export type ReceivedResource =
+  | { kind: "file"; resource: File }
+  | { kind: "conn"; resource: Conn }
+  | { kind: "listener"; resource: Listener }
+  | { kind: "datagram"; resource: DatagramConn };
+
+export interface ReceivedMessage {
+  data: Uint8Array;
+  resources: ReceivedResource[];
+}
+
+export function sendResources(
+  rid: number,
+  rids: number[],
+  data: Uint8Array = new Uint8Array(1)
+): Promise<number> {
+  return netOps.sendResources(rid, rids, data);
+}
+
+function receivedResource(
+  info: netOps.ReceivedResourceInfo
+): ReceivedResource {
+  switch (info.kind) {
+    case "file":
+      return { kind: "file", resource: new File(info.rid) };
+    case "tcpStream":
+      return {
+        kind: "conn",
+        resource: new TcpConnImpl(info.rid, info.remoteAddr!, info.localAddr!),
+      };
+    case "unixStream":
+      return {
+        kind: "conn",
+        resource: new ConnImpl(info.rid, info.remoteAddr!, info.localAddr!),
+      };
+    case "tcpListener":
+    case "unixListener":
+      return {
+        kind: "listener",
+        resource: new ListenerImpl(info.rid, info.localAddr!),
+      };
+    case "udpSocket":
+    case "unixDatagram":
+      return {
+        kind: "datagram",
+        resource: new DatagramImpl(info.rid, info.localAddr!),
+      };
+  }
+}
+
+export async function receiveResources(
+  rid: number,
+  p: Uint8Array = new Uint8Array(1024)
+): Promise<ReceivedMessage | EOF> {
+  const { size, resources } = await netOps.receiveResources(rid, p);
+  if (size === 0 && resources.length === 0) {
+    return EOF;
+  }
+  return {
+    data: p.subarray(0, size),
+    resources: resources.map(receivedResource),
+  };
 }
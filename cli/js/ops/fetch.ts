@@ -1,11 +1,14 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 
-import { sendAsync } from "./dispatch_json.ts";
+import { sendAsync, sendSync } from "./dispatch_json.ts";
 
 interface FetchRequest {
   url: string;
   method: string | null;
   headers: Array<[string, string]>;
+  bodyRid?: number;
+  cancelRid?: number;
+  clientRid?: number;
 }
 
 export interface FetchResponse {
@@ -26,3 +29,37 @@ export function fetch(
 
   return sendAsync("op_fetch", args, zeroCopy);
 }
+
+export interface RequestBodyPipe {
+  writerRid: number;
+  readerRid: number;
+}
+
+export function createRequestBody(): RequestBodyPipe {
+  return sendSync("op_create_request_body");
+}
+
+export function createFetchCancelHandle(): number {
+  return sendSync("op_create_fetch_cancel_handle");
+}
+
+export function cancelFetch(rid: number, reason: "abort" | "timeout"): void {
+  sendSync("op_cancel_fetch", { rid, reason });
+}
+
+export interface CreateHttpClientOptions {
+  caFile?: string;
+  caData?: string;
+  certChain?: string;
+  privateKey?: string;
+  proxy?: string;
+  timeout?: number;
+  connectTimeout?: number;
+  poolMaxIdlePerHost?: number;
+  poolIdleTimeout?: number;
+  http2PriorKnowledge?: boolean;
+}
+
+export function createHttpClient(options: CreateHttpClientOptions): number {
+  return sendSync("op_create_http_client", options);
+}
@@ -0,0 +1,40 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+import { sendAsync, sendSync } from "./dispatch_json.ts";
+
+interface HttpStartResponse {
+  rid: number;
+}
+
+export function startHttp(rid: number): HttpStartResponse {
+  return sendSync("op_http_start", { rid });
+}
+
+export interface HttpRequestResponse {
+  rid: number;
+  method: string;
+  url: string;
+  headers: Array<[string, string]>;
+  bodyRid: number | null;
+}
+
+export function nextRequest(rid: number): Promise<HttpRequestResponse | null> {
+  return sendAsync("op_http_next_request", { rid });
+}
+
+export interface HttpRespondRequest {
+  rid: number;
+  status: number;
+  headers: Array<[string, string]>;
+  stream: boolean;
+}
+
+interface HttpRespondResponse {
+  bodyRid: number | null;
+}
+
+export function respond(
+  args: HttpRespondRequest,
+  body?: Uint8Array
+): HttpRespondResponse {
+  return sendSync("op_http_respond", args, body);
+}
@@ -39,7 +39,14 @@ export function accept(
   return sendAsync("op_accept", { rid, transport });
 }
 
-export type ListenRequest = Addr;
+export interface ListenSocketOptions {
+  reuseAddress?: boolean;
+  reusePort?: boolean;
+  sendBufferSize?: number;
+  recvBufferSize?: number;
+}
+
+export type ListenRequest = Addr & ListenSocketOptions;
 
 interface ListenResponse {
   rid: number;
@@ -85,3 +92,106 @@ export async function send(
 ): Promise<void> {
   await sendAsync("op_send", args, zeroCopy);
 }
+
+export type TCPOption =
+  | { noDelay: boolean }
+  | { keepAlive: { idleTime: number; interval?: number } | null }
+  | { sendBufferSize: number }
+  | { recvBufferSize: number }
+  | { ttl: number };
+
+export function setTCPOption(rid: number, option: TCPOption): void {
+  sendSync("op_set_tcp_option", { rid, option });
+}
+
+export type UDPOption =
+  | { broadcast: boolean }
+  | { ttl: number }
+  | { multicastTtl: number }
+  | { multicastLoopback: boolean };
+
+export function setUDPOption(rid: number, option: UDPOption): void {
+  sendSync("op_set_udp_option", { rid, option });
+}
+
+export function joinMulticast(
+  rid: number,
+  multiaddr: string,
+  networkInterface?: string
+): void {
+  sendSync("op_join_multicast", { rid, multiaddr, networkInterface });
+}
+
+export function leaveMulticast(
+  rid: number,
+  multiaddr: string,
+  networkInterface?: string
+): void {
+  sendSync("op_leave_multicast", { rid, multiaddr, networkInterface });
+}
+
+interface SendResourcesResponse {
+  size: number;
+}
+
+export async function sendResources(
+  rid: number,
+  rids: number[],
+  zeroCopy: Uint8Array
+): Promise<number> {
+  const res: SendResourcesResponse = await sendAsync(
+    "op_send_resources",
+    { rid, rids },
+    zeroCopy
+  );
+  return res.size;
+}
+
+export interface ReceivedResourceInfo {
+  rid: number;
+  kind:
+    | "file"
+    | "tcpListener"
+    | "tcpStream"
+    | "udpSocket"
+    | "unixListener"
+    | "unixStream"
+    | "unixDatagram";
+  localAddr?: Addr;
+  remoteAddr?: Addr;
+}
+
+interface ReceiveResourcesResponse {
+  size: number;
+  resources: ReceivedResourceInfo[];
+}
+
+export function receiveResources(
+  rid: number,
+  zeroCopy: Uint8Array
+): Promise<ReceiveResourcesResponse> {
+  return sendAsync("op_receive_resources", { rid }, zeroCopy);
+}
+
+export interface PeerCredentials {
+  pid: number | null;
+  uid: number;
+  gid: number;
+}
+
+export function getPeerCredentials(rid: number): PeerCredentials {
+  return sendSync("op_peer_credentials", { rid });
+}
+
+export interface ResolveDnsRequest {
+  query: string;
+  recordType: string;
+  nameServer?: {
+    ipAddr: string;
+    port?: number;
+  };
+}
+
+export function resolveDns(args: ResolveDnsRequest): Promise<unknown[]> {
+  return sendAsync("op_resolve_dns", args);
+}
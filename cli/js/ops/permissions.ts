@@ -9,6 +9,8 @@ interface PermissionRequest {
   name: string;
   url?: string;
   path?: string;
+  variable?: string;
+  command?: string;
 }
 
 export function query(desc: PermissionRequest): PermissionResponse {
@@ -1,11 +1,28 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 import { sendAsync, sendSync } from "./dispatch_json.ts";
+import { ListenSocketOptions } from "./net.ts";
 
-export interface ConnectTLSRequest {
+/** The inline PEM or PKCS#12 alternatives to a certificate and key file. */
+export interface TLSKeyPairOptions {
+  certChain?: string;
+  privateKey?: string;
+  pkcs12File?: string;
+  passphrase?: string;
+}
+
+export interface SNICertificate extends TLSKeyPairOptions {
+  certFile?: string;
+  keyFile?: string;
+}
+
+export interface ConnectTLSRequest extends TLSKeyPairOptions {
   transport: "tcp";
   hostname: string;
   port: number;
   certFile?: string;
+  clientCertFile?: string;
+  clientKeyFile?: string;
+  alpnProtocols?: string[];
 }
 
 interface EstablishTLSResponse {
@@ -20,6 +37,8 @@ interface EstablishTLSResponse {
     port: number;
     transport: "tcp";
   };
+  peerCertificates: string[];
+  alpnProtocol: string | null;
 }
 
 export function connectTLS(
@@ -40,18 +59,26 @@ interface AcceptTLSResponse {
     port: number;
     transport: "tcp";
   };
+  peerCertificates: string[];
+  alpnProtocol: string | null;
 }
 
 export function acceptTLS(rid: number): Promise<AcceptTLSResponse> {
   return sendAsync("op_accept_tls", { rid });
 }
 
-export interface ListenTLSRequest {
+export interface ListenTLSRequest
+  extends TLSKeyPairOptions,
+    ListenSocketOptions {
   port: number;
   hostname: string;
   transport: "tcp";
-  certFile: string;
-  keyFile: string;
+  certFile?: string;
+  keyFile?: string;
+  clientAuth?: "none" | "optional" | "required";
+  caFile?: string;
+  alpnProtocols?: string[];
+  sniCertificates?: Record<string, SNICertificate>;
 }
 
 interface ListenTLSResponse {
@@ -67,12 +94,53 @@ export function listenTLS(args: ListenTLSRequest): ListenTLSResponse {
   return sendSync("op_listen_tls", args);
 }
 
-export interface StartTLSRequest {
+export interface StartTLSRequest extends TLSKeyPairOptions {
   rid: number;
   hostname: string;
   certFile?: string;
+  clientCertFile?: string;
+  clientKeyFile?: string;
+  alpnProtocols?: string[];
 }
 
 export function startTLS(args: StartTLSRequest): Promise<EstablishTLSResponse> {
   return sendAsync("op_start_tls", args);
 }
+
+export interface StartTLSServerRequest extends TLSKeyPairOptions {
+  rid: number;
+  certFile?: string;
+  keyFile?: string;
+  clientAuth?: "none" | "optional" | "required";
+  caFile?: string;
+  alpnProtocols?: string[];
+  sniCertificates?: Record<string, SNICertificate>;
+}
+
+export function startTLSServer(
+  args: StartTLSServerRequest
+): Promise<EstablishTLSResponse> {
+  return sendAsync("op_start_tls_server", args);
+}
+
+export interface HandshakeInfoResponse {
+  protocol: string | null;
+  cipherSuite: string | null;
+  alpnProtocol: string | null;
+  peerCertificates: Array<{
+    der: number[];
+    pem: string;
+    fingerprint256: string;
+    parseError: string | null;
+    subject: string | null;
+    issuer: string | null;
+    subjectAltNames: string[] | null;
+    serialNumber: string | null;
+    notBefore: number | null;
+    notAfter: number | null;
+  }>;
+}
+
+export function handshakeInfo(rid: number): HandshakeInfoResponse {
+  return sendSync("op_tls_handshake_info", { rid });
+}
@@ -0,0 +1,65 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+import { sendAsync, sendSync } from "./dispatch_json.ts";
+
+export function checkPermission(url: string): void {
+  sendSync("op_ws_check_permission", { url });
+}
+
+interface CreateResponse {
+  rid: number;
+  protocol: string;
+}
+
+export function create(
+  url: string,
+  protocols: string[]
+): Promise<CreateResponse> {
+  return sendAsync("op_ws_create", { url, protocols });
+}
+
+export interface AcceptResponse {
+  rid: number;
+  url: string;
+  protocol: string;
+  headers: Array<[string, string]>;
+}
+
+export function accept(
+  rid: number,
+  protocols: string[]
+): Promise<AcceptResponse> {
+  return sendAsync("op_ws_accept", { rid, protocols });
+}
+
+export type MessageKind = "text" | "binary" | "ping" | "pong";
+
+export async function send(
+  rid: number,
+  kind: MessageKind,
+  data: string | Uint8Array
+): Promise<void> {
+  if (typeof data === "string") {
+    await sendAsync("op_ws_send", { rid, kind, text: data });
+  } else {
+    await sendAsync("op_ws_send", { rid, kind }, data);
+  }
+}
+
+export async function close(
+  rid: number,
+  code?: number,
+  reason?: string
+): Promise<void> {
+  await sendAsync("op_ws_close", { rid, code, reason });
+}
+
+export type WebSocketEvent =
+  | { kind: "string"; data: string }
+  | { kind: "binary"; data: number[] }
+  | { kind: "ping" | "pong" }
+  | { kind: "close"; code: number; reason: string }
+  | { kind: "error"; message: string };
+
+export function nextEvent(rid: number): Promise<WebSocketEvent> {
+  return sendAsync("op_ws_next_event", { rid });
+}
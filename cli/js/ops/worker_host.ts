@@ -2,12 +2,23 @@
 /* eslint-disable @typescript-eslint/no-explicit-any */
 import { sendAsync, sendSync } from "./dispatch_json.ts";
 
+export interface WorkerPermissions {
+  read?: boolean | string[];
+  write?: boolean | string[];
+  net?: boolean | string[];
+  env?: boolean | string[];
+  run?: boolean | string[];
+  plugin?: boolean;
+  hrtime?: boolean;
+}
+
 export function createWorker(
   specifier: string,
   hasSourceCode: boolean,
   sourceCode: string,
   useDenoNamespace: boolean,
-  name?: string
+  name?: string,
+  permissions?: WorkerPermissions
 ): { id: number } {
   return sendSync("op_create_worker", {
     specifier,
@@ -15,6 +26,7 @@ export function createWorker(
     sourceCode,
     name,
     useDenoNamespace,
+    permissions,
   });
 }
 
@@ -15,6 +15,7 @@ export type PermissionState = "granted" | "denied" | "prompt";
 
 interface RunPermissionDescriptor {
   name: "run";
+  command?: string;
 }
 interface ReadWritePermissionDescriptor {
   name: "read" | "write";
@@ -26,6 +27,7 @@ interface NetPermissionDescriptor {
 }
 interface EnvPermissionDescriptor {
   name: "env";
+  variable?: string;
 }
 interface PluginPermissionDescriptor {
   name: "plugin";
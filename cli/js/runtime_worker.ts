@@ -21,7 +21,11 @@ import * as Deno from "./deno.ts";
 import * as webWorkerOps from "./ops/web_worker.ts";
 import { LocationImpl } from "./web/location.ts";
 import { log, assert, immutableDefine } from "./util.ts";
-import { MessageEvent, ErrorEvent } from "./web/workers.ts";
+import {
+  MessageEvent,
+  ErrorEvent,
+  setMessageTimeStamp,
+} from "./web/workers.ts";
 import { TextEncoder } from "./web/text_encoding.ts";
 import * as runtime from "./runtime.ts";
 import { internalObject } from "./internals.ts";
@@ -60,11 +64,17 @@ export function close(): void {
   webWorkerOps.close();
 }
 
-export async function workerMessageRecvCallback(data: string): Promise<void> {
+export async function workerMessageRecvCallback(
+  data: string,
+  timeStamp?: number
+): Promise<void> {
   const msgEvent = new MessageEvent("message", {
     cancelable: false,
     data,
   });
+  if (timeStamp !== undefined) {
+    setMessageTimeStamp(msgEvent, timeStamp);
+  }
 
   try {
     if (globalThis["onmessage"]) {
@@ -0,0 +1,185 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+import { assert, assertEquals, unitTest } from "./test_util.ts";
+
+const encoder = new TextEncoder();
+const decoder = new TextDecoder();
+
+function u16(n: number): number[] {
+  return [n >> 8, n & 0xff];
+}
+
+function encodeName(name: string): number[] {
+  const bytes: number[] = [];
+  for (const label of name.split(".")) {
+    const encoded = encoder.encode(label);
+    bytes.push(encoded.length, ...encoded);
+  }
+  bytes.push(0);
+  return bytes;
+}
+
+function encodeText(text: string): number[] {
+  const encoded = encoder.encode(text);
+  return [encoded.length, ...encoded];
+}
+
+/** The record data served for "example.test", by record type. */
+const records: Record<number, number[][]> = {
+  // A
+  1: [
+    [127, 0, 0, 1],
+    [127, 0, 0, 2],
+  ],
+  // NS
+  2: [encodeName("ns1.example.test")],
+  // CNAME
+  5: [encodeName("alias.example.test")],
+  // MX
+  15: [[...u16(10), ...encodeName("mail.example.test")]],
+  // TXT
+  16: [[...encodeText("hello"), ...encodeText("world")]],
+  // AAAA
+  28: [[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]],
+  // SRV
+  33: [[...u16(1), ...u16(2), ...u16(5060), ...encodeName("sip.example.test")]],
+};
+
+/** A stand-in name server, which answers `count` queries for
+ * "example.test", and those for any other name with NXDOMAIN. */
+async function serveDns(
+  socket: Deno.DatagramConn,
+  count: number
+): Promise<void> {
+  for (let i = 0; i < count; i++) {
+    const [query, addr] = await socket.receive();
+    // The question follows the 12 byte header.
+    let end = 12;
+    const labels: string[] = [];
+    while (query[end] !== 0) {
+      const label = query.subarray(end + 1, end + 1 + query[end]);
+      labels.push(decoder.decode(label));
+      end += query[end] + 1;
+    }
+    const type = (query[end + 1] << 8) | query[end + 2];
+    // The root label, type and class.
+    end += 5;
+
+    const found = labels.join(".").toLowerCase() === "example.test";
+    const answers = found ? records[type] ?? [] : [];
+    const response = [
+      // ID, QR and RD, RA and RCODE (0 or 3, NXDOMAIN).
+      query[0],
+      query[1],
+      0x81,
+      found ? 0x80 : 0x83,
+      ...u16(1),
+      ...u16(answers.length),
+      ...u16(0),
+      ...u16(0),
+      ...query.subarray(12, end),
+    ];
+    for (const data of answers) {
+      // A pointer to the question's name, type, class IN and a TTL of 60.
+      response.push(0xc0, 12, ...u16(type), ...u16(1), 0, 0, 0, 60);
+      response.push(...u16(data.length), ...data);
+    }
+    await socket.send(new Uint8Array(response), addr);
+  }
+}
+
+const nameServer = { ipAddr: "127.0.0.1", port: 4553 };
+
+unitTest(async function resolveDnsPerm(): Promise<void> {
+  let err;
+  try {
+    await Deno.resolveDns("example.test", "A", { nameServer });
+  } catch (e) {
+    err = e;
+  }
+  assert(err instanceof Deno.errors.PermissionDenied);
+});
+
+unitTest(
+  { perms: { net: true } },
+  async function resolveDnsRecords(): Promise<void> {
+    const socket = Deno.listen({
+      hostname: nameServer.ipAddr,
+      port: nameServer.port,
+      transport: "udp",
+    });
+    const server = serveDns(socket, 7);
+    const options = { nameServer };
+
+    assertEquals(await Deno.resolveDns("example.test", "A", options), [
+      "127.0.0.1",
+      "127.0.0.2",
+    ]);
+    assertEquals(await Deno.resolveDns("example.test", "AAAA", options), [
+      "::1",
+    ]);
+    assertEquals(await Deno.resolveDns("example.test", "CNAME", options), [
+      "alias.example.test.",
+    ]);
+    assertEquals(await Deno.resolveDns("example.test", "NS", options), [
+      "ns1.example.test.",
+    ]);
+    assertEquals(await Deno.resolveDns("example.test", "MX", options), [
+      { preference: 10, exchange: "mail.example.test." },
+    ]);
+    assertEquals(await Deno.resolveDns("example.test", "TXT", options), [
+      ["hello", "world"],
+    ]);
+    assertEquals(await Deno.resolveDns("example.test", "SRV", options), [
+      { priority: 1, weight: 2, port: 5060, target: "sip.example.test." },
+    ]);
+
+    await server;
+    socket.close();
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function resolveDnsNotFound(): Promise<void> {
+    const socket = Deno.listen({
+      hostname: nameServer.ipAddr,
+      port: nameServer.port,
+      transport: "udp",
+    });
+    const server = serveDns(socket, 1);
+
+    let err;
+    try {
+      await Deno.resolveDns("missing.test", "A", { nameServer });
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof Deno.errors.NotFound);
+
+    await server;
+    socket.close();
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function resolveDnsInvalidArguments(): Promise<void> {
+    let err;
+    try {
+      await Deno.resolveDns("example.test", "PTR" as "A", { nameServer });
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof TypeError);
+
+    err = undefined;
+    try {
+      await Deno.resolveDns("example.test", "A", {
+        nameServer: { ipAddr: "not an ip" },
+      });
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof TypeError);
+  }
+);
@@ -13,7 +13,7 @@ unitTest({ perms: { net: true } }, async function fetchProtocolError(): Promise<
 > {
   let err;
   try {
-    await fetch("file:///");
+    await fetch("ftp://localhost:4545/");
   } catch (err_) {
     err = err_;
   }
@@ -21,6 +21,83 @@ unitTest({ perms: { net: true } }, async function fetchProtocolError(): Promise<
   assertStrContains(err.message, "not supported");
 });
 
+unitTest({ perms: { read: true } }, async function fetchFile(): Promise<
+  void
+> {
+  const response = await fetch(
+    new URL("../../tests/fixture.json", import.meta.url)
+  );
+  assertEquals(response.status, 200);
+  assertEquals(response.headers.get("content-type"), "application/json");
+  const json = await response.json();
+  assertEquals(json.name, "deno");
+});
+
+unitTest(async function fetchFilePerm(): Promise<void> {
+  let err;
+  try {
+    await fetch(new URL("../../tests/fixture.json", import.meta.url));
+  } catch (err_) {
+    err = err_;
+  }
+  assert(err instanceof Deno.errors.PermissionDenied);
+  assertEquals(err.name, "PermissionDenied");
+});
+
+unitTest({ perms: { read: true } }, async function fetchFileNotFound(): Promise<
+  void
+> {
+  let err;
+  try {
+    await fetch(new URL("../../tests/not_found.json", import.meta.url));
+  } catch (err_) {
+    err = err_;
+  }
+  assert(err instanceof Deno.errors.NotFound);
+});
+
+unitTest({ perms: { read: true } }, async function fetchFilePost(): Promise<
+  void
+> {
+  let err;
+  try {
+    await fetch(new URL("../../tests/fixture.json", import.meta.url), {
+      method: "POST",
+      body: "hello",
+    });
+  } catch (err_) {
+    err = err_;
+  }
+  assert(err instanceof TypeError);
+  assertStrContains(err.message, "not supported");
+});
+
+unitTest(async function fetchDataUrl(): Promise<void> {
+  const response = await fetch("data:,Hello%2C%20World!");
+  assertEquals(response.status, 200);
+  assertEquals(
+    response.headers.get("content-type"),
+    "text/plain;charset=US-ASCII"
+  );
+  assertEquals(await response.text(), "Hello, World!");
+});
+
+unitTest(async function fetchDataUrlBase64(): Promise<void> {
+  const response = await fetch("data:application/json;base64,eyJhIjoxfQ==");
+  assertEquals(response.headers.get("content-type"), "application/json");
+  assertEquals(await response.json(), { a: 1 });
+});
+
+unitTest(async function fetchDataUrlInvalid(): Promise<void> {
+  let err;
+  try {
+    await fetch("data:text/plain;base64");
+  } catch (err_) {
+    err = err_;
+  }
+  assert(err instanceof TypeError);
+});
+
 unitTest(
   { perms: { net: true } },
   async function fetchConnectionError(): Promise<void> {
@@ -307,6 +384,107 @@ unitTest(
   }
 );
 
+unitTest(
+  { perms: { net: true } },
+  async function fetchInitReadableStreamBody(): Promise<void> {
+    const encoder = new TextEncoder();
+    const stream = new ReadableStream({
+      start(controller): void {
+        controller.enqueue(encoder.encode("hello "));
+        controller.enqueue(encoder.encode("world"));
+        controller.close();
+      },
+    });
+    const response = await fetch("http://localhost:4545/echo_server", {
+      method: "POST",
+      body: stream,
+    });
+    const text = await response.text();
+    assertEquals(text, "hello world");
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function fetchInitReadableStreamBodyError(): Promise<void> {
+    const stream = new ReadableStream({
+      start(controller): void {
+        controller.enqueue(new TextEncoder().encode("hello"));
+        controller.error(new Error("stream failed"));
+      },
+    });
+    let err;
+    try {
+      await fetch("http://localhost:4545/echo_server", {
+        method: "POST",
+        body: stream,
+      });
+    } catch (err_) {
+      err = err_;
+    }
+    assert(err instanceof Error);
+    assertEquals(err.message, "stream failed");
+  }
+);
+
+unitTest(
+  { perms: { read: true, net: true } },
+  async function fetchInitFileBody(): Promise<void> {
+    const data = await Deno.readFile("cli/tests/fixture.json");
+    const file = await Deno.open("cli/tests/fixture.json");
+    const response = await fetch("http://localhost:4545/echo_server", {
+      method: "POST",
+      body: (file as unknown) as BodyInit,
+    });
+    const body = new Uint8Array(await response.arrayBuffer());
+    assertEquals(body, data);
+    // The file is closed once it has been sent.
+    assert(!(file.rid in Deno.resources()));
+  }
+);
+
+unitTest(
+  { perms: { read: true } },
+  async function fetchInitFileBodyPerm(): Promise<void> {
+    const file = await Deno.open("cli/tests/fixture.json");
+    let err;
+    try {
+      await fetch("http://localhost:4545/echo_server", {
+        method: "POST",
+        body: (file as unknown) as BodyInit,
+      });
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof Deno.errors.PermissionDenied);
+    // A rejected request leaves the file to the caller.
+    assert(file.rid in Deno.resources());
+    file.close();
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function fetchStreamingBodyRedirect(): Promise<void> {
+    const stream = new ReadableStream({
+      start(controller): void {
+        controller.enqueue(new TextEncoder().encode("hello"));
+        controller.close();
+      },
+    });
+    let err;
+    try {
+      await fetch("http://localhost:4546/echo_server", {
+        method: "POST",
+        body: stream,
+      }); // will redirect to http://localhost:4545/echo_server
+    } catch (err_) {
+      err = err_;
+    }
+    assert(err instanceof TypeError);
+  }
+);
+
 unitTest({ perms: { net: true } }, async function fetchUserAgent(): Promise<
   void
 > {
@@ -510,6 +688,175 @@ unitTest(
   }
 );
 
+/** A server that sends `head` on its first connection, and then stalls. */
+function stalledServer(
+  addr: string,
+  head: string
+): { listener: Deno.Listener; conn: Promise<Deno.Conn> } {
+  const [hostname, port] = addr.split(":");
+  const listener = Deno.listen({ hostname, port: Number(port) });
+  const conn = listener.accept().then(
+    async (conn: Deno.Conn): Promise<Deno.Conn> => {
+      await conn.write(new TextEncoder().encode(head));
+      return conn;
+    }
+  );
+  return { listener, conn };
+}
+
+unitTest(
+  { perms: { net: true } },
+  async function fetchAbortBeforeResponse(): Promise<void> {
+    const addr = "127.0.0.1:4501";
+    const server = stalledServer(addr, "");
+    const controller = new AbortController();
+    const fetchPromise = fetch(`http://${addr}/`, {
+      signal: controller.signal,
+    });
+    const conn = await server.conn;
+    controller.abort();
+    let err;
+    try {
+      await fetchPromise;
+    } catch (err_) {
+      err = err_;
+    }
+    assert(err instanceof DOMException);
+    assertEquals(err.name, "AbortError");
+    conn.close();
+    server.listener.close();
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function fetchAbortBodyRead(): Promise<void> {
+    const addr = "127.0.0.1:4501";
+    const server = stalledServer(
+      addr,
+      "HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n5\r\nhello\r\n"
+    );
+    const controller = new AbortController();
+    const response = await fetch(`http://${addr}/`, {
+      signal: controller.signal,
+    });
+    assertEquals(response.status, 200);
+    const text = response.text();
+    controller.abort();
+    let err;
+    try {
+      await text;
+    } catch (err_) {
+      err = err_;
+    }
+    assert(err instanceof DOMException);
+    assertEquals(err.name, "AbortError");
+    (await server.conn).close();
+    server.listener.close();
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function fetchAlreadyAborted(): Promise<void> {
+    const controller = new AbortController();
+    controller.abort();
+    let err;
+    try {
+      await fetch("http://localhost:4545/cli/tests/fixture.json", {
+        signal: controller.signal,
+      });
+    } catch (err_) {
+      err = err_;
+    }
+    assert(err instanceof DOMException);
+    assertEquals(err.name, "AbortError");
+  }
+);
+
+unitTest({ perms: { net: true } }, async function fetchTimeout(): Promise<
+  void
+> {
+  const addr = "127.0.0.1:4501";
+  const server = stalledServer(addr, "");
+  let err;
+  try {
+    await fetch(`http://${addr}/`, { timeout: 100 });
+  } catch (err_) {
+    err = err_;
+  }
+  assert(err instanceof Deno.errors.TimedOut);
+  (await server.conn).close();
+  server.listener.close();
+});
+
+unitTest(
+  { perms: { net: true } },
+  async function fetchTimeoutNotReached(): Promise<void> {
+    const response = await fetch(
+      "http://localhost:4545/cli/tests/fixture.json",
+      { timeout: 10000 }
+    );
+    const json = await response.json();
+    assertEquals(json.name, "deno");
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function fetchWithHttpClient(): Promise<void> {
+    const client = Deno.createHttpClient();
+    for (let i = 0; i < 2; i++) {
+      const response = await fetch(
+        "http://localhost:4545/cli/tests/fixture.json",
+        { client }
+      );
+      const json = await response.json();
+      assertEquals(json.name, "deno");
+    }
+    client.close();
+  }
+);
+
+unitTest(
+  { perms: { net: true, read: true } },
+  async function fetchWithHttpClientCaFile(): Promise<void> {
+    const client = Deno.createHttpClient({
+      caFile: "cli/tests/tls/RootCA.pem",
+    });
+    const response = await fetch(
+      "https://localhost:5545/cli/tests/fixture.json",
+      { client }
+    );
+    const json = await response.json();
+    assertEquals(json.name, "deno");
+    client.close();
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  function createHttpClientCaFilePerm(): void {
+    let err;
+    try {
+      Deno.createHttpClient({ caFile: "cli/tests/tls/RootCA.pem" });
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof Deno.errors.PermissionDenied);
+  }
+);
+
+unitTest(function createHttpClientProxyPerm(): void {
+  let err;
+  try {
+    Deno.createHttpClient({ proxy: "http://localhost:4555" });
+  } catch (e) {
+    err = e;
+  }
+  assert(err instanceof Deno.errors.PermissionDenied);
+});
+
 unitTest(function responseRedirect(): void {
   const redir = Response.redirect("example.com/newLocation", 301);
   assertEquals(redir.status, 301);
@@ -0,0 +1,170 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+import { assert, assertEquals, unitTest } from "./test_util.ts";
+import { BufReader, BufWriter } from "../../../std/io/bufio.ts";
+import { TextProtoReader } from "../../../std/textproto/mod.ts";
+
+const encoder = new TextEncoder();
+const decoder = new TextDecoder();
+
+unitTest({ perms: { net: true } }, async function httpServerBasic(): Promise<
+  void
+> {
+  const listener = Deno.listen({ port: 4501 });
+  const promise = (async (): Promise<void> => {
+    const conn = await listener.accept();
+    const httpConn = Deno.serveHttp(conn);
+    const event = await httpConn.nextRequest();
+    assert(event !== null);
+    const { request } = event;
+    assertEquals(request.method, "GET");
+    assertEquals(request.url, "http://127.0.0.1:4501/foo?bar=baz");
+    assertEquals(request.headers.get("x-test"), "hello");
+    assertEquals(request.body, null);
+    await event.respondWith({
+      status: 201,
+      headers: { "content-type": "text/plain" },
+      body: "Hello World",
+    });
+    // The client closes the connection after the response.
+    assertEquals(await httpConn.nextRequest(), null);
+    httpConn.close();
+  })();
+
+  const response = await fetch("http://127.0.0.1:4501/foo?bar=baz", {
+    headers: { "x-test": "hello", connection: "close" },
+  });
+  assertEquals(response.status, 201);
+  assertEquals(response.headers.get("content-type"), "text/plain");
+  assertEquals(await response.text(), "Hello World");
+  await promise;
+  listener.close();
+});
+
+unitTest(
+  { perms: { net: true } },
+  async function httpServerStreamingBodies(): Promise<void> {
+    const listener = Deno.listen({ port: 4501 });
+    const promise = (async (): Promise<void> => {
+      const conn = await listener.accept();
+      const httpConn = Deno.serveHttp(conn);
+      for await (const event of httpConn) {
+        const { request } = event;
+        assertEquals(request.method, "POST");
+        assert(request.body !== null);
+        const buf = new Deno.Buffer();
+        await buf.readFrom(request.body);
+        request.body.close();
+        assertEquals(decoder.decode(buf.bytes()), "ping");
+        const body = new Deno.Buffer(encoder.encode("pong".repeat(20000)));
+        await event.respondWith({ body });
+      }
+      httpConn.close();
+    })();
+
+    const response = await fetch("http://127.0.0.1:4501/", {
+      method: "POST",
+      headers: { connection: "close" },
+      body: "ping",
+    });
+    assertEquals(response.status, 200);
+    assertEquals(await response.text(), "pong".repeat(20000));
+    await promise;
+    listener.close();
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function httpServerLargeBodyWithoutNextRequest(): Promise<void> {
+    const listener = Deno.listen({ port: 4501 });
+    const body = new Uint8Array(8 * 1024 * 1024).fill(42);
+    let httpConn: Deno.HttpConn | undefined;
+    const promise = (async (): Promise<void> => {
+      const conn = await listener.accept();
+      httpConn = Deno.serveHttp(conn);
+      const event = await httpConn.nextRequest();
+      assert(event !== null);
+      // The connection keeps writing the response without another
+      // `nextRequest()` driving it.
+      await event.respondWith({ body });
+    })();
+
+    const response = await fetch("http://127.0.0.1:4501/");
+    assertEquals(response.status, 200);
+    const received = new Uint8Array(await response.arrayBuffer());
+    assertEquals(received.length, body.length);
+    assert(received.every((byte) => byte === 42));
+    await promise;
+    httpConn!.close();
+    listener.close();
+  }
+);
+
+unitTest(
+  { perms: { read: true, net: true } },
+  async function httpServerTls(): Promise<void> {
+    const hostname = "localhost";
+    const port = 4502;
+    const listener = Deno.listenTLS({
+      hostname,
+      port,
+      certFile: "cli/tests/tls/localhost.crt",
+      keyFile: "cli/tests/tls/localhost.key",
+    });
+    const promise = (async (): Promise<void> => {
+      const conn = await listener.accept();
+      const httpConn = Deno.serveHttp(conn);
+      for await (const event of httpConn) {
+        await event.respondWith({ body: event.request.url });
+      }
+      httpConn.close();
+    })();
+
+    const conn = await Deno.connectTLS({
+      hostname,
+      port,
+      certFile: "cli/tests/tls/RootCA.pem",
+    });
+    const w = new BufWriter(conn);
+    const r = new BufReader(conn);
+    const request = `GET /tls HTTP/1.1\r\nHost: ${hostname}:${port}\r\nConnection: close\r\n\r\n`;
+    await w.write(encoder.encode(request));
+    await w.flush();
+    const tpr = new TextProtoReader(r);
+    assertEquals(await tpr.readLine(), "HTTP/1.1 200 OK");
+    const headers = await tpr.readMIMEHeader();
+    assert(headers !== Deno.EOF);
+    const bodyBuf = new Uint8Array(parseInt(headers.get("content-length")!));
+    await r.readFull(bodyBuf);
+    assertEquals(decoder.decode(bodyBuf), `https://${hostname}:${port}/tls`);
+    conn.close();
+    await promise;
+    listener.close();
+  }
+);
+
+unitTest(
+  { ignore: Deno.build.os === "win", perms: { read: true, write: true } },
+  async function httpServerUnix(): Promise<void> {
+    const filePath = await Deno.makeTempFile();
+    const listener = Deno.listen({ address: filePath, transport: "unix" });
+    const promise = (async (): Promise<void> => {
+      const conn = await listener.accept();
+      const httpConn = Deno.serveHttp(conn);
+      for await (const event of httpConn) {
+        await event.respondWith({ body: event.request.url });
+      }
+      httpConn.close();
+    })();
+
+    const conn = await Deno.connect({ address: filePath, transport: "unix" });
+    const request = `GET /unix HTTP/1.1\r\nHost: deno.land\r\nConnection: close\r\n\r\n`;
+    await conn.write(encoder.encode(request));
+    const response = decoder.decode(await Deno.readAll(conn));
+    assert(response.startsWith("HTTP/1.1 200 OK\r\n"));
+    assert(response.endsWith("\r\n\r\nhttp://deno.land/unix"));
+    conn.close();
+    await promise;
+    listener.close();
+  }
+);
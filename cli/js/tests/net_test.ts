@@ -549,3 +549,263 @@ unitTest(
     await resolvable;
   }
 );
+
+unitTest(
+  { perms: { net: true } },
+  async function netTcpSocketOptions(): Promise<void> {
+    const listener = Deno.listen({
+      port: 4500,
+      sendBufferSize: 65536,
+      recvBufferSize: 65536,
+    });
+    const acceptedPromise = listener.accept();
+    const conn = await Deno.connect({ hostname: "127.0.0.1", port: 4500 });
+    const accepted = await acceptedPromise;
+
+    conn.setNoDelay();
+    conn.setNoDelay(false);
+    conn.setKeepAlive(true, 60000);
+    if (Deno.build.os === "linux") {
+      conn.setKeepAlive(true, 60000, 10000);
+    } else {
+      let err;
+      try {
+        conn.setKeepAlive(true, 60000, 10000);
+      } catch (e) {
+        err = e;
+      }
+      assert(err instanceof TypeError);
+    }
+    conn.setKeepAlive(false);
+    conn.setSendBufferSize(32768);
+    conn.setRecvBufferSize(32768);
+    conn.setTTL(32);
+    accepted.setNoDelay();
+
+    let err;
+    try {
+      conn.setKeepAlive(true, 500);
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof TypeError);
+
+    await conn.write(new Uint8Array([1, 2, 3]));
+    const buf = new Uint8Array(3);
+    assertEquals(await accepted.read(buf), 3);
+    assertEquals(buf, new Uint8Array([1, 2, 3]));
+
+    conn.close();
+    accepted.close();
+    listener.close();
+
+    err = undefined;
+    try {
+      conn.setNoDelay();
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof Deno.errors.BadResource);
+  }
+);
+
+unitTest(
+  { ignore: Deno.build.os === "win", perms: { net: true } },
+  function netTcpListenReusePort(): void {
+    const first = Deno.listen({ port: 4500, reusePort: true });
+    const second = Deno.listen({ port: 4500, reusePort: true });
+    assertEquals(second.addr.port, 4500);
+
+    let err;
+    try {
+      Deno.listen({ port: 4500 });
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof Deno.errors.AddrInUse);
+
+    first.close();
+    second.close();
+  }
+);
+
+unitTest(
+  { ignore: Deno.build.os === "win", perms: { net: true } },
+  function netUdpListenReuseAddress(): void {
+    const options = {
+      port: 4500,
+      transport: "udp" as const,
+      reuseAddress: true,
+      recvBufferSize: 65536,
+    };
+    const first = Deno.listen(options);
+    const second = Deno.listen(options);
+    assertEquals(second.addr.port, 4500);
+    first.close();
+    second.close();
+  }
+);
+
+unitTest(
+  { ignore: Deno.build.os === "win", perms: { net: true } },
+  async function netUdpSocketOptions(): Promise<void> {
+    const alice = Deno.listen({ port: 4500, transport: "udp" });
+    const bob = Deno.listen({ port: 4501, transport: "udp" });
+
+    alice.setBroadcast();
+    alice.setBroadcast(false);
+    alice.setTTL(32);
+    alice.setMulticastTTL(2);
+    alice.setMulticastLoopback(false);
+
+    let err;
+    try {
+      alice.joinMulticast("not an ip");
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof TypeError);
+
+    err = undefined;
+    try {
+      alice.joinMulticast("224.0.0.123", "not an ip");
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof TypeError);
+
+    await alice.send(new Uint8Array([1, 2, 3]), bob.addr);
+    const [recvd] = await bob.receive();
+    assertEquals(recvd, new Uint8Array([1, 2, 3]));
+
+    alice.close();
+    bob.close();
+  }
+);
+
+/** Connects to a Unix socket listening on a new temporary path. */
+async function unixConnPair(): Promise<[Deno.Conn, Deno.Conn, Deno.Listener]> {
+  const filePath = await Deno.makeTempFile();
+  const listener = Deno.listen({ address: filePath, transport: "unix" });
+  const acceptedPromise = listener.accept();
+  const conn = await Deno.connect({ address: filePath, transport: "unix" });
+  return [conn, await acceptedPromise, listener];
+}
+
+unitTest(
+  { ignore: Deno.build.os === "win", perms: { read: true, write: true } },
+  async function netUnixPeerCredentials(): Promise<void> {
+    const [conn, accepted, listener] = await unixConnPair();
+
+    for (const credentials of [
+      Deno.getPeerCredentials(conn.rid),
+      Deno.getPeerCredentials(accepted.rid),
+    ]) {
+      if (Deno.build.os === "linux") {
+        assertEquals(credentials.pid, Deno.pid);
+      } else {
+        assertEquals(credentials.pid, null);
+      }
+      assertEquals(typeof credentials.uid, "number");
+      assertEquals(typeof credentials.gid, "number");
+    }
+
+    conn.close();
+    accepted.close();
+    listener.close();
+
+    let err;
+    try {
+      Deno.getPeerCredentials(conn.rid);
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof Deno.errors.BadResource);
+  }
+);
+
+unitTest(
+  {
+    ignore: Deno.build.os === "win",
+    perms: { read: true, write: true, net: true },
+  },
+  async function netUnixSendResources(): Promise<void> {
+    const [conn, accepted, listener] = await unixConnPair();
+    const filePath = await Deno.makeTempFile();
+    await Deno.writeFile(filePath, new TextEncoder().encode("hello"));
+    const file = await Deno.open(filePath);
+    const tcpListener = Deno.listen({ port: 4500 });
+
+    const data = new Uint8Array([1, 2, 3]);
+    assertEquals(
+      await Deno.sendResources(conn.rid, [file.rid, tcpListener.rid], data),
+      3
+    );
+    file.close();
+    tcpListener.close();
+
+    const message = await Deno.receiveResources(accepted.rid);
+    assert(message !== Deno.EOF);
+    assertEquals(message.data, data);
+    assertEquals(message.resources.length, 2);
+
+    const [received, receivedListener] = message.resources;
+    assert(received.kind === "file");
+    const buf = new Uint8Array(5);
+    assertEquals(await received.resource.read(buf), 5);
+    assertEquals(new TextDecoder().decode(buf), "hello");
+    received.resource.close();
+
+    assert(receivedListener.kind === "listener");
+    assertEquals(receivedListener.resource.addr, {
+      hostname: "127.0.0.1",
+      port: 4500,
+      transport: "tcp",
+    });
+    const acceptedPromise = receivedListener.resource.accept();
+    const tcpConn = await Deno.connect({ port: 4500 });
+    (await acceptedPromise).close();
+    tcpConn.close();
+    receivedListener.resource.close();
+
+    conn.close();
+    assertEquals(await Deno.receiveResources(accepted.rid), Deno.EOF);
+    accepted.close();
+    listener.close();
+  }
+);
+
+unitTest(
+  { ignore: Deno.build.os === "win", perms: { read: true, write: true } },
+  async function netUnixSendResourcesErrors(): Promise<void> {
+    const [conn, accepted, listener] = await unixConnPair();
+
+    let err;
+    try {
+      await Deno.sendResources(conn.rid, [conn.rid], new Uint8Array());
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof TypeError);
+
+    err = undefined;
+    try {
+      await Deno.sendResources(conn.rid, [1234567]);
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof Deno.errors.BadResource);
+
+    err = undefined;
+    try {
+      await Deno.sendResources(listener.rid, []);
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof Deno.errors.BadResource);
+
+    conn.close();
+    accepted.close();
+    listener.close();
+  }
+);
@@ -1,5 +1,10 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
-import { unitTest, assert, createResolvable } from "./test_util.ts";
+import {
+  unitTest,
+  assert,
+  assertEquals,
+  createResolvable,
+} from "./test_util.ts";
 
 unitTest({ perms: { hrtime: false } }, async function performanceNow(): Promise<
   void
@@ -13,3 +18,13 @@ unitTest({ perms: { hrtime: false } }, async function performanceNow(): Promise<
   }, 10);
   await resolvable;
 });
+
+unitTest(
+  { perms: { hrtime: false } },
+  function performanceNowResolution(): void {
+    // Without the hrtime permission the time is clamped to 2ms.
+    for (let i = 0; i < 100; i++) {
+      assertEquals(performance.now() % 2, 0);
+    }
+  }
+);
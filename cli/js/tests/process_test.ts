@@ -64,6 +64,23 @@ unitTest(
   }
 );
 
+unitTest(
+  {
+    // Windows has no argv[0] of its own.
+    ignore: Deno.build.os === "win",
+    perms: { run: true },
+  },
+  async function runArgv0(): Promise<void> {
+    const p = run({
+      cmd: ["sh", "-c", "ps -o args= -p $$; true"],
+      stdout: "piped",
+    });
+    const output = new TextDecoder().decode(await p.output());
+    assert(output.startsWith("sh -c"), output);
+    p.close();
+  }
+);
+
 unitTest({ perms: { run: true } }, function runNotFound(): void {
   let error;
   try {
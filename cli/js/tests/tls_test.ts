@@ -210,6 +210,247 @@ unitTest(
   }
 );
 
+unitTest(
+  { perms: { read: true, net: true } },
+  async function listenTLSClientAuthRequired(): Promise<void> {
+    const hostname = "localhost";
+    const port = 4504;
+    const listener = Deno.listenTLS({
+      hostname,
+      port,
+      certFile: "cli/tests/tls/localhost.crt",
+      keyFile: "cli/tests/tls/localhost.key",
+      clientAuth: "required",
+      caFile: "cli/tests/tls/RootCA.pem",
+    });
+    const accepted = listener.accept();
+
+    const conn = await Deno.connectTLS({
+      hostname,
+      port,
+      certFile: "cli/tests/tls/RootCA.pem",
+      clientCertFile: "cli/tests/tls/client.crt",
+      clientKeyFile: "cli/tests/tls/client.key",
+    });
+    await conn.write(encoder.encode("hello"));
+    const serverConn = await accepted;
+    assertEquals(serverConn.peerCertificates.length, 1);
+    assertEquals(
+      serverConn.peerCertificates[0],
+      await Deno.readFile("cli/tests/tls/client.crt").then((data) =>
+        decoder.decode(data)
+      )
+    );
+    const buf = new Uint8Array(5);
+    assertEquals(await serverConn.read(buf), 5);
+    assertEquals(decoder.decode(buf), "hello");
+    serverConn.close();
+    conn.close();
+    listener.close();
+  }
+);
+
+unitTest(
+  { perms: { read: true, net: true } },
+  async function listenTLSClientAuthRequiredNoCert(): Promise<void> {
+    const hostname = "localhost";
+    const port = 4504;
+    const listener = Deno.listenTLS({
+      hostname,
+      port,
+      certFile: "cli/tests/tls/localhost.crt",
+      keyFile: "cli/tests/tls/localhost.key",
+      clientAuth: "required",
+      caFile: "cli/tests/tls/RootCA.pem",
+    });
+    const accepted = listener.accept();
+
+    let conn: Deno.Conn | undefined;
+    try {
+      conn = await Deno.connectTLS({
+        hostname,
+        port,
+        certFile: "cli/tests/tls/RootCA.pem",
+      });
+      await conn.read(new Uint8Array(1));
+    } catch {
+      // The handshake may fail on either side first.
+    }
+    conn?.close();
+
+    let err;
+    try {
+      await accepted;
+    } catch (e) {
+      err = e;
+    }
+    assert(err !== undefined);
+    listener.close();
+  }
+);
+
+unitTest(
+  { perms: { read: true, net: true } },
+  async function listenTLSClientAuthOptional(): Promise<void> {
+    const hostname = "localhost";
+    const port = 4504;
+    const listener = Deno.listenTLS({
+      hostname,
+      port,
+      certFile: "cli/tests/tls/localhost.crt",
+      keyFile: "cli/tests/tls/localhost.key",
+      clientAuth: "optional",
+      caFile: "cli/tests/tls/RootCA.pem",
+    });
+    const accepted = listener.accept();
+
+    const conn = await Deno.connectTLS({
+      hostname,
+      port,
+      certFile: "cli/tests/tls/RootCA.pem",
+    });
+    await conn.write(encoder.encode("hello"));
+    const serverConn = await accepted;
+    assertEquals(serverConn.peerCertificates, []);
+    serverConn.close();
+    conn.close();
+    listener.close();
+  }
+);
+
+unitTest(
+  { perms: { read: true, net: true } },
+  function listenTLSClientAuthNoCaFile(): void {
+    let err;
+    try {
+      Deno.listenTLS({
+        port: 4504,
+        certFile: "cli/tests/tls/localhost.crt",
+        keyFile: "cli/tests/tls/localhost.key",
+        clientAuth: "required",
+      });
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof TypeError);
+    assertEquals(err.message, "caFile is required for client authentication");
+  }
+);
+
+unitTest(
+  { perms: { read: true, net: true } },
+  async function connectTLSClientCertWithoutKey(): Promise<void> {
+    let err;
+    try {
+      await Deno.connectTLS({
+        hostname: "localhost",
+        port: 4504,
+        clientCertFile: "cli/tests/tls/client.crt",
+      });
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof TypeError);
+  }
+);
+
+unitTest(
+  { perms: { read: true, net: true } },
+  async function listenTLSAlpn(): Promise<void> {
+    const hostname = "localhost";
+    const port = 4504;
+    const listener = Deno.listenTLS({
+      hostname,
+      port,
+      certFile: "cli/tests/tls/localhost.crt",
+      keyFile: "cli/tests/tls/localhost.key",
+      alpnProtocols: ["h2", "http/1.1"],
+    });
+    const accepted = listener.accept();
+
+    const conn = await Deno.connectTLS({
+      hostname,
+      port,
+      certFile: "cli/tests/tls/RootCA.pem",
+      alpnProtocols: ["foo", "http/1.1"],
+    });
+    assertEquals(conn.alpnProtocol, "http/1.1");
+    assertEquals(conn.peerCertificates.length, 1);
+    await conn.write(encoder.encode("hello"));
+    const serverConn = await accepted;
+    assertEquals(serverConn.alpnProtocol, "http/1.1");
+    serverConn.close();
+    conn.close();
+    listener.close();
+  }
+);
+
+unitTest(
+  { perms: { read: true, net: true } },
+  async function listenTLSNoAlpn(): Promise<void> {
+    const hostname = "localhost";
+    const port = 4504;
+    const listener = Deno.listenTLS({
+      hostname,
+      port,
+      certFile: "cli/tests/tls/localhost.crt",
+      keyFile: "cli/tests/tls/localhost.key",
+    });
+    const accepted = listener.accept();
+
+    const conn = await Deno.connectTLS({
+      hostname,
+      port,
+      certFile: "cli/tests/tls/RootCA.pem",
+    });
+    assertEquals(conn.alpnProtocol, null);
+    await conn.write(encoder.encode("hello"));
+    const serverConn = await accepted;
+    assertEquals(serverConn.alpnProtocol, null);
+    serverConn.close();
+    conn.close();
+    listener.close();
+  }
+);
+
+unitTest(
+  { perms: { read: true, net: true } },
+  async function listenTLSSniCertificates(): Promise<void> {
+    const hostname = "localhost";
+    const port = 4504;
+    // The default certificate isn't valid for "localhost", so the handshake
+    // only succeeds if the server picks the certificate by name.
+    const listener = Deno.listenTLS({
+      hostname,
+      port,
+      certFile: "cli/tests/tls/client.crt",
+      keyFile: "cli/tests/tls/client.key",
+      sniCertificates: {
+        LOCALHOST: {
+          certFile: "cli/tests/tls/localhost.crt",
+          keyFile: "cli/tests/tls/localhost.key",
+        },
+      },
+    });
+    const accepted = listener.accept();
+
+    const conn = await Deno.connectTLS({
+      hostname,
+      port,
+      certFile: "cli/tests/tls/RootCA.pem",
+    });
+    assertEquals(
+      conn.peerCertificates[0],
+      decoder.decode(await Deno.readFile("cli/tests/tls/localhost.crt"))
+    );
+    await conn.write(encoder.encode("hello"));
+    const serverConn = await accepted;
+    serverConn.close();
+    conn.close();
+    listener.close();
+  }
+);
+
 unitTest(
   { perms: { read: true, net: true } },
   async function startTLS(): Promise<void> {
@@ -260,3 +501,287 @@ unitTest(
     conn.close();
   }
 );
+
+unitTest(
+  { perms: { read: true, net: true } },
+  async function startTLSServer(): Promise<void> {
+    const hostname = "localhost";
+    const port = 4504;
+    const listener = Deno.listen({ hostname, port });
+    const accepted = listener.accept();
+    const conn = await Deno.connect({ hostname, port });
+    const serverConn = await accepted;
+
+    // Both sides switch to TLS once the server has announced it.
+    await serverConn.write(encoder.encode("ready"));
+    const buf = new Uint8Array(5);
+    assertEquals(await conn.read(buf), 5);
+    assertEquals(decoder.decode(buf), "ready");
+
+    const [serverTlsConn, tlsConn] = await Promise.all([
+      Deno.startTLSServer(serverConn, {
+        certFile: "cli/tests/tls/localhost.crt",
+        keyFile: "cli/tests/tls/localhost.key",
+        alpnProtocols: ["smtp"],
+      }),
+      Deno.startTLS(conn, {
+        hostname,
+        certFile: "cli/tests/tls/RootCA.pem",
+        alpnProtocols: ["smtp"],
+      }),
+    ]);
+    assertEquals(serverTlsConn.alpnProtocol, "smtp");
+    assertEquals(tlsConn.alpnProtocol, "smtp");
+    assertEquals(serverTlsConn.remoteAddr, serverConn.remoteAddr);
+
+    await tlsConn.write(encoder.encode("hello"));
+    assertEquals(await serverTlsConn.read(buf), 5);
+    assertEquals(decoder.decode(buf), "hello");
+
+    // The plain connections were replaced.
+    let err;
+    try {
+      await serverConn.write(encoder.encode("hello"));
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof Deno.errors.BadResource);
+
+    serverTlsConn.close();
+    tlsConn.close();
+    listener.close();
+  }
+);
+
+unitTest(async function startTLSServerNoReadPerm(): Promise<void> {
+  let err;
+  try {
+    await Deno.startTLSServer({ rid: 0 } as Deno.Conn, {
+      certFile: "cli/tests/tls/localhost.crt",
+      keyFile: "cli/tests/tls/localhost.key",
+    });
+  } catch (e) {
+    err = e;
+  }
+  assert(err instanceof Deno.errors.PermissionDenied);
+});
+
+function readPem(path: string): string {
+  return decoder.decode(Deno.readFileSync(path));
+}
+
+/** Connects to a listener created with `listenOptions`, returning both ends
+ * of the connection once the handshake is done. */
+async function tlsPair(
+  listenOptions: Omit<Deno.ListenTLSOptions, "port" | "hostname">,
+  connectOptions: Omit<Deno.ConnectTLSOptions, "port" | "hostname"> = {}
+): Promise<[Deno.TLSConn, Deno.TLSConn]> {
+  const hostname = "localhost";
+  const port = 4504;
+  const listener = Deno.listenTLS({ hostname, port, ...listenOptions });
+  try {
+    const accepted = listener.accept();
+    const conn = await Deno.connectTLS({
+      hostname,
+      port,
+      certFile: "cli/tests/tls/RootCA.pem",
+      ...connectOptions,
+    });
+    await conn.write(encoder.encode("hello"));
+    return [await accepted, conn];
+  } finally {
+    listener.close();
+  }
+}
+
+unitTest(
+  { perms: { read: true, net: true } },
+  async function listenTLSEcKey(): Promise<void> {
+    const [serverConn, conn] = await tlsPair({
+      certFile: "cli/tests/tls/localhost_ecc.crt",
+      keyFile: "cli/tests/tls/localhost_ecc.key",
+    });
+    assertEquals(
+      conn.peerCertificates[0],
+      readPem("cli/tests/tls/localhost_ecc.crt")
+    );
+    serverConn.close();
+    conn.close();
+  }
+);
+
+unitTest(
+  { perms: { read: true, net: true } },
+  async function listenTLSInlinePem(): Promise<void> {
+    const [serverConn, conn] = await tlsPair(
+      {
+        certChain: readPem("cli/tests/tls/localhost_ecc.crt"),
+        privateKey: readPem("cli/tests/tls/localhost_ecc.key"),
+        clientAuth: "required",
+        caFile: "cli/tests/tls/RootCA.pem",
+      },
+      {
+        certChain: readPem("cli/tests/tls/client.crt"),
+        privateKey: readPem("cli/tests/tls/client.key"),
+      }
+    );
+    assertEquals(
+      conn.peerCertificates[0],
+      readPem("cli/tests/tls/localhost_ecc.crt")
+    );
+    assertEquals(
+      serverConn.peerCertificates[0],
+      readPem("cli/tests/tls/client.crt")
+    );
+    serverConn.close();
+    conn.close();
+  }
+);
+
+unitTest(
+  { perms: { read: true, net: true } },
+  async function listenTLSPkcs12(): Promise<void> {
+    const [serverConn, conn] = await tlsPair({
+      pkcs12File: "cli/tests/tls/localhost.p12",
+      passphrase: "deno",
+    });
+    assertEquals(
+      conn.peerCertificates[0],
+      readPem("cli/tests/tls/localhost.crt")
+    );
+    serverConn.close();
+    conn.close();
+  }
+);
+
+unitTest(
+  { perms: { read: true, net: true } },
+  function listenTLSPkcs12WrongPassphrase(): void {
+    let err;
+    try {
+      Deno.listenTLS({
+        port: 4504,
+        pkcs12File: "cli/tests/tls/localhost.p12",
+        passphrase: "wrong",
+      });
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof Error);
+    assertEquals(err.message, "Invalid passphrase for PKCS#12 bundle");
+  }
+);
+
+unitTest({ perms: { net: true } }, function listenTLSPkcs12NoReadPerm(): void {
+  let err;
+  try {
+    Deno.listenTLS({
+      port: 4504,
+      pkcs12File: "cli/tests/tls/localhost.p12",
+      passphrase: "deno",
+    });
+  } catch (e) {
+    err = e;
+  }
+  assert(err instanceof Deno.errors.PermissionDenied);
+});
+
+unitTest(
+  { perms: { read: true, net: true } },
+  function listenTLSKeyPairErrors(): void {
+    const options: Array<Omit<Deno.ListenTLSOptions, "port">> = [
+      {},
+      { certFile: "cli/tests/tls/localhost.crt" },
+      {
+        certFile: "cli/tests/tls/localhost.crt",
+        keyFile: "cli/tests/tls/localhost.key",
+        certChain: readPem("cli/tests/tls/localhost.crt"),
+      },
+      {
+        keyFile: "cli/tests/tls/localhost.key",
+        pkcs12File: "cli/tests/tls/localhost.p12",
+      },
+    ];
+    for (const option of options) {
+      let err;
+      try {
+        Deno.listenTLS({ port: 4504, ...option });
+      } catch (e) {
+        err = e;
+      }
+      assert(err instanceof TypeError);
+    }
+  }
+);
+
+unitTest(
+  { perms: { read: true, net: true } },
+  async function tlsHandshakeInfo(): Promise<void> {
+    const [serverConn, conn] = await tlsPair(
+      {
+        certFile: "cli/tests/tls/localhost.crt",
+        keyFile: "cli/tests/tls/localhost.key",
+        clientAuth: "required",
+        caFile: "cli/tests/tls/RootCA.pem",
+      },
+      {
+        clientCertFile: "cli/tests/tls/client.crt",
+        clientKeyFile: "cli/tests/tls/client.key",
+      }
+    );
+
+    const info = conn.handshakeInfo();
+    assertEquals(info.protocol, "TLSv1.3");
+    assert(info.cipherSuite!.startsWith("TLS13_"));
+    assertEquals(info.alpnProtocol, null);
+    assertEquals(info.peerCertificates.length, 1);
+    const [cert] = info.peerCertificates;
+    assertEquals(cert.pem, readPem("cli/tests/tls/localhost.crt"));
+    assert(cert.der instanceof Uint8Array);
+    assertEquals(
+      cert.subject,
+      "C=US, ST=YourState, L=YourCity, O=Example-Certificates, CN=localhost.local"
+    );
+    assertEquals(cert.issuer, "C=US, CN=Example-Root-CA");
+    assertEquals(cert.subjectAltNames, ["localhost"]);
+    assertEquals(cert.serialNumber, "e3f2415772fd4a4f");
+    assertEquals(cert.parseError, null);
+    assertEquals(cert.notAfter!.toISOString(), "2118-09-27T16:28:58.000Z");
+    assert(cert.notBefore! < new Date());
+    assertEquals(
+      cert.fingerprint256,
+      "66afd14ccf73a1e25e904cfa64978586cb3851b1fb9b4a02db7936eb4d44cc78"
+    );
+
+    const serverInfo = serverConn.handshakeInfo();
+    assertEquals(serverInfo.protocol, "TLSv1.3");
+    assertEquals(serverInfo.cipherSuite, info.cipherSuite);
+    assertEquals(
+      serverInfo.peerCertificates[0].subject,
+      "C=US, ST=YourState, L=YourCity, O=Example-Certificates, CN=deno-client"
+    );
+
+    serverConn.close();
+    conn.close();
+  }
+);
+
+unitTest(
+  { perms: { read: true, net: true } },
+  async function tlsHandshakeInfoClosed(): Promise<void> {
+    const [serverConn, conn] = await tlsPair({
+      certFile: "cli/tests/tls/localhost.crt",
+      keyFile: "cli/tests/tls/localhost.key",
+    });
+    assertEquals(serverConn.handshakeInfo().peerCertificates, []);
+    serverConn.close();
+    conn.close();
+    let err;
+    try {
+      conn.handshakeInfo();
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof Deno.errors.BadResource);
+  }
+);
@@ -0,0 +1,200 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+import {
+  assert,
+  assertEquals,
+  assertThrows,
+  createResolvable,
+  unitTest,
+} from "./test_util.ts";
+
+/** Echoes the messages received on the first connection accepted from
+ * `listener` until the client closes it. */
+async function echoServer(
+  listener: Deno.Listener,
+  protocols?: string[]
+): Promise<Deno.WebSocketUpgrade> {
+  const conn = await listener.accept();
+  const upgrade = await Deno.upgradeWebSocket(conn, { protocols });
+  const { socket } = upgrade;
+  socket.binaryType = "arraybuffer";
+  socket.onmessage = (e): void => socket.send(e.data);
+  return upgrade;
+}
+
+function closed(socket: WebSocket): Promise<CloseEvent> {
+  const promise = createResolvable<CloseEvent>();
+  socket.onclose = (e): void => promise.resolve(e);
+  return promise;
+}
+
+unitTest(function websocketConstructorErrors(): void {
+  for (const url of [
+    "not a url",
+    "http://127.0.0.1:4520",
+    "ws://127.0.0.1:4520/#fragment",
+  ]) {
+    assertThrows(
+      (): void => {
+        new WebSocket(url);
+      },
+      DOMException,
+      url === "not a url" ? "is invalid" : ""
+    );
+  }
+  assertThrows(
+    (): void => {
+      new WebSocket("ws://127.0.0.1:4520", ["chat", "chat"]);
+    },
+    DOMException,
+    "duplicates"
+  );
+});
+
+unitTest(function websocketPerm(): void {
+  assertThrows((): void => {
+    new WebSocket("ws://127.0.0.1:4520");
+  }, Deno.errors.PermissionDenied);
+});
+
+unitTest(
+  { perms: { net: true } },
+  async function websocketEcho(): Promise<void> {
+    const listener = Deno.listen({ port: 4520 });
+    const server = echoServer(listener);
+
+    const socket = new WebSocket("ws://127.0.0.1:4520/echo?a=b");
+    assertEquals(socket.readyState, WebSocket.CONNECTING);
+    assertEquals(socket.url, "ws://127.0.0.1:4520/echo?a=b");
+    const opened = createResolvable<void>();
+    socket.onopen = (): void => opened.resolve();
+    await opened;
+    assertEquals(socket.readyState, WebSocket.OPEN);
+    assertEquals(socket.protocol, "");
+
+    const messages: unknown[] = [];
+    const received = createResolvable<void>();
+    socket.binaryType = "arraybuffer";
+    socket.onmessage = (e): void => {
+      messages.push(e.data);
+      if (messages.length === 2) {
+        received.resolve();
+      }
+    };
+    socket.send("hello");
+    socket.send(new Uint8Array([1, 2, 3]));
+    await received;
+    assertEquals(messages[0], "hello");
+    assert(messages[1] instanceof ArrayBuffer);
+    assertEquals(
+      new Uint8Array(messages[1] as ArrayBuffer),
+      new Uint8Array([1, 2, 3])
+    );
+
+    const { url, headers, socket: serverSocket } = await server;
+    assertEquals(url, "ws://127.0.0.1:4520/echo?a=b");
+    assertEquals(headers.get("upgrade")?.toLowerCase(), "websocket");
+
+    const serverClose = closed(serverSocket);
+    const close = closed(socket);
+    socket.close(1000, "done");
+    assertEquals(socket.readyState, WebSocket.CLOSING);
+    const event = await close;
+    assertEquals(socket.readyState, WebSocket.CLOSED);
+    assert(event.wasClean);
+    assertEquals(event.code, 1000);
+    assertEquals(event.reason, "done");
+    assertEquals((await serverClose).code, 1000);
+    listener.close();
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function websocketProtocols(): Promise<void> {
+    const listener = Deno.listen({ port: 4520 });
+    const server = echoServer(listener, ["superchat", "chat"]);
+
+    const socket = new WebSocket("ws://127.0.0.1:4520", ["chat", "superchat"]);
+    const opened = createResolvable<void>();
+    socket.onopen = (): void => opened.resolve();
+    await opened;
+    // The first protocol offered by the client that the server supports.
+    assertEquals(socket.protocol, "chat");
+    const serverSocket = (await server).socket;
+    assertEquals(serverSocket.protocol, "chat");
+
+    const serverClose = closed(serverSocket);
+    const close = closed(socket);
+    socket.close();
+    assert((await close).wasClean);
+    await serverClose;
+    listener.close();
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function websocketServerClose(): Promise<void> {
+    const listener = Deno.listen({ port: 4520 });
+    const server = echoServer(listener);
+
+    const socket = new WebSocket("ws://127.0.0.1:4520");
+    const close = closed(socket);
+    const serverSocket = (await server).socket;
+    const serverClose = closed(serverSocket);
+    serverSocket.close(3000, "bye");
+    const event = await close;
+    assert(event.wasClean);
+    assertEquals(event.code, 3000);
+    assertEquals(event.reason, "bye");
+    assert((await serverClose).wasClean);
+    listener.close();
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function websocketConnectionRefused(): Promise<void> {
+    const socket = new WebSocket("ws://127.0.0.1:4520");
+    const error = createResolvable<void>();
+    socket.onerror = (): void => error.resolve();
+    const close = closed(socket);
+    await error;
+    const event = await close;
+    assert(!event.wasClean);
+    assertEquals(event.code, 1006);
+  }
+);
+
+unitTest(
+  { perms: { net: true } },
+  async function websocketInvalidClose(): Promise<void> {
+    const socket = new WebSocket("ws://127.0.0.1:4520");
+    const close = closed(socket);
+    assertThrows(
+      (): void => {
+        socket.close(1001);
+      },
+      DOMException,
+      "close code"
+    );
+    assertThrows(
+      (): void => {
+        socket.close(1000, "x".repeat(124));
+      },
+      DOMException,
+      "123 bytes"
+    );
+    assertThrows(
+      (): void => {
+        socket.send("hello");
+      },
+      DOMException,
+      "still connecting"
+    );
+    // Closing while connecting fails the connection.
+    socket.close();
+    assertEquals(socket.readyState, WebSocket.CLOSING);
+    assert(!(await close).wasClean);
+  }
+);
@@ -1,14 +1,78 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 import * as tlsOps from "./ops/tls.ts";
-import { Listener, Conn, ConnImpl, ListenerImpl } from "./net.ts";
+import {
+  Listener,
+  Conn,
+  TcpConn,
+  TcpConnImpl,
+  ListenerImpl,
+} from "./net.ts";
+import { Addr, ListenSocketOptions } from "./ops/net.ts";
+import { TLSKeyPairOptions, SNICertificate } from "./ops/tls.ts";
 
 // TODO(ry) There are many configuration options to add...
 // https://docs.rs/rustls/0.16.0/rustls/struct.ClientConfig.html
-interface ConnectTLSOptions {
+interface ConnectTLSOptions extends TLSKeyPairOptions {
   transport?: "tcp";
   port: number;
   hostname?: string;
   certFile?: string;
+  clientCertFile?: string;
+  clientKeyFile?: string;
+  alpnProtocols?: string[];
+}
+
+export interface TLSPeerCertificate {
+  der: Uint8Array;
+  pem: string;
+  fingerprint256: string;
+  parseError: string | null;
+  subject: string | null;
+  issuer: string | null;
+  subjectAltNames: string[] | null;
+  serialNumber: string | null;
+  notBefore: Date | null;
+  notAfter: Date | null;
+}
+
+export interface TLSHandshakeInfo {
+  protocol: string | null;
+  cipherSuite: string | null;
+  alpnProtocol: string | null;
+  peerCertificates: TLSPeerCertificate[];
+}
+
+export interface TLSConn extends TcpConn {
+  readonly peerCertificates: string[];
+  readonly alpnProtocol: string | null;
+  handshakeInfo(): TLSHandshakeInfo;
+}
+
+class TLSConnImpl extends TcpConnImpl implements TLSConn {
+  constructor(
+    rid: number,
+    remoteAddr: Addr,
+    localAddr: Addr,
+    readonly peerCertificates: string[],
+    readonly alpnProtocol: string | null
+  ) {
+    super(rid, remoteAddr, localAddr);
+  }
+
+  handshakeInfo(): TLSHandshakeInfo {
+    const res = tlsOps.handshakeInfo(this.rid);
+    return {
+      ...res,
+      peerCertificates: res.peerCertificates.map(
+        (cert): TLSPeerCertificate => ({
+          ...cert,
+          der: new Uint8Array(cert.der),
+          notBefore: cert.notBefore === null ? null : new Date(cert.notBefore),
+          notAfter: cert.notAfter === null ? null : new Date(cert.notAfter),
+        })
+      ),
+    };
+  }
 }
 
 export async function connectTLS({
@@ -16,61 +80,170 @@ export async function connectTLS({
   hostname = "127.0.0.1",
   transport = "tcp",
   certFile = undefined,
-}: ConnectTLSOptions): Promise<Conn> {
+  clientCertFile = undefined,
+  clientKeyFile = undefined,
+  alpnProtocols = undefined,
+  ...keyPair
+}: ConnectTLSOptions): Promise<TLSConn> {
   const res = await tlsOps.connectTLS({
     port,
     hostname,
     transport,
     certFile,
+    clientCertFile,
+    clientKeyFile,
+    alpnProtocols,
+    ...keyPair,
   });
-  return new ConnImpl(res.rid, res.remoteAddr!, res.localAddr!);
+  return new TLSConnImpl(
+    res.rid,
+    res.remoteAddr,
+    res.localAddr,
+    res.peerCertificates,
+    res.alpnProtocol
+  );
+}
+
+export interface TLSListener extends Listener {
+  accept(): Promise<TLSConn>;
+  [Symbol.asyncIterator](): AsyncIterableIterator<TLSConn>;
 }
 
-class TLSListenerImpl extends ListenerImpl {
-  async accept(): Promise<Conn> {
+class TLSListenerImpl extends ListenerImpl implements TLSListener {
+  async accept(): Promise<TLSConn> {
     const res = await tlsOps.acceptTLS(this.rid);
-    return new ConnImpl(res.rid, res.remoteAddr, res.localAddr);
+    return new TLSConnImpl(
+      res.rid,
+      res.remoteAddr,
+      res.localAddr,
+      res.peerCertificates,
+      res.alpnProtocol
+    );
+  }
+
+  [Symbol.asyncIterator](): AsyncIterableIterator<TLSConn> {
+    return super[Symbol.asyncIterator]() as AsyncIterableIterator<TLSConn>;
   }
 }
 
-export interface ListenTLSOptions {
+export interface ListenTLSOptions
+  extends TLSKeyPairOptions,
+    ListenSocketOptions {
   port: number;
   hostname?: string;
   transport?: "tcp";
-  certFile: string;
-  keyFile: string;
+  certFile?: string;
+  keyFile?: string;
+  clientAuth?: "none" | "optional" | "required";
+  caFile?: string;
+  alpnProtocols?: string[];
+  sniCertificates?: Record<string, SNICertificate>;
 }
 
 export function listenTLS({
   port,
-  certFile,
-  keyFile,
+  certFile = undefined,
+  keyFile = undefined,
   hostname = "0.0.0.0",
   transport = "tcp",
-}: ListenTLSOptions): Listener {
+  clientAuth = "none",
+  caFile = undefined,
+  alpnProtocols = undefined,
+  sniCertificates = undefined,
+  reuseAddress = undefined,
+  reusePort = undefined,
+  sendBufferSize = undefined,
+  recvBufferSize = undefined,
+  ...keyPair
+}: ListenTLSOptions): TLSListener {
   const res = tlsOps.listenTLS({
     port,
     certFile,
     keyFile,
     hostname,
     transport,
+    clientAuth,
+    caFile,
+    alpnProtocols,
+    sniCertificates,
+    reuseAddress,
+    reusePort,
+    sendBufferSize,
+    recvBufferSize,
+    ...keyPair,
   });
   return new TLSListenerImpl(res.rid, res.localAddr);
 }
 
-interface StartTLSOptions {
+interface StartTLSOptions extends TLSKeyPairOptions {
   hostname?: string;
   certFile?: string;
+  clientCertFile?: string;
+  clientKeyFile?: string;
+  alpnProtocols?: string[];
 }
 
 export async function startTLS(
   conn: Conn,
-  { hostname = "127.0.0.1", certFile = undefined }: StartTLSOptions = {}
-): Promise<Conn> {
+  {
+    hostname = "127.0.0.1",
+    certFile = undefined,
+    clientCertFile = undefined,
+    clientKeyFile = undefined,
+    alpnProtocols = undefined,
+    ...keyPair
+  }: StartTLSOptions = {}
+): Promise<TLSConn> {
   const res = await tlsOps.startTLS({
     rid: conn.rid,
     hostname,
     certFile,
+    clientCertFile,
+    clientKeyFile,
+    alpnProtocols,
+    ...keyPair,
+  });
+  return new TLSConnImpl(
+    res.rid,
+    res.remoteAddr,
+    res.localAddr,
+    res.peerCertificates,
+    res.alpnProtocol
+  );
+}
+
+export type StartTLSServerOptions = Omit<
+  ListenTLSOptions,
+  "port" | "hostname" | "transport" | keyof ListenSocketOptions
+>;
+
+export async function startTLSServer(
+  conn: Conn,
+  {
+    certFile = undefined,
+    keyFile = undefined,
+    clientAuth = "none",
+    caFile = undefined,
+    alpnProtocols = undefined,
+    sniCertificates = undefined,
+    ...keyPair
+  }: StartTLSServerOptions
+): Promise<TLSConn> {
+  const res = await tlsOps.startTLSServer({
+    rid: conn.rid,
+    certFile,
+    keyFile,
+    clientAuth,
+    caFile,
+    alpnProtocols,
+    sniCertificates,
+    ...keyPair,
   });
-  return new ConnImpl(res.rid, res.remoteAddr!, res.localAddr!);
+  return new TLSConnImpl(
+    res.rid,
+    res.remoteAddr,
+    res.localAddr,
+    res.peerCertificates,
+    res.alpnProtocol
+  );
 }
@@ -429,6 +429,8 @@ export interface RequestInit {
   referrer?: string;
   referrerPolicy?: ReferrerPolicy;
   signal?: AbortSignal | null;
+  timeout?: number;
+  client?: { readonly rid: number };
   window?: any;
 }
 
@@ -5,11 +5,22 @@ import * as domTypes from "./dom_types.d.ts";
 import { TextDecoder, TextEncoder } from "./text_encoding.ts";
 import { DenoBlob, bytesSymbol as blobBytesSymbol } from "./blob.ts";
 import * as io from "../io.ts";
-import { read } from "../ops/io.ts";
+import { read, write } from "../ops/io.ts";
 import { close } from "../ops/resources.ts";
-import { Buffer } from "../buffer.ts";
-import { fetch as opFetch, FetchResponse } from "../ops/fetch.ts";
+import { Buffer, writeAll } from "../buffer.ts";
+import {
+  fetch as opFetch,
+  cancelFetch,
+  createFetchCancelHandle,
+  createRequestBody,
+  FetchResponse,
+} from "../ops/fetch.ts";
+import { ReadableStream } from "./streams/mod.ts";
 import { DomFileImpl } from "./dom_file.ts";
+import { AbortSignalImpl, add, remove } from "./abort_signal.ts";
+import { DOMExceptionImpl as DOMException } from "./dom_exception.ts";
+import { setTimeout, clearTimeout } from "./timers.ts";
+import { errors } from "../errors.ts";
 
 function getHeaderValueParams(value: string): Map<string, string> {
   const params = new Map();
@@ -28,17 +39,112 @@ function hasHeaderValueOf(s: string, value: string): boolean {
   return new RegExp(`^${value}[\t\s]*;?`).test(s);
 }
 
+/** Cancels a fetch, and reads of its response body, when `signal` is
+ * aborted or after `timeout` milliseconds. */
+class FetchCanceller {
+  readonly rid: number;
+  #signal: AbortSignalImpl | null;
+  #deadline: number | null = null;
+  #timer: number | null = null;
+  #pending = 0;
+  #closed = false;
+
+  constructor(signal: AbortSignal | null, timeout: number | undefined) {
+    this.rid = createFetchCancelHandle();
+    this.#signal = signal as AbortSignalImpl | null;
+    if (this.#signal) {
+      this.#signal[add](this.#abort);
+    }
+    if (timeout !== undefined) {
+      this.#deadline = Date.now() + timeout;
+    }
+  }
+
+  /** Runs an op of the fetch. The timeout timer only runs while an op is
+   * pending, so that a response whose body is never read doesn't keep the
+   * program alive until the timeout. */
+  async run<T>(op: () => Promise<T>): Promise<T> {
+    this.#startTimer();
+    try {
+      return await op();
+    } catch (err) {
+      throw this.mapError(err);
+    } finally {
+      this.#stopTimer();
+    }
+  }
+
+  #startTimer = (): void => {
+    this.#pending++;
+    if (this.#deadline === null || this.#timer !== null || this.#closed) {
+      return;
+    }
+    const remaining = Math.max(0, this.#deadline - Date.now());
+    this.#timer = setTimeout((): void => {
+      this.#timer = null;
+      cancelFetch(this.rid, "timeout");
+    }, remaining);
+  };
+
+  #stopTimer = (): void => {
+    this.#pending--;
+    if (this.#pending === 0 && this.#timer !== null) {
+      clearTimeout(this.#timer);
+      this.#timer = null;
+    }
+  };
+
+  #abort = (): void => {
+    if (!this.#closed) {
+      cancelFetch(this.rid, "abort");
+    }
+  };
+
+  /** Turns the error of an aborted fetch into an `AbortError`. */
+  mapError(err: Error): Error {
+    if (
+      this.#signal &&
+      this.#signal.aborted &&
+      err instanceof errors.ConnectionAborted
+    ) {
+      return new DOMException("The request was aborted.", "AbortError");
+    }
+    return err;
+  }
+
+  close(): void {
+    if (this.#closed) {
+      return;
+    }
+    this.#closed = true;
+    if (this.#signal) {
+      this.#signal[remove](this.#abort);
+    }
+    if (this.#timer !== null) {
+      clearTimeout(this.#timer);
+      this.#timer = null;
+    }
+    close(this.rid);
+  }
+}
+
 class Body
   implements domTypes.Body, domTypes.ReadableStream<Uint8Array>, io.ReadCloser {
   #bodyUsed = false;
   #bodyPromise: Promise<ArrayBuffer> | null = null;
   #data: ArrayBuffer | null = null;
   #rid: number;
+  #canceller: FetchCanceller | null;
   readonly locked: boolean = false; // TODO
   readonly body: domTypes.ReadableStream<Uint8Array>;
 
-  constructor(rid: number, readonly contentType: string) {
+  constructor(
+    rid: number,
+    readonly contentType: string,
+    canceller: FetchCanceller | null = null
+  ) {
     this.#rid = rid;
+    this.#canceller = canceller;
     this.body = this;
   }
 
@@ -222,11 +328,19 @@ class Body
 
   read(p: Uint8Array): Promise<number | io.EOF> {
     this.#bodyUsed = true;
-    return read(this.#rid, p);
+    if (!this.#canceller) {
+      return read(this.#rid, p);
+    }
+    return this.#canceller.run(
+      (): Promise<number | io.EOF> => read(this.#rid, p)
+    );
   }
 
   close(): Promise<void> {
     close(this.#rid);
+    if (this.#canceller) {
+      this.#canceller.close();
+    }
     return Promise.resolve();
   }
 
@@ -466,7 +580,10 @@ function sendFetchReq(
   url: string,
   method: string | null,
   headers: Headers | null,
-  body: ArrayBufferView | undefined
+  body: ArrayBufferView | undefined,
+  bodyRid?: number,
+  cancelRid?: number,
+  clientRid?: number
 ): Promise<FetchResponse> {
   let headerArray: Array<[string, string]> = [];
   if (headers) {
@@ -477,11 +594,87 @@ function sendFetchReq(
     method,
     url,
     headers: headerArray,
+    bodyRid,
+    cancelRid,
+    clientRid,
   };
 
   return opFetch(args, body);
 }
 
+/** Readers backed by a resource, such as `Deno.File` and `Deno.Conn`, which
+ * are streamed as a request body without going through JavaScript. */
+function isResourceReader(
+  body: unknown
+): body is io.Reader & { rid: number } {
+  return (
+    typeof body === "object" &&
+    body != null &&
+    typeof (body as { rid?: unknown }).rid === "number" &&
+    typeof (body as { read?: unknown }).read === "function"
+  );
+}
+
+function isReader(body: unknown): body is io.Reader {
+  return (
+    typeof body === "object" &&
+    body != null &&
+    typeof (body as { read?: unknown }).read === "function"
+  );
+}
+
+async function* readableStreamChunks(
+  stream: domTypes.ReadableStream<Uint8Array>
+): AsyncIterableIterator<Uint8Array> {
+  const reader = stream.getReader();
+  try {
+    while (true) {
+      const result = await reader.read();
+      if (result.done) {
+        break;
+      }
+      yield result.value;
+    }
+  } finally {
+    reader.releaseLock();
+  }
+}
+
+/** Writes `chunks` to the writer end of a request body pipe, and closes it
+ * once they are exhausted. Resolves with the error that ended `chunks` early,
+ * if any. Write errors mean the request is gone, which `op_fetch` reports. */
+async function pumpRequestBody(
+  chunks: AsyncIterableIterator<Uint8Array>,
+  writerRid: number
+): Promise<Error | undefined> {
+  const writer = {
+    write: (p: Uint8Array): Promise<number> => write(writerRid, p),
+  };
+  try {
+    while (true) {
+      let result: IteratorResult<Uint8Array>;
+      try {
+        result = await chunks.next();
+      } catch (e) {
+        return e;
+      }
+      if (result.done) {
+        return undefined;
+      }
+      try {
+        await writeAll(writer, result.value);
+      } catch {
+        if (chunks.return) {
+          await chunks.return();
+        }
+        return undefined;
+      }
+    }
+  } finally {
+    close(writerRid);
+  }
+}
+
 export async function fetch(
   input: domTypes.Request | URL | string,
   init?: domTypes.RequestInit
@@ -490,6 +683,8 @@ export async function fetch(
   let method: string | null = null;
   let headers: Headers | null = null;
   let body: ArrayBufferView | undefined;
+  let bodyRid: number | undefined;
+  let bodyChunks: AsyncIterableIterator<Uint8Array> | undefined;
   let redirected = false;
   let remRedirectCount = 20; // TODO: use a better way to handle
 
@@ -565,8 +760,13 @@ export async function fetch(
           payload += `\r\n--${boundary}--`;
           body = new TextEncoder().encode(payload);
           contentType = "multipart/form-data; boundary=" + boundary;
+        } else if (init.body instanceof ReadableStream) {
+          bodyChunks = readableStreamChunks(init.body);
+        } else if (isResourceReader(init.body)) {
+          bodyRid = init.body.rid;
+        } else if (isReader(init.body)) {
+          bodyChunks = io.toAsyncIterator(init.body);
         } else {
-          // TODO: ReadableStream
           notImplemented();
         }
         if (contentType && !headers.has("content-type")) {
@@ -585,53 +785,126 @@ export async function fetch(
     }
   }
 
-  while (remRedirectCount) {
-    const fetchResponse = await sendFetchReq(url, method, headers, body);
+  let canceller: FetchCanceller | null = null;
+  if (init && (init.signal || init.timeout !== undefined)) {
+    if (init.signal && init.signal.aborted) {
+      throw new DOMException("The request was aborted.", "AbortError");
+    }
+    canceller = new FetchCanceller(init.signal || null, init.timeout);
+  }
+
+  let bodyPump: Promise<Error | undefined> | undefined;
+  if (bodyChunks) {
+    const { writerRid, readerRid } = createRequestBody();
+    bodyRid = readerRid;
+    bodyPump = pumpRequestBody(bodyChunks, writerRid);
+  }
 
-    const response = new Response(
-      url,
-      fetchResponse.status,
-      fetchResponse.statusText,
-      fetchResponse.headers,
-      fetchResponse.bodyRid,
-      redirected
-    );
-    if ([301, 302, 303, 307, 308].includes(response.status)) {
-      // We won't use body of received response, so close it now
-      // otherwise it will be kept in resource table.
-      close(fetchResponse.bodyRid);
-      // We're in a redirect status
-      switch ((init && init.redirect) || "follow") {
-        case "error":
-          /* I suspect that deno will probably crash if you try to use that
-             rid, which suggests to me that Response needs to be refactored */
-          return new Response("", 0, "", [], -1, false, "error", null);
-        case "manual":
-          return new Response("", 0, "", [], -1, false, "opaqueredirect", null);
-        case "follow":
-        default:
-          let redirectUrl = response.headers.get("Location");
-          if (redirectUrl == null) {
-            return response; // Unspecified
-          }
-          if (
-            !redirectUrl.startsWith("http://") &&
-            !redirectUrl.startsWith("https://")
-          ) {
-            redirectUrl =
-              url.split("//")[0] +
-              "//" +
-              url.split("//")[1].split("/")[0] +
-              redirectUrl; // TODO: handle relative redirection more gracefully
-          }
-          url = redirectUrl;
-          redirected = true;
-          remRedirectCount--;
+  try {
+    while (remRedirectCount) {
+      const send = (): Promise<FetchResponse> =>
+        sendFetchReq(
+          url,
+          method,
+          headers,
+          body,
+          bodyRid,
+          canceller ? canceller.rid : undefined,
+          init && init.client ? init.client.rid : undefined
+        );
+      const [fetchResponse, bodyError] = await Promise.all([
+        canceller ? canceller.run(send) : send(),
+        bodyPump,
+      ]);
+      if (bodyError) {
+        close(fetchResponse.bodyRid);
+        throw bodyError;
       }
-    } else {
-      return response;
+
+      let responseBody: Body | null = null;
+      if (canceller) {
+        const contentType =
+          new Headers(fetchResponse.headers).get("content-type") || "";
+        responseBody = new Body(fetchResponse.bodyRid, contentType, canceller);
+      }
+      const response = new Response(
+        url,
+        fetchResponse.status,
+        fetchResponse.statusText,
+        fetchResponse.headers,
+        fetchResponse.bodyRid,
+        redirected,
+        "default",
+        responseBody
+      );
+      if ([301, 302, 303, 307, 308].includes(response.status)) {
+        // We won't use body of received response, so close it now
+        // otherwise it will be kept in resource table.
+        close(fetchResponse.bodyRid);
+        // We're in a redirect status
+        switch ((init && init.redirect) || "follow") {
+          case "error":
+            if (canceller) {
+              canceller.close();
+            }
+            /* I suspect that deno will probably crash if you try to use that
+               rid, which suggests to me that Response needs to be refactored */
+            return new Response("", 0, "", [], -1, false, "error", null);
+          case "manual":
+            if (canceller) {
+              canceller.close();
+            }
+            return new Response(
+              "",
+              0,
+              "",
+              [],
+              -1,
+              false,
+              "opaqueredirect",
+              null
+            );
+          case "follow":
+          default:
+            let redirectUrl = response.headers.get("Location");
+            if (redirectUrl == null) {
+              if (canceller) {
+                canceller.close();
+              }
+              return response; // Unspecified
+            }
+            if (
+              !redirectUrl.startsWith("http://") &&
+              !redirectUrl.startsWith("https://")
+            ) {
+              redirectUrl =
+                url.split("//")[0] +
+                "//" +
+                url.split("//")[1].split("/")[0] +
+                redirectUrl; // TODO: handle relative redirection more gracefully
+            }
+            if (bodyRid !== undefined) {
+              // A streamed body has been consumed and can't be sent again.
+              throw new TypeError(
+                "Cannot follow a redirect with a streaming request body."
+              );
+            }
+            url = redirectUrl;
+            redirected = true;
+            remRedirectCount--;
+        }
+      } else {
+        return response;
+      }
+    }
+    // Return a network error due to too many redirections
+    throw notImplemented();
+  } catch (err) {
+    if (canceller) {
+      const mappedErr = canceller.mapError(err);
+      canceller.close();
+      throw mappedErr;
     }
+    throw err;
   }
-  // Return a network error due to too many redirections
-  throw notImplemented();
 }
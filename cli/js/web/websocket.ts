@@ -0,0 +1,354 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+import * as wsOps from "../ops/websocket.ts";
+import { close } from "../ops/resources.ts";
+import { Conn } from "../net.ts";
+import { DenoBlob, bytesSymbol as blobBytesSymbol } from "./blob.ts";
+import { DOMExceptionImpl as DOMException } from "./dom_exception.ts";
+import { EventImpl as Event } from "./event.ts";
+import { EventTargetImpl as EventTarget } from "./event_target.ts";
+import { Headers } from "./headers.ts";
+import { TextEncoder } from "./text_encoding.ts";
+import { requiredArguments } from "./util.ts";
+import { MessageEvent } from "./workers.ts";
+
+export interface CloseEventInit extends EventInit {
+  wasClean?: boolean;
+  code?: number;
+  reason?: string;
+}
+
+export class CloseEvent extends Event {
+  readonly wasClean: boolean;
+  readonly code: number;
+  readonly reason: string;
+
+  constructor(type: string, eventInitDict?: CloseEventInit) {
+    super(type, {
+      bubbles: eventInitDict?.bubbles ?? false,
+      cancelable: eventInitDict?.cancelable ?? false,
+      composed: eventInitDict?.composed ?? false,
+    });
+
+    this.wasClean = eventInitDict?.wasClean ?? false;
+    this.code = eventInitDict?.code ?? 0;
+    this.reason = eventInitDict?.reason ?? "";
+  }
+}
+
+const CONNECTING = 0;
+const OPEN = 1;
+const CLOSING = 2;
+const CLOSED = 3;
+
+/** Passed to the constructor by `upgradeWebSocket()`, instead of protocols,
+ * for a connection that has already been established. */
+const acceptedSymbol = Symbol("accepted");
+
+interface Accepted {
+  [acceptedSymbol]: wsOps.AcceptResponse;
+}
+
+function isAccepted(value: unknown): value is Accepted {
+  return typeof value === "object" && value != null && acceptedSymbol in value;
+}
+
+export class WebSocketImpl extends EventTarget implements WebSocket {
+  static readonly CONNECTING = CONNECTING;
+  static readonly OPEN = OPEN;
+  static readonly CLOSING = CLOSING;
+  static readonly CLOSED = CLOSED;
+  readonly CONNECTING = CONNECTING;
+  readonly OPEN = OPEN;
+  readonly CLOSING = CLOSING;
+  readonly CLOSED = CLOSED;
+
+  #rid?: number;
+  #url: string;
+  #readyState = CONNECTING;
+  #protocol = "";
+  #binaryType: BinaryType = "blob";
+  #bufferedAmount = 0;
+
+  public onopen: ((e: Event) => void) | null = null;
+  public onmessage: ((e: MessageEvent) => void) | null = null;
+  public onerror: ((e: Event) => void) | null = null;
+  public onclose: ((e: CloseEvent) => void) | null = null;
+
+  constructor(url: string, protocols: string | string[] | Accepted = []) {
+    super();
+    requiredArguments("WebSocket", arguments.length, 1);
+
+    if (isAccepted(protocols)) {
+      // The handshake is over, so there is no open event.
+      const accepted = protocols[acceptedSymbol];
+      this.#url = accepted.url;
+      this.#rid = accepted.rid;
+      this.#protocol = accepted.protocol;
+      this.#readyState = OPEN;
+      this.#eventLoop();
+      return;
+    }
+
+    let wsURL: URL;
+    try {
+      wsURL = new URL(url);
+    } catch {
+      throw new DOMException(`The URL '${url}' is invalid.`, "SyntaxError");
+    }
+    if (wsURL.protocol !== "ws:" && wsURL.protocol !== "wss:") {
+      throw new DOMException(
+        `The URL's scheme must be either 'ws' or 'wss'. '${wsURL.protocol}' is not allowed.`,
+        "SyntaxError"
+      );
+    }
+    if (wsURL.hash !== "" || wsURL.href.endsWith("#")) {
+      throw new DOMException(
+        `The URL contains a fragment identifier ('${wsURL.hash}'). Fragment identifiers are not allowed in WebSocket URLs.`,
+        "SyntaxError"
+      );
+    }
+    if (typeof protocols === "string") {
+      protocols = [protocols];
+    }
+    if (new Set(protocols).size !== protocols.length) {
+      throw new DOMException(
+        "The subprotocols can't contain duplicates.",
+        "SyntaxError"
+      );
+    }
+    this.#url = wsURL.href;
+
+    wsOps.checkPermission(this.#url);
+    wsOps.create(this.#url, protocols).then(
+      ({ rid, protocol }): void => {
+        if (this.#readyState === CLOSING) {
+          // `close()` was called while connecting.
+          close(rid);
+          this.#fail();
+          return;
+        }
+        this.#opened(rid, protocol);
+      },
+      (): void => this.#fail()
+    );
+  }
+
+  get url(): string {
+    return this.#url;
+  }
+
+  get readyState(): number {
+    return this.#readyState;
+  }
+
+  get protocol(): string {
+    return this.#protocol;
+  }
+
+  get extensions(): string {
+    return "";
+  }
+
+  get bufferedAmount(): number {
+    return this.#bufferedAmount;
+  }
+
+  get binaryType(): BinaryType {
+    return this.#binaryType;
+  }
+
+  set binaryType(value: BinaryType) {
+    if (value === "blob" || value === "arraybuffer") {
+      this.#binaryType = value;
+    }
+  }
+
+  send(data: string | ArrayBufferLike | Blob | ArrayBufferView): void {
+    requiredArguments("WebSocket.send", arguments.length, 1);
+    if (this.#readyState === CONNECTING) {
+      throw new DOMException(
+        "The WebSocket is still connecting.",
+        "InvalidStateError"
+      );
+    }
+
+    let kind: wsOps.MessageKind = "binary";
+    let payload: string | Uint8Array;
+    let size: number;
+    if (typeof data === "string") {
+      kind = "text";
+      payload = data;
+      size = new TextEncoder().encode(data).byteLength;
+    } else {
+      if (data instanceof DenoBlob) {
+        payload = data[blobBytesSymbol];
+      } else if (ArrayBuffer.isView(data)) {
+        payload = new Uint8Array(data.buffer, data.byteOffset, data.byteLength);
+      } else {
+        payload = new Uint8Array(data as ArrayBufferLike);
+      }
+      size = payload.byteLength;
+    }
+
+    // Data sent after closing is discarded, but still counted.
+    this.#bufferedAmount += size;
+    if (this.#readyState !== OPEN) {
+      return;
+    }
+    wsOps.send(this.#rid!, kind, payload).then(
+      (): void => {
+        this.#bufferedAmount -= size;
+      },
+      (): void => this.#fail()
+    );
+  }
+
+  close(code?: number, reason?: string): void {
+    if (
+      code !== undefined &&
+      !(code === 1000 || (code >= 3000 && code <= 4999))
+    ) {
+      throw new DOMException(
+        "The close code must be either 1000, or between 3000 and 4999.",
+        "InvalidAccessError"
+      );
+    }
+    if (
+      reason !== undefined &&
+      new TextEncoder().encode(reason).byteLength > 123
+    ) {
+      throw new DOMException(
+        "The close reason may not be longer than 123 bytes.",
+        "SyntaxError"
+      );
+    }
+
+    if (this.#readyState === CONNECTING) {
+      this.#readyState = CLOSING;
+    } else if (this.#readyState === OPEN) {
+      this.#readyState = CLOSING;
+      if (code === undefined && reason !== undefined) {
+        code = 1000;
+      }
+      // The event loop reports the close once the peer has answered.
+      wsOps.close(this.#rid!, code, reason).catch((): void => this.#fail());
+    }
+  }
+
+  get [Symbol.toStringTag](): string {
+    return "WebSocket";
+  }
+
+  #opened = (rid: number, protocol: string): void => {
+    this.#rid = rid;
+    this.#protocol = protocol;
+    this.#readyState = OPEN;
+    const event = new Event("open");
+    if (this.onopen) {
+      this.onopen(event);
+    }
+    this.dispatchEvent(event);
+    this.#eventLoop();
+  };
+
+  #eventLoop = async (): Promise<void> => {
+    while (this.#readyState === OPEN || this.#readyState === CLOSING) {
+      let event: wsOps.WebSocketEvent;
+      try {
+        event = await wsOps.nextEvent(this.#rid!);
+      } catch (err) {
+        event = { kind: "error", message: err.message };
+      }
+
+      switch (event.kind) {
+        case "string":
+          this.#message(event.data);
+          break;
+        case "binary": {
+          const bytes = new Uint8Array(event.data);
+          this.#message(
+            this.#binaryType === "blob" ? new DenoBlob([bytes]) : bytes.buffer
+          );
+          break;
+        }
+        case "close":
+          this.#closed(event.code, event.reason, true);
+          break;
+        case "error":
+          this.#fail();
+          break;
+      }
+    }
+  };
+
+  #message = (data: string | Blob | ArrayBuffer): void => {
+    const event = new MessageEvent("message", {
+      data,
+      origin: new URL(this.#url).origin,
+    });
+    if (this.onmessage) {
+      this.onmessage(event);
+    }
+    this.dispatchEvent(event);
+  };
+
+  /** Fails the connection, with an error event before the close event. */
+  #fail = (): void => {
+    if (this.#readyState === CLOSED) {
+      return;
+    }
+    const event = new Event("error");
+    if (this.onerror) {
+      this.onerror(event);
+    }
+    this.dispatchEvent(event);
+    // 1006: the connection was closed abnormally.
+    this.#closed(1006, "", false);
+  };
+
+  #closed = (code: number, reason: string, wasClean: boolean): void => {
+    if (this.#readyState === CLOSED) {
+      return;
+    }
+    this.#readyState = CLOSED;
+    if (this.#rid !== undefined) {
+      try {
+        close(this.#rid);
+      } catch {
+        // The resource may be gone already.
+      }
+    }
+    const event = new CloseEvent("close", { wasClean, code, reason });
+    if (this.onclose) {
+      this.onclose(event);
+    }
+    this.dispatchEvent(event);
+  };
+}
+
+export interface UpgradeWebSocketOptions {
+  protocols?: string[];
+}
+
+export interface WebSocketUpgrade {
+  socket: WebSocket;
+  url: string;
+  headers: Headers;
+}
+
+/** Performs the WebSocket handshake on an accepted connection, which can't be
+ * used directly anymore afterwards. */
+export async function upgradeWebSocket(
+  conn: Conn,
+  options: UpgradeWebSocketOptions = {}
+): Promise<WebSocketUpgrade> {
+  const accepted = await wsOps.accept(conn.rid, options.protocols ?? []);
+  const socket = new WebSocketImpl(accepted.url, {
+    [acceptedSymbol]: accepted,
+  });
+  return {
+    socket,
+    url: accepted.url,
+    headers: new Headers(accepted.headers),
+  };
+}
@@ -5,6 +5,7 @@ import {
   hostTerminateWorker,
   hostPostMessage,
   hostGetMessage,
+  WorkerPermissions,
 } from "../ops/worker_host.ts";
 import { log } from "../util.ts";
 import { TextDecoder, TextEncoder } from "./text_encoding.ts";
@@ -41,6 +42,18 @@ export class MessageEvent extends Event {
   }
 }
 
+/** Overrides the time stamp of a message event with the (coarsened) time at
+ * which the runtime received the message. */
+export function setMessageTimeStamp(
+  event: MessageEvent,
+  timeStamp: number
+): void {
+  Reflect.defineProperty(event, "timeStamp", {
+    enumerable: true,
+    value: timeStamp,
+  });
+}
+
 export interface ErrorEventInit extends EventInit {
   message?: string;
   filename?: string;
@@ -91,6 +104,7 @@ interface WorkerHostError {
 interface WorkerHostMessage {
   type: "terminalError" | "error" | "msg";
   data?: any;
+  timeStamp?: number;
   error?: WorkerHostError;
 }
 
@@ -105,7 +119,7 @@ export interface Worker {
 export interface WorkerOptions {
   type?: "classic" | "module";
   name?: string;
-  deno?: boolean;
+  deno?: boolean | { permissions?: WorkerPermissions };
 }
 
 export class WorkerImpl extends EventTarget implements Worker {
@@ -148,19 +162,22 @@ export class WorkerImpl extends EventTarget implements Worker {
     */
 
     const useDenoNamespace = options ? !!options.deno : false;
+    const permissions =
+      typeof options?.deno === "object" ? options.deno.permissions : undefined;
 
     const { id } = createWorker(
       specifier,
       hasSourceCode,
       sourceCode,
       useDenoNamespace,
-      options?.name
+      options?.name,
+      permissions
     );
     this.#id = id;
     this.#poll();
   }
 
-  #handleMessage = (msgData: any): void => {
+  #handleMessage = (msgData: any, timeStamp?: number): void => {
     let data;
     try {
       data = decodeMessage(new Uint8Array(msgData));
@@ -179,6 +196,9 @@ export class WorkerImpl extends EventTarget implements Worker {
       cancelable: false,
       data,
     });
+    if (timeStamp !== undefined) {
+      setMessageTimeStamp(msgEvent, timeStamp);
+    }
 
     if (this.onmessage) {
       this.onmessage(msgEvent);
@@ -230,7 +250,7 @@ export class WorkerImpl extends EventTarget implements Worker {
       }
 
       if (type === "msg") {
-        this.#handleMessage(event.data);
+        this.#handleMessage(event.data, event.timeStamp);
         continue;
       }
 
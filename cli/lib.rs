@@ -22,6 +22,7 @@ extern crate tokio;
 extern crate url;
 
 mod checksum;
+mod coarse_time;
 pub mod colors;
 pub mod compilers;
 pub mod deno_dir;
@@ -46,6 +47,8 @@ mod metrics;
 pub mod msg;
 pub mod op_error;
 pub mod ops;
+mod permission_policy;
+mod permission_store;
 pub mod permissions;
 mod repl;
 pub mod resolve_addr;
@@ -60,6 +63,7 @@ mod upgrade;
 pub mod version;
 mod web_worker;
 pub mod worker;
+mod x509;
 
 pub use dprint_plugin_typescript::swc_common;
 pub use dprint_plugin_typescript::swc_ecma_ast;
@@ -297,6 +301,49 @@ async fn install_command(
     .map_err(ErrBox::from)
 }
 
+async fn permissions_command(
+  clear: bool,
+  module: Option<String>,
+) -> Result<(), ErrBox> {
+  let custom_root = env::var("DENO_DIR").map(String::into).ok();
+  let dir = deno_dir::DenoDir::new(custom_root)?;
+  let main_module = match module {
+    Some(module) => Some(ModuleSpecifier::resolve_url_or_path(&module)?),
+    None => None,
+  };
+  if clear {
+    let count = permission_store::clear(&dir.root, main_module.as_ref())?;
+    println!("Forgot the permissions of {} main module(s)", count);
+    return Ok(());
+  }
+  for (module, grants) in
+    permission_store::list(&dir.root, main_module.as_ref())?
+  {
+    println!("{}", colors::bold(module));
+    for (name, grant) in &[
+      ("read", &grants.read),
+      ("write", &grants.write),
+      ("net", &grants.net),
+      ("env", &grants.env),
+      ("run", &grants.run),
+    ] {
+      if grant.is_all() {
+        println!("  {}", name);
+      }
+      for entry in grant.list() {
+        println!("  {} {}", name, entry);
+      }
+    }
+    if grants.plugin {
+      println!("  plugin");
+    }
+    if grants.hrtime {
+      println!("  hrtime");
+    }
+  }
+  Ok(())
+}
+
 async fn cache_command(flags: Flags, files: Vec<String>) -> Result<(), ErrBox> {
   let main_module =
     ModuleSpecifier::resolve_url_or_path("./__$deno$fetch.ts").unwrap();
@@ -579,6 +626,9 @@ pub fn main() {
       force,
     } => install_command(flags, root, exe_name, module_url, args, force)
       .boxed_local(),
+    DenoSubcommand::Permissions { clear, module } => {
+      permissions_command(clear, module).boxed_local()
+    }
     DenoSubcommand::Repl => run_repl(flags).boxed_local(),
     DenoSubcommand::Run { script } => run_command(flags, script).boxed_local(),
     DenoSubcommand::Test {
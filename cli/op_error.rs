@@ -22,6 +22,9 @@ use std::env::VarError;
 use std::error::Error;
 use std::fmt;
 use std::io;
+use tokio_tungstenite::tungstenite;
+use trust_dns_resolver::error::ResolveError;
+use trust_dns_resolver::error::ResolveErrorKind;
 
 // Warning! The values in this enum are duplicated in js/errors.ts
 // Update carefully!
@@ -263,6 +266,75 @@ impl From<&reqwest::Error> for OpError {
   }
 }
 
+impl From<hyper::Error> for OpError {
+  fn from(error: hyper::Error) -> Self {
+    OpError::from(&error)
+  }
+}
+
+impl From<&hyper::Error> for OpError {
+  fn from(error: &hyper::Error) -> Self {
+    match error.source().and_then(|e| e.downcast_ref::<io::Error>()) {
+      Some(io_error) => io_error.into(),
+      None if error.is_closed() => Self {
+        kind: ErrorKind::ConnectionReset,
+        msg: error.to_string(),
+      },
+      None => Self {
+        kind: ErrorKind::Http,
+        msg: error.to_string(),
+      },
+    }
+  }
+}
+
+impl From<tungstenite::Error> for OpError {
+  fn from(error: tungstenite::Error) -> Self {
+    OpError::from(&error)
+  }
+}
+
+impl From<&tungstenite::Error> for OpError {
+  fn from(error: &tungstenite::Error) -> Self {
+    use tungstenite::Error::*;
+    let kind = match error {
+      Io(io_error) => return io_error.into(),
+      ConnectionClosed | AlreadyClosed => ErrorKind::ConnectionReset,
+      Url(_) => ErrorKind::URIError,
+      Http(_) => ErrorKind::Http,
+      Protocol(_) | Utf8 => ErrorKind::InvalidData,
+      _ => ErrorKind::Other,
+    };
+
+    Self {
+      kind,
+      msg: error.to_string(),
+    }
+  }
+}
+
+impl From<ResolveError> for OpError {
+  fn from(error: ResolveError) -> Self {
+    OpError::from(&error)
+  }
+}
+
+impl From<&ResolveError> for OpError {
+  fn from(error: &ResolveError) -> Self {
+    let kind = match error.kind() {
+      ResolveErrorKind::Io(io_error) => return io_error.into(),
+      ResolveErrorKind::NoRecordsFound { .. } => ErrorKind::NotFound,
+      ResolveErrorKind::Timeout => ErrorKind::TimedOut,
+      _ => ErrorKind::Other,
+    };
+
+    Self {
+      kind,
+      msg: error.to_string(),
+    }
+  }
+}
+
 impl From<ReadlineError> for OpError {
   fn from(error: ReadlineError) -> Self {
     OpError::from(&error)
@@ -400,6 +472,9 @@ impl From<ErrBox> for OpError {
           .map(|e| OpError::new(e.kind, e.msg.to_string()))
       })
       .or_else(|| error.downcast_ref::<reqwest::Error>().map(|e| e.into()))
+      .or_else(|| error.downcast_ref::<hyper::Error>().map(|e| e.into()))
+      .or_else(|| error.downcast_ref::<tungstenite::Error>().map(|e| e.into()))
+      .or_else(|| error.downcast_ref::<ResolveError>().map(|e| e.into()))
       .or_else(|| error.downcast_ref::<ImportMapError>().map(|e| e.into()))
       .or_else(|| error.downcast_ref::<io::Error>().map(|e| e.into()))
       .or_else(|| {
@@ -1,25 +1,68 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 use super::dispatch_json::{Deserialize, JsonOp, Value};
-use super::io::{StreamResource, StreamResourceHolder};
-use crate::http_util::{create_http_client, HttpBody};
+use super::io::{DenoAsyncRead, StreamResource, StreamResourceHolder};
+use crate::file_fetcher::map_file_content_type;
+use crate::fs::{resolve_from_cwd, resolve_real_path};
+use crate::http_util::{
+  build_http_client, parse_data_url, parse_proxy_url, HttpBody,
+  HttpClientOptions,
+};
 use crate::op_error::OpError;
 use crate::state::State;
+use bytes::Bytes;
 use deno_core::*;
+use futures::channel::mpsc;
+use futures::future::poll_fn;
+use futures::future::Future;
 use futures::future::FutureExt;
+use futures::ready;
+use futures::sink::SinkExt;
+use futures::stream::StreamExt;
 use http::header::HeaderName;
 use http::header::HeaderValue;
 use http::Method;
+use std::cell::RefCell;
+use std::cmp::min;
 use std::convert::From;
+use std::io;
+use std::path::Path;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Waker;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// Size of the chunks in which streaming request bodies are read.
+const BODY_CHUNK_SIZE: usize = 64 * 1024;
 
 pub fn init(i: &mut Isolate, s: &State) {
   i.register_op("op_fetch", s.stateful_json_op2(op_fetch));
+  i.register_op(
+    "op_create_request_body",
+    s.stateful_json_op2(op_create_request_body),
+  );
+  i.register_op(
+    "op_create_fetch_cancel_handle",
+    s.stateful_json_op2(op_create_fetch_cancel_handle),
+  );
+  i.register_op("op_cancel_fetch", s.stateful_json_op2(op_cancel_fetch));
+  i.register_op(
+    "op_create_http_client",
+    s.stateful_json_op2(op_create_http_client),
+  );
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct FetchArgs {
   method: Option<String>,
   url: String,
   headers: Vec<(String, String)>,
+  body_rid: Option<u32>,
+  cancel_rid: Option<u32>,
+  client_rid: Option<u32>,
 }
 
 pub fn op_fetch(
@@ -31,8 +74,36 @@ pub fn op_fetch(
   let args: FetchArgs = serde_json::from_value(args)?;
   let url = args.url;
 
-  let client =
-    create_http_client(state.borrow().global_state.flags.ca_file.clone())?;
+  // The body is only taken below, once the request can't be rejected anymore,
+  // so that a rejected request leaves it to the caller.
+  if let Some(rid) = args.body_rid {
+    let resource_table = isolate.resource_table.borrow();
+    if resource_table.get::<StreamResourceHolder>(rid).is_none() {
+      return Err(OpError::bad_resource_id());
+    }
+  }
+
+  let cancel = match args.cancel_rid {
+    Some(rid) => {
+      let resource_table = isolate.resource_table.borrow();
+      let resource = resource_table
+        .get::<FetchCancelResource>(rid)
+        .ok_or_else(OpError::bad_resource_id)?;
+      Some(resource.0.clone())
+    }
+    None => None,
+  };
+
+  let client = match args.client_rid {
+    Some(rid) => {
+      let resource_table = isolate.resource_table.borrow();
+      let resource = resource_table
+        .get::<HttpClientResource>(rid)
+        .ok_or_else(OpError::bad_resource_id)?;
+      resource.0.clone()
+    }
+    None => state.borrow().global_state.http_client.clone(),
+  };
 
   let method = match args.method {
     Some(method_str) => Method::from_bytes(method_str.as_bytes())
@@ -44,6 +115,24 @@ pub fn op_fetch(
 
   // Check scheme before asking for net permission
   let scheme = url_.scheme();
+  if scheme == "file" || scheme == "data" {
+    if method != Method::GET {
+      return Err(OpError::type_error(format!(
+        "method '{}' not supported for {} URLs",
+        method, scheme
+      )));
+    }
+    let op = if scheme == "file" {
+      op_fetch_file(isolate, state, url_, cancel)?
+    } else {
+      op_fetch_data(isolate, url_, cancel)?
+    };
+    // The body isn't sent, but it is owned by the request all the same.
+    if let Some(rid) = args.body_rid {
+      take_request_body(&mut isolate.resource_table.borrow_mut(), rid);
+    }
+    return Ok(op);
+  }
   if scheme != "http" && scheme != "https" {
     return Err(OpError::type_error(format!(
       "scheme '{}' not supported",
@@ -53,9 +142,20 @@ pub fn op_fetch(
 
   state.check_net_url(&url_)?;
 
+  let body_resource = args.body_rid.map(|rid| {
+    take_request_body(&mut isolate.resource_table.borrow_mut(), rid)
+  });
+
   let mut request = client.request(method, url_);
 
-  if let Some(buf) = data {
+  let mut body_pump = None;
+  if let Some(resource_holder) = body_resource {
+    // The channel holds a single chunk, so the resource is only read as fast
+    // as the body is sent.
+    let (sender, receiver) = mpsc::channel(1);
+    request = request.body(reqwest::Body::wrap_stream(receiver));
+    body_pump = Some(pump_body(resource_holder, sender));
+  } else if let Some(buf) = data {
     request = request.body(Vec::from(&*buf));
   }
 
@@ -68,7 +168,22 @@ pub fn op_fetch(
 
   let resource_table = isolate.resource_table.clone();
   let future = async move {
-    let res = request.send().await?;
+    let send = async move {
+      // The response resolves once the whole request body has been sent.
+      let (res, pumped) = match body_pump {
+        Some(body_pump) => {
+          futures::future::join(request.send(), body_pump).await
+        }
+        None => (request.send().await, Ok(())),
+      };
+      // An error reading the body is what made the request fail.
+      pumped?;
+      Ok::<_, OpError>(res?)
+    };
+    let res = match &cancel {
+      Some(cancel) => cancel.or_cancelled(send).await?,
+      None => send.await?,
+    };
     debug!("Fetch response {}", url);
     let status = res.status();
     let mut res_headers = Vec::new();
@@ -76,14 +191,12 @@ pub fn op_fetch(
       res_headers.push((key.to_string(), val.to_str().unwrap().to_owned()));
     }
 
-    let body = HttpBody::from(res);
+    let body = FetchBody {
+      body: Box::new(HttpBody::from(res)),
+      cancel,
+    };
     let mut resource_table = resource_table.borrow_mut();
-    let rid = resource_table.add(
-      "httpBody",
-      Box::new(StreamResourceHolder::new(StreamResource::HttpBody(
-        Box::new(body),
-      ))),
-    );
+    let rid = add_fetch_body(&mut resource_table, body);
 
     let json_res = json!({
       "bodyRid": rid,
@@ -97,3 +210,415 @@ pub fn op_fetch(
 
   Ok(JsonOp::Async(future.boxed_local()))
 }
+
+/// Takes the body resource `rid` of a request out of the resource table. The
+/// request owns it from then on, and closes it once it has been sent or the
+/// request failed.
+fn take_request_body(
+  resource_table: &mut ResourceTable,
+  rid: u32,
+) -> Box<StreamResourceHolder> {
+  resource_table.remove::<StreamResourceHolder>(rid).unwrap()
+}
+
+/// Responds to a `file:` URL with the contents of the file, if it can be
+/// read.
+fn op_fetch_file(
+  isolate: &mut deno_core::Isolate,
+  state: &State,
+  url: url::Url,
+  cancel: Option<FetchCancel>,
+) -> Result<JsonOp, OpError> {
+  let path = url
+    .to_file_path()
+    .map_err(|()| OpError::type_error(format!("Invalid file URL: {}", url)))?;
+  state.check_read(&path)?;
+
+  let resource_table = isolate.resource_table.clone();
+  let future = async move {
+    let open = async { Ok::<_, OpError>(tokio::fs::File::open(&path).await?) };
+    let file = match &cancel {
+      Some(cancel) => cancel.or_cancelled(open).await?,
+      None => open.await?,
+    };
+    let content_type = map_file_content_type(&path);
+    let body = FetchBody {
+      body: Box::new(file),
+      cancel,
+    };
+    let mut resource_table = resource_table.borrow_mut();
+    let rid = add_fetch_body(&mut resource_table, body);
+    Ok(local_response(rid, content_type))
+  };
+
+  Ok(JsonOp::Async(future.boxed_local()))
+}
+
+/// Responds to a `data:` URL with its decoded payload.
+fn op_fetch_data(
+  isolate: &mut deno_core::Isolate,
+  url: url::Url,
+  cancel: Option<FetchCancel>,
+) -> Result<JsonOp, OpError> {
+  let (content_type, data) =
+    parse_data_url(&url).map_err(|e| OpError::type_error(e.to_string()))?;
+  let body = FetchBody {
+    body: Box::new(io::Cursor::new(data)),
+    cancel,
+  };
+  let mut resource_table = isolate.resource_table.borrow_mut();
+  let rid = add_fetch_body(&mut resource_table, body);
+  let response = local_response(rid, &content_type);
+  Ok(JsonOp::Async(
+    futures::future::ok::<_, OpError>(response).boxed_local(),
+  ))
+}
+
+/// The response to a `file:` or `data:` URL, which has no status of its own.
+fn local_response(body_rid: u32, content_type: &str) -> Value {
+  json!({
+    "bodyRid": body_rid,
+    "status": 200,
+    "statusText": "OK",
+    "headers": [["content-type", content_type]]
+  })
+}
+
+fn add_fetch_body(resource_table: &mut ResourceTable, body: FetchBody) -> u32 {
+  resource_table.add(
+    "httpBody",
+    Box::new(StreamResourceHolder::new(StreamResource::FetchBody(
+      Box::new(body),
+    ))),
+  )
+}
+
+/// A client created with `Deno.createHttpClient()`, whose connections are
+/// reused by every fetch that passes it.
+struct HttpClientResource(reqwest::Client);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateHttpClientArgs {
+  ca_file: Option<String>,
+  ca_data: Option<String>,
+  cert_chain: Option<String>,
+  private_key: Option<String>,
+  proxy: Option<String>,
+  timeout: Option<u64>,
+  connect_timeout: Option<u64>,
+  pool_max_idle_per_host: Option<usize>,
+  pool_idle_timeout: Option<u64>,
+  #[serde(default)]
+  http2_prior_knowledge: bool,
+}
+
+fn op_create_http_client(
+  isolate: &mut deno_core::Isolate,
+  state: &State,
+  args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  let args: CreateHttpClientArgs = serde_json::from_value(args)?;
+
+  let ca_file = match args.ca_file {
+    Some(ca_file) => {
+      let path = resolve_real_path(&resolve_from_cwd(Path::new(&ca_file))?);
+      state.check_read(&path)?;
+      Some(path.to_string_lossy().to_string())
+    }
+    None => None,
+  };
+
+  // Every request of the client is sent to the proxy, whatever its target.
+  if let Some(proxy) = &args.proxy {
+    let url = parse_proxy_url(proxy, "the proxy option")?;
+    let host = url
+      .host_str()
+      .ok_or_else(|| OpError::uri_error("missing host".to_owned()))?;
+    state.check_net(host, url.port_or_known_default().unwrap())?;
+  }
+
+  let client = build_http_client(HttpClientOptions {
+    ca_file,
+    ca_data: args.ca_data,
+    cert_chain: args.cert_chain,
+    private_key: args.private_key,
+    proxy: args.proxy,
+    timeout: args.timeout.map(Duration::from_millis),
+    connect_timeout: args.connect_timeout.map(Duration::from_millis),
+    pool_max_idle_per_host: args.pool_max_idle_per_host,
+    pool_idle_timeout: args.pool_idle_timeout.map(Duration::from_millis),
+    http2_prior_knowledge: args.http2_prior_knowledge,
+  })?;
+
+  let mut resource_table = isolate.resource_table.borrow_mut();
+  let rid =
+    resource_table.add("httpClient", Box::new(HttpClientResource(client)));
+  Ok(JsonOp::Sync(json!(rid)))
+}
+
+/// Why a fetch was cancelled.
+#[derive(Clone, Copy)]
+enum CancelReason {
+  Abort,
+  Timeout,
+}
+
+impl CancelReason {
+  fn to_error(self) -> io::Error {
+    match self {
+      CancelReason::Abort => io::Error::new(
+        io::ErrorKind::ConnectionAborted,
+        "The request was aborted",
+      ),
+      CancelReason::Timeout => {
+        io::Error::new(io::ErrorKind::TimedOut, "The request timed out")
+      }
+    }
+  }
+}
+
+#[derive(Default)]
+struct FetchCancelState {
+  reason: Option<CancelReason>,
+  wakers: Vec<Waker>,
+}
+
+/// Cancels a fetch: the connect, the wait for the response and reads of the
+/// response body all fail once it is cancelled.
+#[derive(Clone, Default)]
+pub struct FetchCancel(Rc<RefCell<FetchCancelState>>);
+
+impl FetchCancel {
+  fn cancel(&self, reason: CancelReason) {
+    let mut state = self.0.borrow_mut();
+    if state.reason.is_none() {
+      state.reason = Some(reason);
+    }
+    for waker in state.wakers.drain(..) {
+      waker.wake();
+    }
+  }
+
+  fn poll_cancelled(&self, cx: &mut Context) -> Poll<io::Error> {
+    let mut state = self.0.borrow_mut();
+    match state.reason {
+      Some(reason) => Poll::Ready(reason.to_error()),
+      None => {
+        if !state.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+          state.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+      }
+    }
+  }
+
+  /// Runs `future` until it completes or the fetch is cancelled. The future is
+  /// dropped on cancellation, which aborts the request.
+  async fn or_cancelled<T>(
+    &self,
+    future: impl Future<Output = Result<T, OpError>>,
+  ) -> Result<T, OpError> {
+    futures::pin_mut!(future);
+    poll_fn(|cx| {
+      if let Poll::Ready(e) = self.poll_cancelled(cx) {
+        return Poll::Ready(Err(OpError::from(e)));
+      }
+      future.as_mut().poll(cx)
+    })
+    .await
+  }
+}
+
+struct FetchCancelResource(FetchCancel);
+
+/// The body of a fetch response, read with `op_read`.
+pub struct FetchBody {
+  body: Box<dyn AsyncRead + Unpin>,
+  cancel: Option<FetchCancel>,
+}
+
+impl AsyncRead for FetchBody {
+  fn poll_read(
+    self: Pin<&mut Self>,
+    cx: &mut Context,
+    buf: &mut [u8],
+  ) -> Poll<Result<usize, io::Error>> {
+    let inner = self.get_mut();
+    if let Some(cancel) = &inner.cancel {
+      if let Poll::Ready(e) = cancel.poll_cancelled(cx) {
+        return Poll::Ready(Err(e));
+      }
+    }
+    Pin::new(&mut inner.body).poll_read(cx, buf)
+  }
+}
+
+fn op_create_fetch_cancel_handle(
+  isolate: &mut deno_core::Isolate,
+  _state: &State,
+  _args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  let mut resource_table = isolate.resource_table.borrow_mut();
+  let rid = resource_table.add(
+    "fetchCancelHandle",
+    Box::new(FetchCancelResource(FetchCancel::default())),
+  );
+  Ok(JsonOp::Sync(json!(rid)))
+}
+
+#[derive(Deserialize)]
+struct CancelFetchArgs {
+  rid: i32,
+  reason: String,
+}
+
+fn op_cancel_fetch(
+  isolate: &mut deno_core::Isolate,
+  _state: &State,
+  args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  let args: CancelFetchArgs = serde_json::from_value(args)?;
+  let reason = match args.reason.as_str() {
+    "abort" => CancelReason::Abort,
+    "timeout" => CancelReason::Timeout,
+    _ => {
+      return Err(OpError::type_error(format!(
+        "Invalid cancel reason '{}'",
+        args.reason
+      )))
+    }
+  };
+  let resource_table = isolate.resource_table.borrow();
+  let resource = resource_table
+    .get::<FetchCancelResource>(args.rid as u32)
+    .ok_or_else(OpError::bad_resource_id)?;
+  resource.0.cancel(reason);
+  Ok(JsonOp::Sync(json!({})))
+}
+
+/// Reads the request body from a resource and feeds it, chunk by chunk, to
+/// the request. Stops without an error if the request was dropped.
+async fn pump_body(
+  mut resource_holder: Box<StreamResourceHolder>,
+  mut sender: mpsc::Sender<Result<Bytes, io::Error>>,
+) -> Result<(), OpError> {
+  let mut buf = vec![0u8; BODY_CHUNK_SIZE];
+  loop {
+    let result =
+      poll_fn(|cx| resource_holder.resource.poll_read(cx, &mut buf)).await;
+    let (chunk, result) = match result {
+      Ok(0) => return Ok(()),
+      Ok(n) => (Ok(Bytes::copy_from_slice(&buf[..n])), Ok(())),
+      Err(e) => (
+        Err(io::Error::new(io::ErrorKind::Other, e.to_string())),
+        Err(e),
+      ),
+    };
+    if sender.send(chunk).await.is_err() {
+      return Ok(());
+    }
+    result?;
+  }
+}
+
+/// The end of a request body pipe that JavaScript writes to with `op_write`,
+/// e.g. the chunks of a `ReadableStream`. Closing the resource ends the body.
+pub struct RequestBodyWriter {
+  sender: mpsc::Sender<Bytes>,
+}
+
+impl AsyncWrite for RequestBodyWriter {
+  fn poll_write(
+    self: Pin<&mut Self>,
+    cx: &mut Context,
+    buf: &[u8],
+  ) -> Poll<Result<usize, io::Error>> {
+    let inner = self.get_mut();
+    ready!(inner.sender.poll_ready(cx))
+      .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+    inner
+      .sender
+      .start_send(Bytes::copy_from_slice(buf))
+      .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+    Poll::Ready(Ok(buf.len()))
+  }
+
+  fn poll_flush(
+    self: Pin<&mut Self>,
+    _cx: &mut Context,
+  ) -> Poll<Result<(), io::Error>> {
+    Poll::Ready(Ok(()))
+  }
+
+  fn poll_shutdown(
+    self: Pin<&mut Self>,
+    _cx: &mut Context,
+  ) -> Poll<Result<(), io::Error>> {
+    self.get_mut().sender.close_channel();
+    Poll::Ready(Ok(()))
+  }
+}
+
+/// The end of a request body pipe that is passed to `op_fetch` as `bodyRid`.
+pub struct RequestBodyReader {
+  receiver: mpsc::Receiver<Bytes>,
+  chunk: Option<Bytes>,
+}
+
+impl AsyncRead for RequestBodyReader {
+  fn poll_read(
+    self: Pin<&mut Self>,
+    cx: &mut Context,
+    buf: &mut [u8],
+  ) -> Poll<Result<usize, io::Error>> {
+    let inner = self.get_mut();
+    loop {
+      match inner.chunk.take() {
+        Some(mut chunk) if !chunk.is_empty() => {
+          let n = min(buf.len(), chunk.len());
+          buf[..n].copy_from_slice(&chunk.split_to(n));
+          inner.chunk = Some(chunk);
+          return Poll::Ready(Ok(n));
+        }
+        _ => {}
+      }
+      match ready!(inner.receiver.poll_next_unpin(cx)) {
+        Some(chunk) => inner.chunk = Some(chunk),
+        None => return Poll::Ready(Ok(0)),
+      }
+    }
+  }
+}
+
+fn op_create_request_body(
+  isolate: &mut deno_core::Isolate,
+  _state: &State,
+  _args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  let (sender, receiver) = mpsc::channel(1);
+  let mut resource_table = isolate.resource_table.borrow_mut();
+  let writer_rid = resource_table.add(
+    "requestBodyWriter",
+    Box::new(StreamResourceHolder::new(
+      StreamResource::RequestBodyWriter(Box::new(RequestBodyWriter { sender })),
+    )),
+  );
+  let reader_rid = resource_table.add(
+    "requestBodyReader",
+    Box::new(StreamResourceHolder::new(
+      StreamResource::RequestBodyReader(Box::new(RequestBodyReader {
+        receiver,
+        chunk: None,
+      })),
+    )),
+  );
+  Ok(JsonOp::Sync(json!({
+    "writerRid": writer_rid,
+    "readerRid": reader_rid,
+  })))
+}
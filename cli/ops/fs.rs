@@ -3,7 +3,9 @@
 use super::dispatch_json::{blocking_json, Deserialize, JsonOp, Value};
 use super::io::std_file_resource;
 use super::io::{FileMetadata, StreamResource, StreamResourceHolder};
+use crate::coarse_time;
 use crate::fs::resolve_from_cwd;
+use crate::fs::{resolve_real_parent, resolve_real_path};
 use crate::op_error::OpError;
 use crate::ops::dispatch_json::JsonResult;
 use crate::state::State;
@@ -13,7 +15,7 @@ use futures::future::FutureExt;
 use std::convert::From;
 use std::env::{current_dir, set_current_dir, temp_dir};
 use std::path::{Path, PathBuf};
-use std::time::UNIX_EPOCH;
+use std::time::{Duration, UNIX_EPOCH};
 
 use rand::{thread_rng, Rng};
 
@@ -74,11 +76,21 @@ fn op_open(
   _zero_copy: Option<ZeroCopyBuf>,
 ) -> Result<JsonOp, OpError> {
   let args: OpenArgs = serde_json::from_value(args)?;
-  let path = resolve_from_cwd(Path::new(&args.path))?;
+  // The file is opened by the real path that the permissions are checked
+  // for, so swapping a symlink into the path after the check has no effect.
+  let path = resolve_real_path(&resolve_from_cwd(Path::new(&args.path))?);
   let resource_table = isolate.resource_table.clone();
 
   let mut open_options = std::fs::OpenOptions::new();
 
+  // The last component of a real path is only a symlink if one was created
+  // since the path was resolved.
+  #[cfg(unix)]
+  {
+    use std::os::unix::fs::OpenOptionsExt;
+    open_options.custom_flags(libc::O_NOFOLLOW);
+  }
+
   if let Some(mode) = args.mode {
     // mode only used if creating the file on Unix
     // if not specified, defaults to 0o666
@@ -440,7 +452,8 @@ fn op_remove(
   let path = resolve_from_cwd(Path::new(&args.path))?;
   let recursive = args.recursive;
 
-  state.check_write(&path)?;
+  // A symlink is removed itself, not its target.
+  state.check_write_entry(&path)?;
 
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
@@ -472,8 +485,8 @@ fn op_copy_file(
   _zero_copy: Option<ZeroCopyBuf>,
 ) -> Result<JsonOp, OpError> {
   let args: CopyFileArgs = serde_json::from_value(args)?;
-  let from = resolve_from_cwd(Path::new(&args.from))?;
-  let to = resolve_from_cwd(Path::new(&args.to))?;
+  let from = resolve_real_path(&resolve_from_cwd(Path::new(&args.from))?);
+  let to = resolve_real_path(&resolve_from_cwd(Path::new(&args.to))?);
 
   state.check_read(&from)?;
   state.check_write(&to)?;
@@ -495,11 +508,17 @@ fn op_copy_file(
 }
 
 macro_rules! to_seconds {
-  ($time:expr) => {{
+  ($time:expr, $resolution:expr) => {{
     // Unwrap is safe here as if the file is before the unix epoch
     // something is very wrong.
     $time
-      .and_then(|t| Ok(t.duration_since(UNIX_EPOCH).unwrap().as_secs()))
+      .and_then(|t| {
+        let secs = t.duration_since(UNIX_EPOCH).unwrap().as_secs();
+        // Coarsen the seconds that are returned, coarsening the exact time
+        // could round it up into the next second.
+        let time = Duration::from_secs(secs);
+        Ok(coarse_time::coarsen(time, $resolution).as_secs())
+      })
       .unwrap_or(0)
   }};
 }
@@ -508,6 +527,7 @@ macro_rules! to_seconds {
 fn get_stat_json(
   metadata: std::fs::Metadata,
   maybe_name: Option<String>,
+  timer_resolution: Option<Duration>,
 ) -> JsonResult {
   // Unix stat member (number types only). 0 if not on unix.
   macro_rules! usm {
@@ -531,9 +551,10 @@ fn get_stat_json(
     "isSymlink": metadata.file_type().is_symlink(),
     "size": metadata.len(),
     // In seconds. Available on both Unix or Windows.
-    "modified":to_seconds!(metadata.modified()),
-    "accessed":to_seconds!(metadata.accessed()),
-    "created":to_seconds!(metadata.created()),
+    // Clamped to the timer resolution without the hrtime permission.
+    "modified":to_seconds!(metadata.modified(), timer_resolution),
+    "accessed":to_seconds!(metadata.accessed(), timer_resolution),
+    "created":to_seconds!(metadata.created(), timer_resolution),
     // Following are only valid under Unix.
     "dev": usm!(dev),
     "ino": usm!(ino),
@@ -575,7 +596,12 @@ fn op_stat(
   let path = resolve_from_cwd(Path::new(&args.path))?;
   let lstat = args.lstat;
 
-  state.check_read(&path)?;
+  if lstat {
+    state.check_read_entry(&path)?;
+  } else {
+    state.check_read(&path)?;
+  }
+  let timer_resolution = state.timer_resolution();
 
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
@@ -585,7 +611,7 @@ fn op_stat(
     } else {
       std::fs::metadata(&path)?
     };
-    get_stat_json(metadata, None)
+    get_stat_json(metadata, None, timer_resolution)
   })
 }
 
@@ -637,6 +663,7 @@ fn op_read_dir(
   let path = resolve_from_cwd(Path::new(&args.path))?;
 
   state.check_read(&path)?;
+  let timer_resolution = state.timer_resolution();
 
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
@@ -647,7 +674,9 @@ fn op_read_dir(
         let metadata = entry.metadata().unwrap();
         // Not all filenames can be encoded as UTF-8. Skip those for now.
         if let Ok(filename) = into_string(entry.file_name()) {
-          Some(get_stat_json(metadata, Some(filename)).unwrap())
+          Some(
+            get_stat_json(metadata, Some(filename), timer_resolution).unwrap(),
+          )
         } else {
           None
         }
@@ -672,12 +701,16 @@ fn op_rename(
   _zero_copy: Option<ZeroCopyBuf>,
 ) -> Result<JsonOp, OpError> {
   let args: RenameArgs = serde_json::from_value(args)?;
-  let oldpath = resolve_from_cwd(Path::new(&args.oldpath))?;
-  let newpath = resolve_from_cwd(Path::new(&args.newpath))?;
+  // Renaming moves the directory entries themselves, so only their parent
+  // directories are resolved, and a symlink is checked rather than its target.
+  let oldpath =
+    resolve_real_parent(&resolve_from_cwd(Path::new(&args.oldpath))?);
+  let newpath =
+    resolve_real_parent(&resolve_from_cwd(Path::new(&args.newpath))?);
 
-  state.check_read(&oldpath)?;
-  state.check_write(&oldpath)?;
-  state.check_write(&newpath)?;
+  state.check_read_entry(&oldpath)?;
+  state.check_write_entry(&oldpath)?;
+  state.check_write_entry(&newpath)?;
 
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
@@ -701,11 +734,14 @@ fn op_link(
   _zero_copy: Option<ZeroCopyBuf>,
 ) -> Result<JsonOp, OpError> {
   let args: LinkArgs = serde_json::from_value(args)?;
-  let oldpath = resolve_from_cwd(Path::new(&args.oldpath))?;
-  let newpath = resolve_from_cwd(Path::new(&args.newpath))?;
+  let oldpath =
+    resolve_real_parent(&resolve_from_cwd(Path::new(&args.oldpath))?);
+  let newpath =
+    resolve_real_parent(&resolve_from_cwd(Path::new(&args.newpath))?);
 
-  state.check_read(&oldpath)?;
-  state.check_write(&newpath)?;
+  // A hard link to a symlink links the symlink itself, not its target.
+  state.check_read_entry(&oldpath)?;
+  state.check_write_entry(&newpath)?;
 
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
@@ -729,10 +765,13 @@ fn op_symlink(
   _zero_copy: Option<ZeroCopyBuf>,
 ) -> Result<JsonOp, OpError> {
   let args: SymlinkArgs = serde_json::from_value(args)?;
+  // The target is stored as is. Access through the symlink is checked
+  // against the real path it points to, like any other path.
   let oldpath = resolve_from_cwd(Path::new(&args.oldpath))?;
-  let newpath = resolve_from_cwd(Path::new(&args.newpath))?;
+  let newpath =
+    resolve_real_parent(&resolve_from_cwd(Path::new(&args.newpath))?);
 
-  state.check_write(&newpath)?;
+  state.check_write_entry(&newpath)?;
 
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
@@ -769,7 +808,7 @@ fn op_read_link(
   let args: ReadLinkArgs = serde_json::from_value(args)?;
   let path = resolve_from_cwd(Path::new(&args.path))?;
 
-  state.check_read(&path)?;
+  state.check_read_entry(&path)?;
 
   let is_sync = args.promise_id.is_none();
   blocking_json(is_sync, move || {
@@ -0,0 +1,485 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+//! HTTP/1.1 and HTTP/2 server ops backed by hyper.
+//!
+//! `op_http_start` takes an accepted TCP, TLS or Unix connection out of the
+//! resource table and serves it with hyper. The requests hyper receives are
+//! handed to JavaScript by `op_http_next_request` and answered with
+//! `op_http_respond`. Request bodies and streaming response bodies are
+//! `StreamResource`s, read and written with `op_read` and `op_write`.
+//!
+//! Every connection is driven by a task of its own, so responses are written
+//! without JavaScript having to poll the connection. Closing the connection
+//! resource shuts the connection down once the responses in flight are sent.
+use super::dispatch_json::{Deserialize, JsonOp, Value};
+use super::io::{StreamResource, StreamResourceHolder};
+use crate::op_error::OpError;
+use crate::state::State;
+use bytes::Bytes;
+use deno_core::*;
+use futures::channel::oneshot;
+use futures::future::poll_fn;
+use futures::future::FutureExt;
+use futures::ready;
+use http::header::HeaderName;
+use http::header::HeaderValue;
+use http::request::Parts;
+use hyper::body::HttpBody as _;
+use hyper::server::conn::Connection;
+use hyper::server::conn::Http;
+use hyper::service::Service;
+use hyper::Body;
+use hyper::Request;
+use hyper::Response;
+use std::cell::RefCell;
+use std::cmp::min;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Waker;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::rustls::Session;
+
+pub fn init(i: &mut Isolate, s: &State) {
+  i.register_op("op_http_start", s.stateful_json_op2(op_http_start));
+  i.register_op(
+    "op_http_next_request",
+    s.stateful_json_op2(op_http_next_request),
+  );
+  i.register_op("op_http_respond", s.stateful_json_op2(op_http_respond));
+}
+
+trait HttpStream: AsyncRead + AsyncWrite + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> HttpStream for T {}
+
+type HttpConnection = Connection<Box<dyn HttpStream>, HttpService>;
+
+type PendingRequest = (Request<Body>, oneshot::Sender<Response<Body>>);
+
+/// Queues the requests received by hyper until `op_http_next_request` takes
+/// them. The response is sent back through the oneshot channel.
+#[derive(Clone, Default)]
+struct HttpService(Rc<RefCell<ServiceState>>);
+
+#[derive(Default)]
+struct ServiceState {
+  requests: VecDeque<PendingRequest>,
+  waker: Option<Waker>,
+}
+
+impl Service<Request<Body>> for HttpService {
+  type Response = Response<Body>;
+  type Error = oneshot::Canceled;
+  type Future = oneshot::Receiver<Response<Body>>;
+
+  fn poll_ready(&mut self, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+    Poll::Ready(Ok(()))
+  }
+
+  fn call(&mut self, request: Request<Body>) -> Self::Future {
+    let (sender, receiver) = oneshot::channel();
+    let mut state = self.0.borrow_mut();
+    state.requests.push_back((request, sender));
+    if let Some(waker) = state.waker.take() {
+      waker.wake();
+    }
+    receiver
+  }
+}
+
+enum ConnState {
+  Serving(Pin<Box<HttpConnection>>),
+  /// The connection is closed. Holds the error that closed it until it's
+  /// reported by `op_http_next_request`.
+  Closed(Option<OpError>),
+}
+
+/// A connection served by hyper. It's shared by the connection resource and
+/// the task driving it.
+struct HttpConn {
+  state: RefCell<ConnState>,
+  service: HttpService,
+  /// The task driving the connection, woken up to start a shutdown.
+  driver: RefCell<Option<Waker>>,
+  scheme: &'static str,
+  /// Host of request URLs, when the request has no "Host" header.
+  host: String,
+}
+
+impl HttpConn {
+  /// Drives the connection, returns `Ready` once it's closed. Only polled by
+  /// the task spawned by `op_http_start`.
+  fn poll_connection(&self, cx: &mut Context) -> Poll<()> {
+    let mut state = self.state.borrow_mut();
+    let connection = match &mut *state {
+      ConnState::Serving(connection) => connection,
+      ConnState::Closed(_) => return Poll::Ready(()),
+    };
+    self.driver.replace(Some(cx.waker().clone()));
+    let result = ready!(connection.as_mut().poll(cx));
+    *state = ConnState::Closed(result.err().map(OpError::from));
+    self.driver.replace(None);
+    // Let a pending `op_http_next_request` see that the connection is closed.
+    self.wake_next_request();
+    Poll::Ready(())
+  }
+
+  fn is_closed(&self) -> bool {
+    match &*self.state.borrow() {
+      ConnState::Serving(_) => false,
+      ConnState::Closed(_) => true,
+    }
+  }
+
+  /// Stops reading requests, the connection is closed once the responses in
+  /// flight are written.
+  fn shutdown(&self) {
+    if let ConnState::Serving(connection) = &mut *self.state.borrow_mut() {
+      connection.as_mut().graceful_shutdown();
+    }
+    if let Some(waker) = self.driver.borrow_mut().take() {
+      waker.wake();
+    }
+  }
+
+  fn wake_next_request(&self) {
+    if let Some(waker) = self.service.0.borrow_mut().waker.take() {
+      waker.wake();
+    }
+  }
+
+  /// Takes the next request received by hyper, or registers the current task
+  /// to be woken up when there is one.
+  fn poll_request(&self, cx: &mut Context) -> Option<PendingRequest> {
+    let mut service = self.service.0.borrow_mut();
+    let request = service.requests.pop_front();
+    if request.is_none() {
+      service.waker = Some(cx.waker().clone());
+    }
+    request
+  }
+
+  /// Takes the error that closed the connection, if there was one.
+  fn take_error(&self) -> Option<OpError> {
+    match &mut *self.state.borrow_mut() {
+      ConnState::Closed(error) => error.take(),
+      ConnState::Serving(_) => None,
+    }
+  }
+
+  /// HTTP/2 requests have an absolute URI, HTTP/1.1 requests usually only
+  /// have a path.
+  fn request_url(&self, parts: &Parts) -> String {
+    if parts.uri.scheme().is_some() && parts.uri.authority().is_some() {
+      return parts.uri.to_string();
+    }
+    let host = parts
+      .headers
+      .get(http::header::HOST)
+      .and_then(|host| host.to_str().ok())
+      .unwrap_or(&self.host);
+    let path = parts
+      .uri
+      .path_and_query()
+      .map(|path| path.as_str())
+      .unwrap_or("/");
+    format!("{}://{}{}", self.scheme, host, path)
+  }
+}
+
+struct HttpConnResource(Rc<HttpConn>);
+
+impl Drop for HttpConnResource {
+  fn drop(&mut self) {
+    self.0.shutdown();
+    // A pending `op_http_next_request` fails once the resource is gone.
+    self.0.wake_next_request();
+  }
+}
+
+struct HttpResponderResource(oneshot::Sender<Response<Body>>);
+
+/// The body of a request, read with `op_read`.
+pub struct HttpRequestBody {
+  body: Body,
+  chunk: Option<Bytes>,
+}
+
+impl AsyncRead for HttpRequestBody {
+  fn poll_read(
+    self: Pin<&mut Self>,
+    cx: &mut Context,
+    buf: &mut [u8],
+  ) -> Poll<Result<usize, io::Error>> {
+    let inner = self.get_mut();
+    loop {
+      match inner.chunk.take() {
+        Some(mut chunk) if !chunk.is_empty() => {
+          let n = min(buf.len(), chunk.len());
+          buf[..n].copy_from_slice(&chunk.split_to(n));
+          inner.chunk = Some(chunk);
+          return Poll::Ready(Ok(n));
+        }
+        _ => {}
+      }
+      match ready!(Pin::new(&mut inner.body).poll_data(cx)) {
+        Some(Ok(chunk)) => inner.chunk = Some(chunk),
+        Some(Err(e)) => {
+          return Poll::Ready(Err(io::Error::new(io::ErrorKind::Other, e)))
+        }
+        None => return Poll::Ready(Ok(0)),
+      }
+    }
+  }
+}
+
+/// The body of a streaming response, written with `op_write`. Closing the
+/// resource ends the response.
+pub struct HttpResponseBody(hyper::body::Sender);
+
+impl AsyncWrite for HttpResponseBody {
+  fn poll_write(
+    self: Pin<&mut Self>,
+    cx: &mut Context,
+    buf: &[u8],
+  ) -> Poll<Result<usize, io::Error>> {
+    let sender = &mut self.get_mut().0;
+    ready!(sender.poll_ready(cx))
+      .map_err(|e| io::Error::new(io::ErrorKind::BrokenPipe, e))?;
+    sender
+      .try_send_data(Bytes::copy_from_slice(buf))
+      .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+    Poll::Ready(Ok(buf.len()))
+  }
+
+  fn poll_flush(
+    self: Pin<&mut Self>,
+    _cx: &mut Context,
+  ) -> Poll<Result<(), io::Error>> {
+    Poll::Ready(Ok(()))
+  }
+
+  fn poll_shutdown(
+    self: Pin<&mut Self>,
+    _cx: &mut Context,
+  ) -> Poll<Result<(), io::Error>> {
+    Poll::Ready(Ok(()))
+  }
+}
+
+#[derive(Deserialize)]
+struct HttpStartArgs {
+  rid: i32,
+}
+
+fn op_http_start(
+  isolate: &mut deno_core::Isolate,
+  _state: &State,
+  args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  let args: HttpStartArgs = serde_json::from_value(args)?;
+  let rid = args.rid as u32;
+
+  let mut resource_table = isolate.resource_table.borrow_mut();
+  match resource_table
+    .get::<StreamResourceHolder>(rid)
+    .map(|resource_holder| &resource_holder.resource)
+  {
+    Some(StreamResource::TcpStream(Some(_)))
+    | Some(StreamResource::ServerTlsStream(_)) => {}
+    #[cfg(unix)]
+    Some(StreamResource::UnixStream(_)) => {}
+    _ => return Err(OpError::bad_resource_id()),
+  }
+  let mut resource_holder =
+    resource_table.remove::<StreamResourceHolder>(rid).unwrap();
+  // `StreamResourceHolder` implements `Drop`, so the stream is swapped out.
+  let resource = std::mem::replace(
+    &mut resource_holder.resource,
+    StreamResource::FsFile(None),
+  );
+
+  let mut http = Http::new();
+  let (stream, scheme, host): (Box<dyn HttpStream>, _, _) = match resource {
+    StreamResource::TcpStream(Some(tcp_stream)) => {
+      let host = tcp_stream.local_addr()?.to_string();
+      (Box::new(tcp_stream), "http", host)
+    }
+    StreamResource::ServerTlsStream(tls_stream) => {
+      let (tcp_stream, session) = tls_stream.get_ref();
+      if session.get_alpn_protocol() == Some(&b"h2"[..]) {
+        http.http2_only(true);
+      }
+      let host = tcp_stream.local_addr()?.to_string();
+      (tls_stream, "https", host)
+    }
+    #[cfg(unix)]
+    StreamResource::UnixStream(unix_stream) => {
+      (Box::new(unix_stream), "http", "localhost".to_string())
+    }
+    _ => unreachable!(),
+  };
+
+  let service = HttpService::default();
+  // Without a negotiated protocol, hyper serves HTTP/1.1 and switches to
+  // HTTP/2 if the client starts with the HTTP/2 connection preface.
+  let connection = http.serve_connection(stream, service.clone());
+  let conn = Rc::new(HttpConn {
+    state: RefCell::new(ConnState::Serving(Box::pin(connection))),
+    service,
+    driver: RefCell::new(None),
+    scheme,
+    host,
+  });
+  let driver_conn = conn.clone();
+  tokio::task::spawn_local(poll_fn(move |cx| driver_conn.poll_connection(cx)));
+  let rid = resource_table.add("httpConn", Box::new(HttpConnResource(conn)));
+
+  Ok(JsonOp::Sync(json!({ "rid": rid })))
+}
+
+#[derive(Deserialize)]
+struct HttpNextRequestArgs {
+  rid: i32,
+}
+
+fn op_http_next_request(
+  isolate: &mut deno_core::Isolate,
+  _state: &State,
+  args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  let args: HttpNextRequestArgs = serde_json::from_value(args)?;
+  let rid = args.rid as u32;
+  let resource_table = isolate.resource_table.clone();
+
+  let op = async move {
+    let next_request = poll_fn(|cx| {
+      let resource_table = resource_table.borrow();
+      let conn = &resource_table
+        .get::<HttpConnResource>(rid)
+        .ok_or_else(|| {
+          OpError::bad_resource("Http connection has been closed".to_string())
+        })?
+        .0;
+      if let Some(request) = conn.poll_request(cx) {
+        return Poll::Ready(Ok(Some((request, conn.clone()))));
+      }
+      if !conn.is_closed() {
+        return Poll::Pending;
+      }
+      match conn.take_error() {
+        Some(error) => Poll::Ready(Err(error)),
+        None => Poll::Ready(Ok(None)),
+      }
+    });
+    let ((request, sender), conn) = match next_request.await? {
+      Some(next_request) => next_request,
+      None => return Ok(json!(null)),
+    };
+
+    let (parts, body) = request.into_parts();
+    let url = conn.request_url(&parts);
+    let mut headers = Vec::new();
+    for (key, value) in parts.headers.iter() {
+      headers.push((
+        key.to_string(),
+        String::from_utf8_lossy(value.as_bytes()).into_owned(),
+      ));
+    }
+
+    let mut resource_table = resource_table.borrow_mut();
+    let body_rid = if body.is_end_stream() {
+      None
+    } else {
+      let body = HttpRequestBody { body, chunk: None };
+      Some(resource_table.add(
+        "httpRequestBody",
+        Box::new(StreamResourceHolder::new(StreamResource::HttpRequestBody(
+          Box::new(body),
+        ))),
+      ))
+    };
+    let rid = resource_table
+      .add("httpResponder", Box::new(HttpResponderResource(sender)));
+
+    Ok(json!({
+      "rid": rid,
+      "method": parts.method.as_str(),
+      "url": url,
+      "headers": headers,
+      "bodyRid": body_rid,
+    }))
+  };
+
+  Ok(JsonOp::Async(op.boxed_local()))
+}
+
+#[derive(Deserialize)]
+struct HttpRespondArgs {
+  rid: i32,
+  status: u16,
+  headers: Vec<(String, String)>,
+  /// If set, the body is written with `op_write` to the returned resource
+  /// instead of being passed to this op.
+  stream: bool,
+}
+
+fn op_http_respond(
+  isolate: &mut deno_core::Isolate,
+  _state: &State,
+  args: Value,
+  data: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  let args: HttpRespondArgs = serde_json::from_value(args)?;
+  let rid = args.rid as u32;
+
+  let mut builder = Response::builder().status(args.status);
+  for (key, value) in args.headers {
+    let name = HeaderName::from_bytes(key.as_bytes())
+      .map_err(|e| OpError::type_error(e.to_string()))?;
+    let value = HeaderValue::from_str(&value)
+      .map_err(|e| OpError::type_error(e.to_string()))?;
+    builder = builder.header(name, value);
+  }
+  let (body_sender, body) = if args.stream {
+    let (sender, body) = Body::channel();
+    (Some(sender), body)
+  } else {
+    let body = match data {
+      Some(buf) => Body::from(Vec::from(&*buf)),
+      None => Body::empty(),
+    };
+    (None, body)
+  };
+  let response = builder
+    .body(body)
+    .map_err(|e| OpError::type_error(e.to_string()))?;
+
+  let mut resource_table = isolate.resource_table.borrow_mut();
+  let responder = resource_table
+    .remove::<HttpResponderResource>(rid)
+    .ok_or_else(OpError::bad_resource_id)?;
+  if responder.0.send(response).is_err() {
+    return Err(OpError::from(io::Error::new(
+      io::ErrorKind::BrokenPipe,
+      "connection closed before the response was sent",
+    )));
+  }
+  let body_rid = body_sender.map(|sender| {
+    let body = HttpResponseBody(sender);
+    resource_table.add(
+      "httpResponseBody",
+      Box::new(StreamResourceHolder::new(StreamResource::HttpResponseBody(
+        Box::new(body),
+      ))),
+    )
+  });
+
+  Ok(JsonOp::Sync(json!({ "bodyRid": body_rid })))
+}
@@ -1,5 +1,6 @@
 use super::dispatch_minimal::MinimalOp;
-use crate::http_util::HttpBody;
+use super::fetch::{FetchBody, RequestBodyReader, RequestBodyWriter};
+use super::http::{HttpRequestBody, HttpResponseBody};
 use crate::op_error::OpError;
 use crate::state::State;
 use deno_core::*;
@@ -155,7 +156,11 @@ pub enum StreamResource {
   UnixStream(tokio::net::UnixStream),
   ServerTlsStream(Box<ServerTlsStream<TcpStream>>),
   ClientTlsStream(Box<ClientTlsStream<TcpStream>>),
-  HttpBody(Box<HttpBody>),
+  FetchBody(Box<FetchBody>),
+  HttpRequestBody(Box<HttpRequestBody>),
+  HttpResponseBody(Box<HttpResponseBody>),
+  RequestBodyReader(Box<RequestBodyReader>),
+  RequestBodyWriter(Box<RequestBodyWriter>),
   ChildStdin(tokio::process::ChildStdin),
   ChildStdout(tokio::process::ChildStdout),
   ChildStderr(tokio::process::ChildStderr),
@@ -195,7 +200,9 @@ impl DenoAsyncRead for StreamResource {
       ServerTlsStream(f) => f,
       ChildStdout(f) => f,
       ChildStderr(f) => f,
-      HttpBody(f) => f,
+      FetchBody(f) => f,
+      HttpRequestBody(f) => f,
+      RequestBodyReader(f) => f,
       _ => return Err(OpError::bad_resource_id()).into(),
     };
     let v = ready!(Pin::new(f).poll_read(cx, buf))?;
@@ -297,6 +304,8 @@ impl DenoAsyncWrite for StreamResource {
       ClientTlsStream(f) => f,
       ServerTlsStream(f) => f,
       ChildStdin(f) => f,
+      HttpResponseBody(f) => f,
+      RequestBodyWriter(f) => f,
       _ => return Err(OpError::bad_resource_id()).into(),
     };
 
@@ -315,6 +324,8 @@ impl DenoAsyncWrite for StreamResource {
       ClientTlsStream(f) => f,
       ServerTlsStream(f) => f,
       ChildStdin(f) => f,
+      HttpResponseBody(f) => f,
+      RequestBodyWriter(f) => f,
       _ => return Err(OpError::bad_resource_id()).into(),
     };
 
@@ -13,6 +13,7 @@ pub mod errors;
 pub mod fetch;
 pub mod fs;
 pub mod fs_events;
+pub mod http;
 pub mod io;
 pub mod net;
 #[cfg(unix)]
@@ -31,4 +32,5 @@ pub mod timers;
 pub mod tls;
 pub mod tty;
 pub mod web_worker;
+pub mod websocket;
 pub mod worker_host;
@@ -7,17 +7,32 @@ use crate::state::State;
 use deno_core::*;
 use futures::future::poll_fn;
 use futures::future::FutureExt;
+use socket2::{Domain, Protocol, Socket, Type};
 use std::convert::From;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
 use std::net::Shutdown;
 use std::net::SocketAddr;
 use std::task::Context;
 use std::task::Poll;
+use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::net::TcpStream;
 use tokio::net::UdpSocket;
+use trust_dns_resolver::config::NameServerConfigGroup;
+use trust_dns_resolver::config::ResolverConfig;
+use trust_dns_resolver::config::ResolverOpts;
+use trust_dns_resolver::proto::rr::RData;
+use trust_dns_resolver::proto::rr::RecordType;
+use trust_dns_resolver::proto::xfer::DnsRequestOptions;
+use trust_dns_resolver::TokioAsyncResolver;
 
 #[cfg(unix)]
 use super::net_unix;
+#[cfg(unix)]
+use super::net_unix::{
+  op_peer_credentials, op_receive_resources, op_send_resources,
+};
 
 pub fn init(i: &mut Isolate, s: &State) {
   i.register_op("op_accept", s.stateful_json_op2(op_accept));
@@ -26,6 +41,23 @@ pub fn init(i: &mut Isolate, s: &State) {
   i.register_op("op_listen", s.stateful_json_op2(op_listen));
   i.register_op("op_receive", s.stateful_json_op2(op_receive));
   i.register_op("op_send", s.stateful_json_op2(op_send));
+  i.register_op("op_set_tcp_option", s.stateful_json_op2(op_set_tcp_option));
+  i.register_op("op_set_udp_option", s.stateful_json_op2(op_set_udp_option));
+  i.register_op("op_join_multicast", s.stateful_json_op2(op_join_multicast));
+  i.register_op(
+    "op_leave_multicast",
+    s.stateful_json_op2(op_leave_multicast),
+  );
+  i.register_op("op_resolve_dns", s.stateful_json_op2(op_resolve_dns));
+  i.register_op("op_send_resources", s.stateful_json_op2(op_send_resources));
+  i.register_op(
+    "op_receive_resources",
+    s.stateful_json_op2(op_receive_resources),
+  );
+  i.register_op(
+    "op_peer_credentials",
+    s.stateful_json_op2(op_peer_credentials),
+  );
 }
 
 #[derive(Deserialize)]
@@ -201,6 +233,7 @@ fn op_send(
     } if transport == "udp" => {
       state.check_net(&args.hostname, args.port)?;
 
+      let state = state.clone();
       let op = async move {
         let mut resource_table = resource_table.borrow_mut();
         let resource = resource_table
@@ -210,6 +243,7 @@ fn op_send(
           })?;
         let socket = &mut resource.socket;
         let addr = resolve_addr(&args.hostname, args.port)?;
+        state.check_net_addr(&addr)?;
         socket.send_to(&buf, addr).await?;
         Ok(json!({}))
       };
@@ -266,8 +300,10 @@ fn op_connect(
       transport_args: ArgsEnum::Ip(args),
     } if transport == "tcp" => {
       state.check_net(&args.hostname, args.port)?;
+      let state = state.clone();
       let op = async move {
         let addr = resolve_addr(&args.hostname, args.port)?;
+        state.check_net_addr(&addr)?;
         let tcp_stream = TcpStream::connect(&addr).await?;
         let local_addr = tcp_stream.local_addr()?;
         let remote_addr = tcp_stream.peer_addr()?;
@@ -374,6 +410,244 @@ fn op_shutdown(
   Ok(JsonOp::Sync(json!({})))
 }
 
+/// The TCP stream underlying a stream resource, if it has one.
+fn tcp_stream(resource: &StreamResource) -> Option<&TcpStream> {
+  match resource {
+    StreamResource::TcpStream(Some(stream)) => Some(stream),
+    StreamResource::ServerTlsStream(stream) => Some(stream.get_ref().0),
+    StreamResource::ClientTlsStream(stream) => Some(stream.get_ref().0),
+    _ => None,
+  }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeepAliveArgs {
+  /// The idle time in milliseconds before probes are sent.
+  idle_time: u64,
+  /// The time in milliseconds between probes, if not the system's default.
+  interval: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum TcpOption {
+  NoDelay(bool),
+  /// `None` disables keep-alive.
+  KeepAlive(Option<KeepAliveArgs>),
+  SendBufferSize(usize),
+  RecvBufferSize(usize),
+  Ttl(u32),
+}
+
+/// Sets the time between keep-alive probes (`TCP_KEEPINTVL`), which tokio
+/// leaves to the system.
+#[cfg(any(target_os = "android", target_os = "freebsd", target_os = "linux"))]
+fn set_keepalive_interval(
+  stream: &TcpStream,
+  interval: Duration,
+) -> Result<(), OpError> {
+  use std::os::unix::io::AsRawFd;
+  let secs = interval.as_secs() as libc::c_int;
+  let ret = unsafe {
+    libc::setsockopt(
+      stream.as_raw_fd(),
+      libc::IPPROTO_TCP,
+      libc::TCP_KEEPINTVL,
+      &secs as *const libc::c_int as *const libc::c_void,
+      std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+    )
+  };
+  if ret == -1 {
+    return Err(std::io::Error::last_os_error().into());
+  }
+  Ok(())
+}
+
+#[cfg(not(any(
+  target_os = "android",
+  target_os = "freebsd",
+  target_os = "linux"
+)))]
+fn set_keepalive_interval(
+  _stream: &TcpStream,
+  _interval: Duration,
+) -> Result<(), OpError> {
+  Err(OpError::type_error(
+    "The keep-alive interval is not supported on this platform".to_string(),
+  ))
+}
+
+#[derive(Deserialize)]
+struct SetTcpOptionArgs {
+  rid: i32,
+  option: TcpOption,
+}
+
+/// Sets an option of a TCP connection, which may be wrapped in TLS.
+fn op_set_tcp_option(
+  isolate: &mut deno_core::Isolate,
+  _state: &State,
+  args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  let args: SetTcpOptionArgs = serde_json::from_value(args)?;
+  let resource_table = isolate.resource_table.borrow();
+  let resource_holder = resource_table
+    .get::<StreamResourceHolder>(args.rid as u32)
+    .ok_or_else(OpError::bad_resource_id)?;
+  let stream = tcp_stream(&resource_holder.resource)
+    .ok_or_else(OpError::bad_resource_id)?;
+  match args.option {
+    TcpOption::NoDelay(no_delay) => stream.set_nodelay(no_delay)?,
+    TcpOption::KeepAlive(None) => stream.set_keepalive(None)?,
+    TcpOption::KeepAlive(Some(args)) => {
+      if args.idle_time < 1000 || args.interval.map_or(false, |i| i < 1000) {
+        return Err(OpError::type_error(
+          "Keep-alive idle time and interval must be at least 1000 milliseconds"
+            .to_string(),
+        ));
+      }
+      if let Some(interval) = args.interval {
+        set_keepalive_interval(stream, Duration::from_millis(interval))?;
+      }
+      stream.set_keepalive(Some(Duration::from_millis(args.idle_time)))?
+    }
+    TcpOption::SendBufferSize(size) => stream.set_send_buffer_size(size)?,
+    TcpOption::RecvBufferSize(size) => stream.set_recv_buffer_size(size)?,
+    TcpOption::Ttl(ttl) => stream.set_ttl(ttl)?,
+  }
+  Ok(JsonOp::Sync(json!({})))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum UdpOption {
+  Broadcast(bool),
+  Ttl(u32),
+  MulticastTtl(u32),
+  MulticastLoopback(bool),
+}
+
+#[derive(Deserialize)]
+struct SetUdpOptionArgs {
+  rid: i32,
+  option: UdpOption,
+}
+
+fn op_set_udp_option(
+  isolate: &mut deno_core::Isolate,
+  _state: &State,
+  args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  let args: SetUdpOptionArgs = serde_json::from_value(args)?;
+  let resource_table = isolate.resource_table.borrow();
+  let socket = &resource_table
+    .get::<UdpSocketResource>(args.rid as u32)
+    .ok_or_else(OpError::bad_resource_id)?
+    .socket;
+  let is_ipv4 = socket.local_addr()?.is_ipv4();
+  match args.option {
+    UdpOption::Broadcast(broadcast) => socket.set_broadcast(broadcast)?,
+    UdpOption::Ttl(ttl) => socket.set_ttl(ttl)?,
+    UdpOption::MulticastTtl(ttl) if is_ipv4 => {
+      socket.set_multicast_ttl_v4(ttl)?
+    }
+    UdpOption::MulticastTtl(_) => {
+      return Err(OpError::type_error(
+        "multicastTtl is only supported by IPv4 sockets".to_string(),
+      ))
+    }
+    UdpOption::MulticastLoopback(enabled) if is_ipv4 => {
+      socket.set_multicast_loop_v4(enabled)?
+    }
+    UdpOption::MulticastLoopback(enabled) => {
+      socket.set_multicast_loop_v6(enabled)?
+    }
+  }
+  Ok(JsonOp::Sync(json!({})))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MulticastArgs {
+  rid: i32,
+  multiaddr: String,
+  network_interface: Option<String>,
+}
+
+/// Joins or leaves the multicast group of `args`. IPv4 groups are joined on
+/// the interface with the given address and IPv6 groups on the interface with
+/// the given index, or on the default interface when there is none.
+fn multicast(
+  isolate: &mut deno_core::Isolate,
+  args: Value,
+  join: bool,
+) -> Result<JsonOp, OpError> {
+  let args: MulticastArgs = serde_json::from_value(args)?;
+  let invalid = |what: &str, value: &str| {
+    OpError::type_error(format!("Invalid {}: {}", what, value))
+  };
+  let multiaddr: IpAddr = args
+    .multiaddr
+    .parse()
+    .map_err(|_| invalid("multicast address", &args.multiaddr))?;
+  let resource_table = isolate.resource_table.borrow();
+  let socket = &resource_table
+    .get::<UdpSocketResource>(args.rid as u32)
+    .ok_or_else(OpError::bad_resource_id)?
+    .socket;
+  let network_interface = args.network_interface.as_deref();
+  match multiaddr {
+    IpAddr::V4(multiaddr) => {
+      let interface: Ipv4Addr = match network_interface {
+        Some(interface) => interface
+          .parse()
+          .map_err(|_| invalid("network interface", interface))?,
+        None => Ipv4Addr::UNSPECIFIED,
+      };
+      if join {
+        socket.join_multicast_v4(multiaddr, interface)?;
+      } else {
+        socket.leave_multicast_v4(multiaddr, interface)?;
+      }
+    }
+    IpAddr::V6(multiaddr) => {
+      let interface: u32 = match network_interface {
+        Some(interface) => interface
+          .parse()
+          .map_err(|_| invalid("network interface", interface))?,
+        None => 0,
+      };
+      if join {
+        socket.join_multicast_v6(&multiaddr, interface)?;
+      } else {
+        socket.leave_multicast_v6(&multiaddr, interface)?;
+      }
+    }
+  }
+  Ok(JsonOp::Sync(json!({})))
+}
+
+fn op_join_multicast(
+  isolate: &mut deno_core::Isolate,
+  _state: &State,
+  args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  multicast(isolate, args, true)
+}
+
+fn op_leave_multicast(
+  isolate: &mut deno_core::Isolate,
+  _state: &State,
+  args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  multicast(isolate, args, false)
+}
+
 #[allow(dead_code)]
 struct TcpListenerResource {
   listener: TcpListener,
@@ -427,6 +701,22 @@ struct UdpSocketResource {
   socket: UdpSocket,
 }
 
+/// The descriptor of a TCP listener or UDP socket resource.
+#[cfg(unix)]
+pub fn socket_fd(
+  resource_table: &ResourceTable,
+  rid: u32,
+) -> Option<std::os::unix::io::RawFd> {
+  use std::os::unix::io::AsRawFd;
+  if let Some(resource) = resource_table.get::<TcpListenerResource>(rid) {
+    Some(resource.listener.as_raw_fd())
+  } else if let Some(resource) = resource_table.get::<UdpSocketResource>(rid) {
+    Some(resource.socket.as_raw_fd())
+  } else {
+    None
+  }
+}
+
 #[derive(Deserialize)]
 struct IpListenArgs {
   hostname: String,
@@ -446,14 +736,85 @@ struct ListenArgs {
   transport: String,
   #[serde(flatten)]
   transport_args: ArgsEnum,
+  #[serde(flatten)]
+  socket: ListenSocketArgs,
+}
+
+/// The options of TCP and UDP sockets which have to be set before binding.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListenSocketArgs {
+  reuse_address: Option<bool>,
+  reuse_port: Option<bool>,
+  send_buffer_size: Option<usize>,
+  recv_buffer_size: Option<usize>,
+}
+
+fn bind_socket(
+  addr: SocketAddr,
+  type_: Type,
+  protocol: Protocol,
+  args: &ListenSocketArgs,
+) -> Result<Socket, OpError> {
+  let domain = if addr.is_ipv4() {
+    Domain::ipv4()
+  } else {
+    Domain::ipv6()
+  };
+  let socket = Socket::new(domain, type_, Some(protocol))?;
+  if let Some(reuse_address) = args.reuse_address {
+    socket.set_reuse_address(reuse_address)?;
+  }
+  if let Some(reuse_port) = args.reuse_port {
+    #[cfg(unix)]
+    socket.set_reuse_port(reuse_port)?;
+    #[cfg(not(unix))]
+    {
+      if reuse_port {
+        return Err(OpError::type_error(
+          "reusePort is not supported on this platform".to_string(),
+        ));
+      }
+    }
+  }
+  if let Some(size) = args.send_buffer_size {
+    socket.set_send_buffer_size(size)?;
+  }
+  if let Some(size) = args.recv_buffer_size {
+    socket.set_recv_buffer_size(size)?;
+  }
+  socket.bind(&addr.into())?;
+  Ok(socket)
+}
+
+/// Binds a TCP listener, which like `std::net::TcpListener::bind` reuses the
+/// address by default on unix.
+pub fn bind_tcp_listener(
+  addr: SocketAddr,
+  args: &ListenSocketArgs,
+) -> Result<std::net::TcpListener, OpError> {
+  let args = ListenSocketArgs {
+    reuse_address: args.reuse_address.or(Some(cfg!(unix))),
+    ..*args
+  };
+  let socket = bind_socket(addr, Type::stream(), Protocol::tcp(), &args)?;
+  socket.listen(128)?;
+  Ok(socket.into_tcp_listener())
 }
 
 fn listen_tcp(
   resource_table: &mut ResourceTable,
   addr: SocketAddr,
+  args: &ListenSocketArgs,
+) -> Result<(u32, SocketAddr), OpError> {
+  let std_listener = bind_tcp_listener(addr, args)?;
+  add_tcp_listener(resource_table, TcpListener::from_std(std_listener)?)
+}
+
+pub fn add_tcp_listener(
+  resource_table: &mut ResourceTable,
+  listener: TcpListener,
 ) -> Result<(u32, SocketAddr), OpError> {
-  let std_listener = std::net::TcpListener::bind(&addr)?;
-  let listener = TcpListener::from_std(std_listener)?;
   let local_addr = listener.local_addr()?;
   let listener_resource = TcpListenerResource {
     listener,
@@ -468,9 +829,17 @@ fn listen_tcp(
 fn listen_udp(
   resource_table: &mut ResourceTable,
   addr: SocketAddr,
+  args: &ListenSocketArgs,
+) -> Result<(u32, SocketAddr), OpError> {
+  let std_socket =
+    bind_socket(addr, Type::dgram(), Protocol::udp(), args)?.into_udp_socket();
+  add_udp_socket(resource_table, UdpSocket::from_std(std_socket)?)
+}
+
+pub fn add_udp_socket(
+  resource_table: &mut ResourceTable,
+  socket: UdpSocket,
 ) -> Result<(u32, SocketAddr), OpError> {
-  let std_socket = std::net::UdpSocket::bind(&addr)?;
-  let socket = UdpSocket::from_std(std_socket)?;
   let local_addr = socket.local_addr()?;
   let socket_resource = UdpSocketResource { socket };
   let rid = resource_table.add("udpSocket", Box::new(socket_resource));
@@ -489,13 +858,15 @@ fn op_listen(
     ListenArgs {
       transport,
       transport_args: ArgsEnum::Ip(args),
+      socket,
     } => {
       state.check_net(&args.hostname, args.port)?;
       let addr = resolve_addr(&args.hostname, args.port)?;
+      state.check_net_addr(&addr)?;
       let (rid, local_addr) = if transport == "tcp" {
-        listen_tcp(&mut resource_table, addr)?
+        listen_tcp(&mut resource_table, addr, &socket)?
       } else {
-        listen_udp(&mut resource_table, addr)?
+        listen_udp(&mut resource_table, addr, &socket)?
       };
       debug!(
         "New listener {} {}:{}",
@@ -516,6 +887,7 @@ fn op_listen(
     ListenArgs {
       transport,
       transport_args: ArgsEnum::Unix(args),
+      ..
     } if transport == "unix" || transport == "unixpacket" => {
       let address_path = net_unix::Path::new(&args.address);
       state.check_read(&address_path)?;
@@ -541,3 +913,146 @@ fn op_listen(
     _ => Err(OpError::other("Wrong argument format!".to_owned())),
   }
 }
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResolveDnsArgs {
+  query: String,
+  record_type: String,
+  name_server: Option<NameServerArgs>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NameServerArgs {
+  ip_addr: String,
+  port: Option<u16>,
+}
+
+fn op_resolve_dns(
+  _isolate: &mut deno_core::Isolate,
+  state: &State,
+  args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  let args: ResolveDnsArgs = serde_json::from_value(args)?;
+  let record_type = match args.record_type.as_str() {
+    "A" => RecordType::A,
+    "AAAA" => RecordType::AAAA,
+    "CNAME" => RecordType::CNAME,
+    "MX" => RecordType::MX,
+    "NS" => RecordType::NS,
+    "SRV" => RecordType::SRV,
+    "TXT" => RecordType::TXT,
+    record_type => {
+      return Err(OpError::type_error(format!(
+        "Unsupported record type: {}",
+        record_type
+      )))
+    }
+  };
+  let query = args.query;
+  state.check_net_host(&query)?;
+
+  // Without a name server, the system configuration is used.
+  let config = match args.name_server {
+    Some(name_server) => {
+      let port = name_server.port.unwrap_or(53);
+      state.check_net(&name_server.ip_addr, port)?;
+      let ip: IpAddr = name_server.ip_addr.parse().map_err(|_| {
+        OpError::type_error(format!(
+          "Invalid name server address: {}",
+          name_server.ip_addr
+        ))
+      })?;
+      let group = NameServerConfigGroup::from_ips_clear(&[ip], port);
+      Some(ResolverConfig::from_parts(None, vec![], group))
+    }
+    None => None,
+  };
+
+  let state = state.clone();
+  let op = async move {
+    let cached_resolver = state.borrow().dns_resolver.clone();
+    let resolver = match (config, cached_resolver) {
+      (Some(config), _) => {
+        TokioAsyncResolver::tokio(config, ResolverOpts::default()).await?
+      }
+      (None, Some(resolver)) => resolver,
+      (None, None) => {
+        let resolver = TokioAsyncResolver::tokio_from_system_conf().await?;
+        state.borrow_mut().dns_resolver = Some(resolver.clone());
+        resolver
+      }
+    };
+    let lookup = resolver
+      .lookup(query.as_str(), record_type, DnsRequestOptions::default())
+      .await?;
+    // Lookups also return the CNAME records that were followed.
+    let records: Vec<Value> = lookup
+      .iter()
+      .filter(|rdata| rdata.to_record_type() == record_type)
+      .filter_map(rdata_to_json)
+      .collect();
+    Ok(json!(records))
+  };
+  Ok(JsonOp::Async(op.boxed_local()))
+}
+
+fn rdata_to_json(rdata: &RData) -> Option<Value> {
+  let value = match rdata {
+    RData::A(addr) => json!(addr.to_string()),
+    RData::AAAA(addr) => json!(addr.to_string()),
+    RData::CNAME(name) | RData::NS(name) => json!(name.to_string()),
+    RData::MX(mx) => json!({
+      "preference": mx.preference(),
+      "exchange": mx.exchange().to_string(),
+    }),
+    RData::SRV(srv) => json!({
+      "priority": srv.priority(),
+      "weight": srv.weight(),
+      "port": srv.port(),
+      "target": srv.target().to_string(),
+    }),
+    RData::TXT(txt) => {
+      let strings: Vec<String> = txt
+        .txt_data()
+        .iter()
+        .map(|string| String::from_utf8_lossy(string).into_owned())
+        .collect();
+      json!(strings)
+    }
+    _ => return None,
+  };
+  Some(value)
+}
+
+#[cfg(not(unix))]
+fn op_send_resources(
+  _isolate: &mut deno_core::Isolate,
+  _state: &State,
+  _args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  Err(OpError::not_implemented())
+}
+
+#[cfg(not(unix))]
+fn op_receive_resources(
+  _isolate: &mut deno_core::Isolate,
+  _state: &State,
+  _args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  Err(OpError::not_implemented())
+}
+
+#[cfg(not(unix))]
+fn op_peer_credentials(
+  _isolate: &mut deno_core::Isolate,
+  _state: &State,
+  _args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  Err(OpError::not_implemented())
+}
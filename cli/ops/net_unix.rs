@@ -1,16 +1,42 @@
-use super::dispatch_json::{Deserialize, JsonOp};
-use super::io::{StreamResource, StreamResourceHolder};
+use super::dispatch_json::{Deserialize, JsonOp, Value};
+use super::io::{FileMetadata, StreamResource, StreamResourceHolder};
+use super::net::{add_tcp_listener, add_udp_socket, socket_fd};
 use crate::op_error::OpError;
+use crate::state::State;
+use futures::future::poll_fn;
 use futures::future::FutureExt;
 
 use deno_core::*;
+use mio::unix::EventedFd;
+use mio::{Evented, PollOpt, Ready, Token};
+use nix::errno::Errno;
+use nix::fcntl::{fcntl, FcntlArg, FdFlag, OFlag};
+use nix::sys::socket::{getpeername, getsockname, getsockopt, sockopt};
+use nix::sys::socket::{recvmsg, sendmsg};
+use nix::sys::socket::{ControlMessage, ControlMessageOwned, MsgFlags};
+use nix::sys::socket::{SockAddr, SockType};
+use nix::sys::stat::{fstat, SFlag};
+use nix::sys::uio::IoVec;
+use nix::unistd::close;
+use std::cell::RefCell;
 use std::fs::remove_file;
+use std::fs::File;
 use std::os::unix;
+use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
 pub use std::path::Path;
+use std::rc::Rc;
+use std::task::Poll;
+use tokio::io::PollEvented;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::net::UdpSocket;
 use tokio::net::UnixDatagram;
 use tokio::net::UnixListener;
 pub use tokio::net::UnixStream;
 
+/// The most resources which can be passed in a single message.
+const MAX_PASSED_RESOURCES: usize = 32;
+
 struct UnixListenerResource {
   listener: UnixListener,
 }
@@ -136,3 +162,555 @@ pub fn listen_unix_packet(
 
   Ok((rid, local_addr))
 }
+
+/// Converts the error of a socket call. `From<nix::Error>` only expects the
+/// errors of file system calls.
+fn io_error(error: nix::Error) -> OpError {
+  match error {
+    nix::Error::Sys(errno) => {
+      std::io::Error::from_raw_os_error(errno as i32).into()
+    }
+    error => error.into(),
+  }
+}
+
+/// The descriptor of the Unix stream `rid`.
+fn unix_stream_fd(
+  resource_table: &ResourceTable,
+  rid: u32,
+) -> Result<RawFd, OpError> {
+  let resource_holder = resource_table
+    .get::<StreamResourceHolder>(rid)
+    .ok_or_else(OpError::bad_resource_id)?;
+  match resource_holder.resource {
+    StreamResource::UnixStream(ref stream) => Ok(stream.as_raw_fd()),
+    _ => Err(OpError::bad_resource_id()),
+  }
+}
+
+/// The descriptor of a resource which can be passed to another process.
+fn passable_fd(
+  resource_table: &ResourceTable,
+  rid: u32,
+) -> Result<RawFd, OpError> {
+  if let Some(resource_holder) = resource_table.get::<StreamResourceHolder>(rid)
+  {
+    return match resource_holder.resource {
+      StreamResource::FsFile(Some((ref file, _))) => Ok(file.as_raw_fd()),
+      StreamResource::TcpStream(Some(ref stream)) => Ok(stream.as_raw_fd()),
+      StreamResource::UnixStream(ref stream) => Ok(stream.as_raw_fd()),
+      _ => Err(OpError::bad_resource_id()),
+    };
+  }
+  if let Some(resource) = resource_table.get::<UnixListenerResource>(rid) {
+    return Ok(resource.listener.as_raw_fd());
+  }
+  if let Some(resource) = resource_table.get::<UnixDatagramResource>(rid) {
+    return Ok(resource.socket.as_raw_fd());
+  }
+  socket_fd(resource_table, rid).ok_or_else(OpError::bad_resource_id)
+}
+
+/// A duplicate of the descriptor of a socket, which is registered with the
+/// reactor on its own to wait for the socket to become ready.
+struct SocketReadiness(RawFd);
+
+impl SocketReadiness {
+  fn new(fd: RawFd) -> Result<PollEvented<Self>, OpError> {
+    let fd = fcntl(fd, FcntlArg::F_DUPFD_CLOEXEC(0)).map_err(io_error)?;
+    Ok(PollEvented::new(Self(fd))?)
+  }
+}
+
+impl Drop for SocketReadiness {
+  fn drop(&mut self) {
+    let _ = close(self.0);
+  }
+}
+
+impl Evented for SocketReadiness {
+  fn register(
+    &self,
+    poll: &mio::Poll,
+    token: Token,
+    interest: Ready,
+    opts: PollOpt,
+  ) -> std::io::Result<()> {
+    EventedFd(&self.0).register(poll, token, interest, opts)
+  }
+
+  fn reregister(
+    &self,
+    poll: &mio::Poll,
+    token: Token,
+    interest: Ready,
+    opts: PollOpt,
+  ) -> std::io::Result<()> {
+    EventedFd(&self.0).reregister(poll, token, interest, opts)
+  }
+
+  fn deregister(&self, poll: &mio::Poll) -> std::io::Result<()> {
+    EventedFd(&self.0).deregister(poll)
+  }
+}
+
+/// Calls `f` with the descriptor of the Unix stream `rid` until it returns
+/// something other than `None`, which it does when the call would block. In
+/// between the stream is waited for to become ready for `interest`, or to be
+/// closed.
+async fn retry_unix_stream<T>(
+  resource_table: Rc<RefCell<ResourceTable>>,
+  rid: u32,
+  interest: Ready,
+  mut f: impl FnMut(&ResourceTable, RawFd) -> Result<Option<T>, OpError>,
+) -> Result<T, OpError> {
+  let readiness = {
+    let resource_table = resource_table.borrow();
+    SocketReadiness::new(unix_stream_fd(&resource_table, rid)?)?
+  };
+  let mut task_tracker_id: Option<usize> = None;
+  poll_fn(move |cx| {
+    let mut resource_table = resource_table.borrow_mut();
+    if let Some(id) = task_tracker_id.take() {
+      if let Some(resource_holder) =
+        resource_table.get_mut::<StreamResourceHolder>(rid)
+      {
+        resource_holder.untrack_task(id);
+      }
+    }
+    loop {
+      let fd = unix_stream_fd(&resource_table, rid)?;
+      let ready = if interest.is_writable() {
+        readiness.poll_write_ready(cx)
+      } else {
+        readiness.poll_read_ready(cx, interest)
+      };
+      if let Poll::Ready(ready) = ready {
+        ready?;
+        if let Some(t) = f(&resource_table, fd)? {
+          return Poll::Ready(Ok(t));
+        }
+        if interest.is_writable() {
+          readiness.clear_write_ready(cx)?;
+        } else {
+          readiness.clear_read_ready(cx, interest)?;
+        }
+        continue;
+      }
+      // Closing the resource wakes the task as well.
+      let resource_holder = resource_table
+        .get_mut::<StreamResourceHolder>(rid)
+        .ok_or_else(OpError::bad_resource_id)?;
+      task_tracker_id.replace(resource_holder.track_task(cx)?);
+      return Poll::Pending;
+    }
+  })
+  .await
+}
+
+#[derive(Deserialize)]
+struct SendResourcesArgs {
+  rid: u32,
+  rids: Vec<u32>,
+}
+
+/// Sends the descriptors of the resources `rids` over the Unix stream `rid`
+/// (`SCM_RIGHTS`), along with the bytes of `zero_copy`.
+pub fn op_send_resources(
+  isolate: &mut deno_core::Isolate,
+  _state: &State,
+  args: Value,
+  zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  assert!(zero_copy.is_some());
+  let buf = zero_copy.unwrap();
+  let args: SendResourcesArgs = serde_json::from_value(args)?;
+  if buf.is_empty() {
+    return Err(OpError::type_error(
+      "At least one byte has to be sent along with resources".to_string(),
+    ));
+  }
+  if args.rids.len() > MAX_PASSED_RESOURCES {
+    return Err(OpError::type_error(format!(
+      "At most {} resources can be sent at once",
+      MAX_PASSED_RESOURCES
+    )));
+  }
+  let resource_table = isolate.resource_table.clone();
+
+  let op = async move {
+    let size = retry_unix_stream(
+      resource_table,
+      args.rid,
+      Ready::writable(),
+      |resource_table, fd| {
+        let fds = args
+          .rids
+          .iter()
+          .map(|rid| passable_fd(resource_table, *rid))
+          .collect::<Result<Vec<_>, _>>()?;
+        let iov = [IoVec::from_slice(&buf)];
+        let cmsgs = [ControlMessage::ScmRights(&fds)];
+        match sendmsg(fd, &iov, &cmsgs, MsgFlags::empty(), None) {
+          Ok(size) => Ok(Some(size)),
+          Err(nix::Error::Sys(Errno::EAGAIN)) => Ok(None),
+          Err(e) => Err(io_error(e)),
+        }
+      },
+    )
+    .await?;
+    Ok(json!({ "size": size }))
+  };
+
+  Ok(JsonOp::Async(op.boxed_local()))
+}
+
+#[derive(Deserialize)]
+struct ReceiveResourcesArgs {
+  rid: u32,
+}
+
+/// Receives bytes into `zero_copy` and the descriptors sent along with them
+/// from the Unix stream `rid`, which are added as new resources.
+pub fn op_receive_resources(
+  isolate: &mut deno_core::Isolate,
+  state: &State,
+  args: Value,
+  zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  assert!(zero_copy.is_some());
+  let mut buf = zero_copy.unwrap();
+  let args: ReceiveResourcesArgs = serde_json::from_value(args)?;
+  let resource_table = isolate.resource_table.clone();
+  let state = state.clone();
+
+  let op = async move {
+    let (size, files) = retry_unix_stream(
+      resource_table.clone(),
+      args.rid,
+      Ready::readable(),
+      |_, fd| {
+        let iov = [IoVec::from_mut_slice(&mut buf)];
+        let mut cmsg_buffer = nix::cmsg_space!([RawFd; MAX_PASSED_RESOURCES]);
+        #[cfg(any(target_os = "android", target_os = "linux"))]
+        let flags = MsgFlags::MSG_CMSG_CLOEXEC;
+        #[cfg(not(any(target_os = "android", target_os = "linux")))]
+        let flags = MsgFlags::empty();
+        match recvmsg(fd, &iov, Some(&mut cmsg_buffer), flags) {
+          Ok(msg) => {
+            // Owning the descriptors right away closes them on errors.
+            let mut files = Vec::new();
+            for cmsg in msg.cmsgs() {
+              if let ControlMessageOwned::ScmRights(fds) = cmsg {
+                files.extend(
+                  fds.into_iter().map(|fd| unsafe { File::from_raw_fd(fd) }),
+                );
+              }
+            }
+            if msg.flags.contains(MsgFlags::MSG_CTRUNC) {
+              return Err(OpError::other(format!(
+                "More than {} resources were received at once",
+                MAX_PASSED_RESOURCES
+              )));
+            }
+            Ok(Some((msg.bytes, files)))
+          }
+          Err(nix::Error::Sys(Errno::EAGAIN)) => Ok(None),
+          Err(e) => Err(io_error(e)),
+        }
+      },
+    )
+    .await?;
+
+    let mut resource_table = resource_table.borrow_mut();
+    let mut rids = Vec::with_capacity(files.len());
+    let mut resources = Vec::with_capacity(files.len());
+    for file in files {
+      match add_received_resource(&state, &mut resource_table, file) {
+        Ok((rid, resource)) => {
+          rids.push(rid);
+          resources.push(resource);
+        }
+        Err(e) => {
+          // Don't leak the resources added for the preceding descriptors,
+          // whose rids are never returned. The remaining ones are closed as
+          // the files are dropped.
+          for rid in rids {
+            resource_table.close(rid);
+          }
+          return Err(e);
+        }
+      }
+    }
+    Ok(json!({
+      "size": size,
+      "resources": resources,
+    }))
+  };
+
+  Ok(JsonOp::Async(op.boxed_local()))
+}
+
+/// Adds a received descriptor as the resource matching its type, once the
+/// permissions allow the access it grants. Descriptors which are not sockets,
+/// e.g. pipes, are added as files. `FD_CLOEXEC` is set here where `recvmsg`
+/// can't set it already. Returns the rid along with the resource for JS.
+fn add_received_resource(
+  state: &State,
+  resource_table: &mut ResourceTable,
+  file: File,
+) -> Result<(u32, Value), OpError> {
+  let fd = file.as_raw_fd();
+  fcntl(fd, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC)).map_err(io_error)?;
+  let mode = SFlag::from_bits_truncate(fstat(fd).map_err(io_error)?.st_mode);
+  let kind = if mode & SFlag::S_IFMT == SFlag::S_IFSOCK {
+    let addr = getsockname(fd).map_err(io_error)?;
+    let sock_type = getsockopt(fd, sockopt::SockType).map_err(io_error)?;
+    let listening = getsockopt(fd, sockopt::AcceptConn).map_err(io_error)?;
+    match (addr, sock_type, listening) {
+      (SockAddr::Inet(_), SockType::Stream, true) => "tcpListener",
+      (SockAddr::Inet(_), SockType::Stream, false) => "tcpStream",
+      (SockAddr::Inet(_), SockType::Datagram, _) => "udpSocket",
+      (SockAddr::Unix(_), SockType::Stream, true) => "unixListener",
+      (SockAddr::Unix(_), SockType::Stream, false) => "unixStream",
+      (SockAddr::Unix(_), SockType::Datagram, _) => "unixDatagram",
+      _ => "file",
+    }
+  } else {
+    "file"
+  };
+  check_received_resource(state, fd, kind)?;
+  let fd = file.into_raw_fd();
+
+  match kind {
+    "tcpListener" => {
+      let listener = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+      let (rid, local_addr) =
+        add_tcp_listener(resource_table, TcpListener::from_std(listener)?)?;
+      let resource = json!({
+        "rid": rid,
+        "kind": kind,
+        "localAddr": {
+          "hostname": local_addr.ip().to_string(),
+          "port": local_addr.port(),
+          "transport": "tcp",
+        },
+      });
+      Ok((rid, resource))
+    }
+    "tcpStream" => {
+      let stream = unsafe { std::net::TcpStream::from_raw_fd(fd) };
+      let stream = TcpStream::from_std(stream)?;
+      let local_addr = stream.local_addr()?;
+      let remote_addr = stream.peer_addr()?;
+      let rid = resource_table.add(
+        "tcpStream",
+        Box::new(StreamResourceHolder::new(StreamResource::TcpStream(Some(
+          stream,
+        )))),
+      );
+      let resource = json!({
+        "rid": rid,
+        "kind": kind,
+        "localAddr": {
+          "hostname": local_addr.ip().to_string(),
+          "port": local_addr.port(),
+          "transport": "tcp",
+        },
+        "remoteAddr": {
+          "hostname": remote_addr.ip().to_string(),
+          "port": remote_addr.port(),
+          "transport": "tcp",
+        },
+      });
+      Ok((rid, resource))
+    }
+    "udpSocket" => {
+      let socket = unsafe { std::net::UdpSocket::from_raw_fd(fd) };
+      let (rid, local_addr) =
+        add_udp_socket(resource_table, UdpSocket::from_std(socket)?)?;
+      let resource = json!({
+        "rid": rid,
+        "kind": kind,
+        "localAddr": {
+          "hostname": local_addr.ip().to_string(),
+          "port": local_addr.port(),
+          "transport": "udp",
+        },
+      });
+      Ok((rid, resource))
+    }
+    "unixListener" => {
+      let listener = unsafe { unix::net::UnixListener::from_raw_fd(fd) };
+      let listener = UnixListener::from_std(listener)?;
+      let local_addr = listener.local_addr()?;
+      let listener_resource = UnixListenerResource { listener };
+      let rid = resource_table.add("unixListener", Box::new(listener_resource));
+      let resource = json!({
+        "rid": rid,
+        "kind": kind,
+        "localAddr": {
+          "address": local_addr.as_pathname(),
+          "transport": "unix",
+        },
+      });
+      Ok((rid, resource))
+    }
+    "unixStream" => {
+      let stream = unsafe { unix::net::UnixStream::from_raw_fd(fd) };
+      let stream = UnixStream::from_std(stream)?;
+      let local_addr = stream.local_addr()?;
+      let remote_addr = stream.peer_addr()?;
+      let rid = resource_table.add(
+        "unixStream",
+        Box::new(StreamResourceHolder::new(StreamResource::UnixStream(
+          stream,
+        ))),
+      );
+      let resource = json!({
+        "rid": rid,
+        "kind": kind,
+        "localAddr": {
+          "address": local_addr.as_pathname(),
+          "transport": "unix",
+        },
+        "remoteAddr": {
+          "address": remote_addr.as_pathname(),
+          "transport": "unix",
+        },
+      });
+      Ok((rid, resource))
+    }
+    "unixDatagram" => {
+      let socket = unsafe { unix::net::UnixDatagram::from_raw_fd(fd) };
+      let socket = UnixDatagram::from_std(socket)?;
+      let local_addr = socket.local_addr()?;
+      let datagram_resource = UnixDatagramResource {
+        socket,
+        local_addr: local_addr.clone(),
+      };
+      let rid = resource_table.add("unixDatagram", Box::new(datagram_resource));
+      let resource = json!({
+        "rid": rid,
+        "kind": kind,
+        "localAddr": {
+          "address": local_addr.as_pathname(),
+          "transport": "unixpacket",
+        },
+      });
+      Ok((rid, resource))
+    }
+    _ => {
+      let file = tokio::fs::File::from_std(unsafe { File::from_raw_fd(fd) });
+      let rid = resource_table.add(
+        "fsFile",
+        Box::new(StreamResourceHolder::new(StreamResource::FsFile(Some((
+          file,
+          FileMetadata::default(),
+        ))))),
+      );
+      let resource = json!({
+        "rid": rid,
+        "kind": kind,
+      });
+      Ok((rid, resource))
+    }
+  }
+}
+
+/// Checks the permissions for a received descriptor `fd` of type `kind` as if
+/// it was opened in this process: the address of a TCP or UDP socket, the
+/// path of a Unix socket and the access mode of a file. Their paths aren't
+/// known for files and unnamed Unix sockets, which need `--allow-read` or
+/// `--allow-write` for every path.
+fn check_received_resource(
+  state: &State,
+  fd: RawFd,
+  kind: &str,
+) -> Result<(), OpError> {
+  match kind {
+    "tcpListener" | "udpSocket" | "tcpStream" => {
+      let addr = if kind == "tcpStream" {
+        getpeername(fd)
+      } else {
+        getsockname(fd)
+      };
+      match addr.map_err(io_error)? {
+        SockAddr::Inet(addr) => state.check_net_addr(&addr.to_std()),
+        _ => unreachable!(),
+      }
+    }
+    "unixListener" | "unixStream" | "unixDatagram" => {
+      let mut addrs = vec![getsockname(fd).map_err(io_error)?];
+      if kind == "unixStream" {
+        addrs.push(getpeername(fd).map_err(io_error)?);
+      }
+      let path = addrs.iter().find_map(|addr| match addr {
+        SockAddr::Unix(addr) => addr.path(),
+        _ => None,
+      });
+      match path {
+        Some(path) => state.check_read(path),
+        None => state.check_read_all("a received Unix socket"),
+      }
+    }
+    _ => {
+      let flags = fcntl(fd, FcntlArg::F_GETFL).map_err(io_error)?;
+      let access = OFlag::from_bits_truncate(flags) & OFlag::O_ACCMODE;
+      if access != OFlag::O_WRONLY {
+        state.check_read_all("a received file")?;
+      }
+      if access != OFlag::O_RDONLY {
+        state.check_write_all("a received file")?;
+      }
+      Ok(())
+    }
+  }
+}
+
+#[derive(Deserialize)]
+struct PeerCredentialsArgs {
+  rid: u32,
+}
+
+/// Looks up the process on the other end of the Unix stream `rid`
+/// (`SO_PEERCRED`). Its pid is only known on Linux.
+pub fn op_peer_credentials(
+  isolate: &mut deno_core::Isolate,
+  _state: &State,
+  args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  let args: PeerCredentialsArgs = serde_json::from_value(args)?;
+  let resource_table = isolate.resource_table.borrow();
+  let resource_holder = resource_table
+    .get::<StreamResourceHolder>(args.rid)
+    .ok_or_else(OpError::bad_resource_id)?;
+  match resource_holder.resource {
+    StreamResource::UnixStream(ref stream) => {
+      Ok(JsonOp::Sync(peer_credentials(stream)?))
+    }
+    _ => Err(OpError::bad_resource_id()),
+  }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn peer_credentials(stream: &UnixStream) -> Result<Value, OpError> {
+  let credentials = getsockopt(stream.as_raw_fd(), sockopt::PeerCredentials)
+    .map_err(io_error)?;
+  Ok(json!({
+    "pid": credentials.pid(),
+    "uid": credentials.uid(),
+    "gid": credentials.gid(),
+  }))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn peer_credentials(stream: &UnixStream) -> Result<Value, OpError> {
+  let credentials = stream.peer_cred()?;
+  Ok(json!({
+    "pid": null,
+    "uid": credentials.uid,
+    "gid": credentials.gid,
+  }))
+}
@@ -101,7 +101,7 @@ fn op_set_env(
   _zero_copy: Option<ZeroCopyBuf>,
 ) -> Result<JsonOp, OpError> {
   let args: SetEnv = serde_json::from_value(args)?;
-  state.check_env()?;
+  state.check_env_var(&args.key)?;
   env::set_var(args.key, args.value);
   Ok(JsonOp::Sync(json!({})))
 }
@@ -111,8 +111,16 @@ fn op_env(
   _args: Value,
   _zero_copy: Option<ZeroCopyBuf>,
 ) -> Result<JsonOp, OpError> {
-  state.check_env()?;
-  let v = env::vars().collect::<HashMap<String, String>>();
+  let state = state.borrow();
+  let permissions = &state.permissions;
+  // With an `--allow-env=<vars>` whitelist only the permitted subset of the
+  // environment is returned.
+  if permissions.env_whitelist.is_empty() {
+    permissions.check_env()?;
+  }
+  let v = env::vars()
+    .filter(|(key, _)| permissions.is_env_var_allowed(key))
+    .collect::<HashMap<String, String>>();
   Ok(JsonOp::Sync(json!(v)))
 }
 
@@ -127,7 +135,7 @@ fn op_get_env(
   _zero_copy: Option<ZeroCopyBuf>,
 ) -> Result<JsonOp, OpError> {
   let args: GetEnv = serde_json::from_value(args)?;
-  state.check_env()?;
+  state.check_env_var(&args.key)?;
   let r = match env::var(args.key) {
     Err(env::VarError::NotPresent) => json!([]),
     v => json!([v?]),
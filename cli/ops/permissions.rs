@@ -2,9 +2,10 @@
 use super::dispatch_json::{Deserialize, JsonOp, Value};
 use crate::fs as deno_fs;
 use crate::op_error::OpError;
+use crate::permissions::PermissionState;
 use crate::state::State;
 use deno_core::*;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 pub fn init(i: &mut Isolate, s: &State) {
   i.register_op(
@@ -26,6 +27,8 @@ struct PermissionArgs {
   name: String,
   url: Option<String>,
   path: Option<String>,
+  variable: Option<String>,
+  command: Option<String>,
 }
 
 fn resolve_path(path: &str) -> String {
@@ -36,6 +39,12 @@ fn resolve_path(path: &str) -> String {
     .to_string()
 }
 
+fn resolve_command(command: &str) -> PathBuf {
+  let cwd = std::env::current_dir().unwrap();
+  deno_fs::resolve_program(command, &cwd, std::env::var_os("PATH").as_deref())
+    .unwrap_or_else(|| PathBuf::from(command))
+}
+
 pub fn op_query_permission(
   state: &State,
   args: Value,
@@ -44,10 +53,13 @@ pub fn op_query_permission(
   let args: PermissionArgs = serde_json::from_value(args)?;
   let state = state.borrow();
   let resolved_path = args.path.as_deref().map(resolve_path);
+  let resolved_command = args.command.as_deref().map(resolve_command);
   let perm = state.permissions.get_permission_state(
     &args.name,
     &args.url.as_deref(),
     &resolved_path.as_deref().map(Path::new),
+    &args.variable.as_deref(),
+    &resolved_command.as_deref(),
   )?;
   Ok(JsonOp::Sync(json!({ "state": perm.to_string() })))
 }
@@ -60,12 +72,18 @@ pub fn op_revoke_permission(
   let args: PermissionArgs = serde_json::from_value(args)?;
   let mut state = state.borrow_mut();
   let permissions = &mut state.permissions;
+  let resolved_command = args.command.as_deref().map(resolve_command);
   match args.name.as_ref() {
-    "run" => permissions.allow_run.revoke(),
+    "run" => {
+      permissions.allow_run.revoke();
+      if let Some(command) = &resolved_command {
+        permissions.run_whitelist.remove(command);
+      }
+    }
     "read" => permissions.allow_read.revoke(),
     "write" => permissions.allow_write.revoke(),
     "net" => permissions.allow_net.revoke(),
-    "env" => permissions.allow_env.revoke(),
+    "env" => permissions.revoke_env(&args.variable.as_deref())?,
     "plugin" => permissions.allow_plugin.revoke(),
     "hrtime" => permissions.allow_hrtime.revoke(),
     _ => {}
@@ -75,6 +93,8 @@ pub fn op_revoke_permission(
     &args.name,
     &args.url.as_deref(),
     &resolved_path.as_deref().map(Path::new),
+    &args.variable.as_deref(),
+    &resolved_command.as_deref(),
   )?;
   Ok(JsonOp::Sync(json!({ "state": perm.to_string() })))
 }
@@ -88,8 +108,21 @@ pub fn op_request_permission(
   let mut state = state.borrow_mut();
   let permissions = &mut state.permissions;
   let resolved_path = args.path.as_deref().map(resolve_path);
+  let resolved_command = args.command.as_deref().map(resolve_command);
+  // Don't prompt for a permission that the permission policy of the calling
+  // module denies anyway.
+  let policy_state = permissions.get_policy_state(
+    &args.name,
+    &args.url.as_deref(),
+    &resolved_path.as_deref().map(Path::new),
+    &args.variable.as_deref(),
+    &resolved_command.as_deref(),
+  )?;
+  if policy_state == PermissionState::Deny {
+    return Ok(JsonOp::Sync(json!({ "state": policy_state.to_string() })));
+  }
   let perm = match args.name.as_ref() {
-    "run" => Ok(permissions.request_run()),
+    "run" => Ok(permissions.request_run(&resolved_command.as_deref())),
     "read" => {
       Ok(permissions.request_read(&resolved_path.as_deref().map(Path::new)))
     }
@@ -97,7 +130,7 @@ pub fn op_request_permission(
       Ok(permissions.request_write(&resolved_path.as_deref().map(Path::new)))
     }
     "net" => permissions.request_net(&args.url.as_deref()),
-    "env" => Ok(permissions.request_env()),
+    "env" => Ok(permissions.request_env(&args.variable.as_deref())),
     "plugin" => Ok(permissions.request_plugin()),
     "hrtime" => Ok(permissions.request_hrtime()),
     n => Err(OpError::other(format!("No such permission name: {}", n))),
@@ -1,6 +1,7 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 use super::dispatch_json::{Deserialize, JsonOp, Value};
 use super::io::{std_file_resource, StreamResource, StreamResourceHolder};
+use crate::fs as deno_fs;
 use crate::op_error::OpError;
 use crate::signal::kill;
 use crate::state::State;
@@ -9,8 +10,12 @@ use futures::future::poll_fn;
 use futures::future::FutureExt;
 use futures::TryFutureExt;
 use std::convert::From;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
 use tokio::process::Command;
 
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
 #[cfg(unix)]
 use std::os::unix::process::ExitStatusExt;
 
@@ -53,6 +58,16 @@ struct RunArgs {
   stderr_rid: u32,
 }
 
+#[cfg(windows)]
+fn is_path_env_key(key: &str) -> bool {
+  key.eq_ignore_ascii_case("PATH")
+}
+
+#[cfg(not(windows))]
+fn is_path_env_key(key: &str) -> bool {
+  key == "PATH"
+}
+
 struct ChildResource {
   child: tokio::process::Child,
 }
@@ -65,14 +80,34 @@ fn op_run(
 ) -> Result<JsonOp, OpError> {
   let run_args: RunArgs = serde_json::from_value(args)?;
 
-  state.check_run()?;
-  let mut resource_table = isolate.resource_table.borrow_mut();
-
   let args = run_args.cmd;
   let env = run_args.env;
   let cwd = run_args.cwd;
 
-  let mut c = Command::new(args.get(0).unwrap());
+  // Resolve the program the same way the child would be spawned, so the
+  // `--allow-run` whitelist is matched against the file actually executed.
+  let resolved_cwd =
+    deno_fs::resolve_from_cwd(Path::new(cwd.as_deref().unwrap_or(".")))?;
+  let path_env = env
+    .iter()
+    .rev()
+    .find(|(key, _)| is_path_env_key(key))
+    .map(|(_, value)| OsString::from(value))
+    .or_else(|| std::env::var_os("PATH"));
+  let program = args.get(0).unwrap();
+  let resolved_program =
+    deno_fs::resolve_program(program, &resolved_cwd, path_env.as_deref())
+      .unwrap_or_else(|| PathBuf::from(program));
+
+  state.check_run_program(&resolved_program)?;
+  let mut resource_table = isolate.resource_table.borrow_mut();
+
+  let mut c = std::process::Command::new(&resolved_program);
+  // The child still sees the name it was called by, which multi-call
+  // programs like busybox depend on.
+  #[cfg(unix)]
+  c.arg0(program);
+  let mut c = Command::from(c);
   (1..args.len()).for_each(|i| {
     let arg = args.get(i).unwrap();
     c.arg(arg);
@@ -173,14 +208,15 @@ struct RunStatusArgs {
 
 fn op_run_status(
   isolate: &mut deno_core::Isolate,
-  state: &State,
+  _state: &State,
   args: Value,
   _zero_copy: Option<ZeroCopyBuf>,
 ) -> Result<JsonOp, OpError> {
   let args: RunStatusArgs = serde_json::from_value(args)?;
   let rid = args.rid as u32;
 
-  state.check_run()?;
+  // No permission check here: a child resource only exists if `op_run` was
+  // permitted to spawn it, possibly through the `--allow-run` whitelist.
   let resource_table = isolate.resource_table.clone();
 
   let future = async move {
@@ -1,5 +1,6 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 use super::dispatch_json::{Deserialize, JsonOp, Value};
+use crate::coarse_time;
 use crate::op_error::OpError;
 use crate::state::State;
 use deno_core::*;
@@ -52,26 +53,18 @@ fn op_global_timer(
 // Returns a milliseconds and nanoseconds subsec
 // since the start time of the deno runtime.
 // If the High precision flag is not set, the
-// nanoseconds are rounded on 2ms.
+// result is clamped and jittered to the timer
+// resolution, see `coarse_time`.
 fn op_now(
   state: &State,
   _args: Value,
   _zero_copy: Option<ZeroCopyBuf>,
 ) -> Result<JsonOp, OpError> {
-  let state = state.borrow();
-  let seconds = state.start_time.elapsed().as_secs();
-  let mut subsec_nanos = state.start_time.elapsed().subsec_nanos();
-  let reduced_time_precision = 2_000_000; // 2ms in nanoseconds
-
-  // If the permission is not enabled
-  // Round the nano result on 2 milliseconds
-  // see: https://developer.mozilla.org/en-US/docs/Web/API/DOMHighResTimeStamp#Reduced_time_precision
-  if !state.permissions.allow_hrtime.is_allow() {
-    subsec_nanos -= subsec_nanos % reduced_time_precision
-  }
+  let elapsed = state.borrow().start_time.elapsed();
+  let elapsed = coarse_time::coarsen(elapsed, state.timer_resolution());
 
   Ok(JsonOp::Sync(json!({
-    "seconds": seconds,
-    "subsecNanos": subsec_nanos,
+    "seconds": elapsed.as_secs(),
+    "subsecNanos": elapsed.subsec_nanos(),
   })))
 }
@@ -0,0 +1,431 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+//! WebSocket client and server ops backed by tungstenite.
+//!
+//! `op_ws_create` connects to a `ws:` or `wss:` URL, and `op_ws_accept`
+//! upgrades a connection accepted from a TCP, TLS or Unix listener. Either
+//! way the connection becomes a WebSocket resource, which is written with
+//! `op_ws_send` and `op_ws_close` and read with `op_ws_next_event`.
+use super::dispatch_json::{Deserialize, JsonOp, Value};
+use super::io::{StreamResource, StreamResourceHolder};
+use super::tls::client_config;
+use crate::op_error::OpError;
+use crate::resolve_addr::resolve_addr;
+use crate::state::State;
+use deno_core::*;
+use futures::future::FutureExt;
+use futures::lock::Mutex as AsyncMutex;
+use futures::sink::SinkExt;
+use futures::stream::SplitSink;
+use futures::stream::SplitStream;
+use futures::stream::StreamExt;
+use http::header::HeaderValue;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use tokio_tungstenite::tungstenite::handshake::client::Request;
+use tokio_tungstenite::tungstenite::handshake::server;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+use url::Url;
+use webpki::DNSNameRef;
+
+pub fn init(i: &mut Isolate, s: &State) {
+  i.register_op(
+    "op_ws_check_permission",
+    s.stateful_json_op2(op_ws_check_permission),
+  );
+  i.register_op("op_ws_create", s.stateful_json_op2(op_ws_create));
+  i.register_op("op_ws_accept", s.stateful_json_op2(op_ws_accept));
+  i.register_op("op_ws_send", s.stateful_json_op2(op_ws_send));
+  i.register_op("op_ws_close", s.stateful_json_op2(op_ws_close));
+  i.register_op("op_ws_next_event", s.stateful_json_op2(op_ws_next_event));
+}
+
+trait WebSocketIo: AsyncRead + AsyncWrite + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> WebSocketIo for T {}
+
+type WsStream = WebSocketStream<Box<dyn WebSocketIo>>;
+
+/// The halves of a WebSocket are locked separately, so that messages can be
+/// sent while `op_ws_next_event` waits for the next one.
+struct WsResource {
+  sink: Rc<AsyncMutex<SplitSink<WsStream, Message>>>,
+  stream: Rc<AsyncMutex<SplitStream<WsStream>>>,
+}
+
+fn add_ws_resource(resource_table: &mut ResourceTable, ws: WsStream) -> u32 {
+  let (sink, stream) = ws.split();
+  resource_table.add(
+    "webSocketStream",
+    Box::new(WsResource {
+      sink: Rc::new(AsyncMutex::new(sink)),
+      stream: Rc::new(AsyncMutex::new(stream)),
+    }),
+  )
+}
+
+fn get_ws_resource<'a>(
+  resource_table: &'a ResourceTable,
+  rid: u32,
+) -> Result<&'a WsResource, OpError> {
+  resource_table
+    .get::<WsResource>(rid)
+    .ok_or_else(OpError::bad_resource_id)
+}
+
+#[derive(Deserialize)]
+struct CheckPermissionArgs {
+  url: String,
+}
+
+/// Lets the `WebSocket` constructor throw on a denied permission, instead of
+/// failing the connection later on.
+fn op_ws_check_permission(
+  _isolate: &mut deno_core::Isolate,
+  state: &State,
+  args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  let args: CheckPermissionArgs = serde_json::from_value(args)?;
+  state.check_net_url(&Url::parse(&args.url)?)?;
+  Ok(JsonOp::Sync(json!({})))
+}
+
+#[derive(Deserialize)]
+struct CreateArgs {
+  url: String,
+  #[serde(default)]
+  protocols: Vec<String>,
+}
+
+fn op_ws_create(
+  isolate: &mut deno_core::Isolate,
+  state: &State,
+  args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  let args: CreateArgs = serde_json::from_value(args)?;
+  let url = Url::parse(&args.url)?;
+  let scheme = url.scheme();
+  if scheme != "ws" && scheme != "wss" {
+    return Err(OpError::type_error(format!(
+      "scheme '{}' not supported",
+      scheme
+    )));
+  }
+  state.check_net_url(&url)?;
+  let ca_file = state.borrow().global_state.flags.ca_file.clone();
+  let resource_table = isolate.resource_table.clone();
+
+  let state = state.clone();
+  let op = async move {
+    let mut request = Request::builder().uri(url.as_str());
+    if !args.protocols.is_empty() {
+      request =
+        request.header("Sec-WebSocket-Protocol", args.protocols.join(", "));
+    }
+    let request = request
+      .body(())
+      .map_err(|e| OpError::type_error(e.to_string()))?;
+
+    let host = url.host_str().unwrap_or("");
+    let addr = resolve_addr(host, url.port_or_known_default().unwrap())?;
+    state.check_net_addr(&addr)?;
+    let tcp_stream = TcpStream::connect(&addr).await?;
+    let stream: Box<dyn WebSocketIo> = if url.scheme() == "wss" {
+      let config = client_config(ca_file.as_deref(), None)?;
+      let tls_connector = TlsConnector::from(Arc::new(config));
+      let dnsname = DNSNameRef::try_from_ascii_str(host).map_err(|_| {
+        OpError::type_error(format!("Invalid DNS name: {}", host))
+      })?;
+      Box::new(tls_connector.connect(dnsname, tcp_stream).await?)
+    } else {
+      Box::new(tcp_stream)
+    };
+
+    let (ws, response) =
+      tokio_tungstenite::client_async(request, stream).await?;
+    let protocol = response
+      .headers()
+      .get("Sec-WebSocket-Protocol")
+      .and_then(|value| value.to_str().ok())
+      .unwrap_or("")
+      .to_string();
+    if !protocol.is_empty() && !args.protocols.contains(&protocol) {
+      return Err(OpError::other(format!(
+        "Server selected a subprotocol that wasn't requested: {}",
+        protocol
+      )));
+    }
+
+    let rid = add_ws_resource(&mut resource_table.borrow_mut(), ws);
+    Ok(json!({
+      "rid": rid,
+      "protocol": protocol,
+    }))
+  };
+
+  Ok(JsonOp::Async(op.boxed_local()))
+}
+
+#[derive(Deserialize)]
+struct AcceptArgs {
+  rid: i32,
+  #[serde(default)]
+  protocols: Vec<String>,
+}
+
+/// What the handshake callback learned about the request.
+#[derive(Default)]
+struct AcceptedRequest {
+  path: String,
+  host: Option<String>,
+  headers: Vec<(String, String)>,
+  protocol: String,
+}
+
+fn op_ws_accept(
+  isolate: &mut deno_core::Isolate,
+  _state: &State,
+  args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  let args: AcceptArgs = serde_json::from_value(args)?;
+  let rid = args.rid as u32;
+
+  let mut resource_table = isolate.resource_table.borrow_mut();
+  match resource_table
+    .get::<StreamResourceHolder>(rid)
+    .map(|resource_holder| &resource_holder.resource)
+  {
+    Some(StreamResource::TcpStream(Some(_)))
+    | Some(StreamResource::ServerTlsStream(_)) => {}
+    #[cfg(unix)]
+    Some(StreamResource::UnixStream(_)) => {}
+    _ => return Err(OpError::bad_resource_id()),
+  }
+  let mut resource_holder =
+    resource_table.remove::<StreamResourceHolder>(rid).unwrap();
+  // `StreamResourceHolder` implements `Drop`, so the stream is swapped out.
+  let resource = std::mem::replace(
+    &mut resource_holder.resource,
+    StreamResource::FsFile(None),
+  );
+  drop(resource_table);
+
+  let (stream, scheme, local_host): (Box<dyn WebSocketIo>, _, _) =
+    match resource {
+      StreamResource::TcpStream(Some(tcp_stream)) => {
+        let host = tcp_stream.local_addr()?.to_string();
+        (Box::new(tcp_stream), "ws", host)
+      }
+      StreamResource::ServerTlsStream(tls_stream) => {
+        let host = tls_stream.get_ref().0.local_addr()?.to_string();
+        (tls_stream, "wss", host)
+      }
+      #[cfg(unix)]
+      StreamResource::UnixStream(unix_stream) => {
+        (Box::new(unix_stream), "ws", "localhost".to_string())
+      }
+      _ => unreachable!(),
+    };
+
+  let resource_table = isolate.resource_table.clone();
+  let op = async move {
+    let accepted = Rc::new(RefCell::new(AcceptedRequest::default()));
+    let accepted_ = accepted.clone();
+    let protocols = args.protocols;
+    // Picks the first subprotocol offered by the client that we support.
+    let callback =
+      move |request: &server::Request,
+            mut response: server::Response|
+            -> Result<server::Response, server::ErrorResponse> {
+        let mut accepted = accepted_.borrow_mut();
+        accepted.path = request
+          .uri()
+          .path_and_query()
+          .map(|p| p.to_string())
+          .unwrap_or_else(|| "/".to_string());
+        for (name, value) in request.headers() {
+          if let Ok(value) = value.to_str() {
+            accepted.headers.push((name.to_string(), value.to_string()));
+          }
+        }
+        accepted.host = request
+          .headers()
+          .get("Host")
+          .and_then(|value| value.to_str().ok())
+          .map(|host| host.to_string());
+        let offered = request
+          .headers()
+          .get_all("Sec-WebSocket-Protocol")
+          .iter()
+          .filter_map(|value| value.to_str().ok())
+          .flat_map(|value| value.split(','))
+          .map(|protocol| protocol.trim());
+        for protocol in offered {
+          if protocols.iter().any(|p| p == protocol) {
+            // Only tokens can have been matched, so the value is valid.
+            response.headers_mut().insert(
+              "Sec-WebSocket-Protocol",
+              HeaderValue::from_str(protocol).unwrap(),
+            );
+            accepted.protocol = protocol.to_string();
+            break;
+          }
+        }
+        Ok(response)
+      };
+    let ws = tokio_tungstenite::accept_hdr_async(stream, callback).await?;
+
+    let accepted = accepted.replace(AcceptedRequest::default());
+    let host = accepted.host.unwrap_or(local_host);
+    let rid = add_ws_resource(&mut resource_table.borrow_mut(), ws);
+    Ok(json!({
+      "rid": rid,
+      "url": format!("{}://{}{}", scheme, host, accepted.path),
+      "protocol": accepted.protocol,
+      "headers": accepted.headers,
+    }))
+  };
+
+  Ok(JsonOp::Async(op.boxed_local()))
+}
+
+#[derive(Deserialize)]
+struct SendArgs {
+  rid: i32,
+  kind: String,
+  text: Option<String>,
+}
+
+fn op_ws_send(
+  isolate: &mut deno_core::Isolate,
+  _state: &State,
+  args: Value,
+  zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  let args: SendArgs = serde_json::from_value(args)?;
+  let data = || zero_copy.map(|buf| buf.to_vec()).unwrap_or_default();
+  let message = match args.kind.as_str() {
+    "text" => Message::Text(args.text.unwrap_or_default()),
+    "binary" => Message::Binary(data()),
+    "ping" => Message::Ping(data()),
+    "pong" => Message::Pong(data()),
+    kind => {
+      return Err(OpError::type_error(format!(
+        "Unknown message kind: {}",
+        kind
+      )))
+    }
+  };
+
+  let resource_table = isolate.resource_table.borrow();
+  let sink = get_ws_resource(&resource_table, args.rid as u32)?
+    .sink
+    .clone();
+  let op = async move {
+    sink.lock().await.send(message).await?;
+    Ok(json!({}))
+  };
+
+  Ok(JsonOp::Async(op.boxed_local()))
+}
+
+#[derive(Deserialize)]
+struct CloseArgs {
+  rid: i32,
+  code: Option<u16>,
+  reason: Option<String>,
+}
+
+/// Starts the closing handshake. The connection is closed once the peer
+/// answers, which `op_ws_next_event` reports.
+fn op_ws_close(
+  isolate: &mut deno_core::Isolate,
+  _state: &State,
+  args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  let args: CloseArgs = serde_json::from_value(args)?;
+  let frame = args.code.map(|code| CloseFrame {
+    code: CloseCode::from(code),
+    reason: Cow::Owned(args.reason.unwrap_or_default()),
+  });
+
+  let resource_table = isolate.resource_table.borrow();
+  let sink = get_ws_resource(&resource_table, args.rid as u32)?
+    .sink
+    .clone();
+  let op = async move {
+    sink.lock().await.send(Message::Close(frame)).await?;
+    Ok(json!({}))
+  };
+
+  Ok(JsonOp::Async(op.boxed_local()))
+}
+
+#[derive(Deserialize)]
+struct NextEventArgs {
+  rid: i32,
+}
+
+fn op_ws_next_event(
+  isolate: &mut deno_core::Isolate,
+  _state: &State,
+  args: Value,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<JsonOp, OpError> {
+  let args: NextEventArgs = serde_json::from_value(args)?;
+
+  let resource_table = isolate.resource_table.borrow();
+  let stream = get_ws_resource(&resource_table, args.rid as u32)?
+    .stream
+    .clone();
+  let op = async move {
+    let message = stream.lock().await.next().await;
+    let event = match message {
+      Some(Ok(Message::Text(text))) => json!({
+        "kind": "string",
+        "data": text,
+      }),
+      Some(Ok(Message::Binary(data))) => json!({
+        "kind": "binary",
+        "data": data,
+      }),
+      Some(Ok(Message::Ping(_))) => json!({ "kind": "ping" }),
+      Some(Ok(Message::Pong(_))) => json!({ "kind": "pong" }),
+      Some(Ok(Message::Close(Some(frame)))) => json!({
+        "kind": "close",
+        "code": u16::from(frame.code),
+        "reason": frame.reason.to_string(),
+      }),
+      // 1005: the peer closed the connection without a status code.
+      Some(Ok(Message::Close(None))) => json!({
+        "kind": "close",
+        "code": 1005,
+        "reason": "",
+      }),
+      Some(Err(err)) => json!({
+        "kind": "error",
+        "message": err.to_string(),
+      }),
+      // 1006: the connection was closed without a closing handshake.
+      None => json!({
+        "kind": "close",
+        "code": 1006,
+        "reason": "",
+      }),
+    };
+    Ok(event)
+  };
+
+  Ok(JsonOp::Async(op.boxed_local()))
+}
@@ -1,12 +1,15 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 use super::dispatch_json::{Deserialize, JsonOp, Value};
+use crate::coarse_time;
 use crate::fmt_errors::JSError;
 use crate::global_state::GlobalState;
 use crate::op_error::OpError;
 use crate::ops::io::get_stdio;
 use crate::permissions::DenoPermissions;
+use crate::permissions::PermissionsDescriptor;
 use crate::startup_data;
 use crate::state::State;
+use crate::tokio_util::block_on_local;
 use crate::tokio_util::create_basic_runtime;
 use crate::web_worker::WebWorker;
 use crate::web_worker::WebWorkerHandle;
@@ -15,6 +18,7 @@ use deno_core::*;
 use futures::future::FutureExt;
 use std::convert::From;
 use std::thread::JoinHandle;
+use std::time::Duration;
 
 pub fn init(i: &mut Isolate, s: &State) {
   i.register_op("op_create_worker", s.stateful_json_op(op_create_worker));
@@ -131,7 +135,7 @@ fn run_worker_thread(
       // script instead of module
       let load_future = worker.execute_module(&specifier).boxed_local();
 
-      rt.block_on(load_future)
+      block_on_local(&mut rt, load_future)
     };
 
     if let Err(e) = result {
@@ -147,7 +151,7 @@ fn run_worker_thread(
     // TODO(bartlomieju): this thread should return result of event loop
     // that means that we should store JoinHandle to thread to ensure
     // that it actually terminates.
-    rt.block_on(worker).expect("Panic in event loop");
+    block_on_local(&mut rt, worker).expect("Panic in event loop");
     debug!("Worker thread shuts down {}", &name);
   })?;
 
@@ -163,6 +167,7 @@ struct CreateWorkerArgs {
   has_source_code: bool,
   source_code: String,
   use_deno_namespace: bool,
+  permissions: Option<PermissionsDescriptor>,
 }
 
 /// Create worker as the host
@@ -184,7 +189,15 @@ fn op_create_worker(
   let parent_state = state.clone();
   let mut state = state.borrow_mut();
   let global_state = state.global_state.clone();
-  let permissions = state.permissions.clone();
+  // Without a descriptor the worker inherits the permissions of its parent,
+  // otherwise it gets the subset of them that the descriptor asks for.
+  let permissions = match &args.permissions {
+    Some(descriptor) => {
+      let cwd = std::env::current_dir()?;
+      state.permissions.narrow(descriptor, &cwd)?
+    }
+    None => state.permissions.clone(),
+  };
   let referrer = state.main_module.to_string();
   let worker_id = state.next_worker_id;
   state.next_worker_id += 1;
@@ -234,9 +247,18 @@ fn op_host_terminate_worker(
   Ok(JsonOp::Sync(json!({})))
 }
 
-fn serialize_worker_event(event: WorkerEvent) -> Value {
+/// `timer_resolution` is the resolution of the time stamp of messages, see
+/// `State::timer_resolution()`.
+fn serialize_worker_event(
+  event: WorkerEvent,
+  timer_resolution: Option<Duration>,
+) -> Value {
   match event {
-    WorkerEvent::Message(buf) => json!({ "type": "msg", "data": buf }),
+    WorkerEvent::Message(buf) => json!({
+      "type": "msg",
+      "data": buf,
+      "timeStamp": coarse_time::unix_time_millis(timer_resolution),
+    }),
     WorkerEvent::TerminalError(error) => {
       let mut serialized_error = json!({
         "type": "terminalError",
@@ -312,7 +334,7 @@ fn op_host_get_message(
             join_handle.join().expect("Worker thread panicked");
           }
         }
-        serialize_worker_event(event)
+        serialize_worker_event(event, state_.timer_resolution())
       }
       None => {
         // Worker shuts down
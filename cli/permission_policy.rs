@@ -0,0 +1,187 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+//! Per-module permissions loaded from the file given with
+//! `--permission-policy`. The file maps module origins or URL prefixes to the
+//! permissions granted to the modules loaded from them, for example:
+//!
+//! ```json
+//! {
+//!   "https://deno.land/x/": { "net": ["deno.land"] },
+//!   "https://deno.land/x/sqlite/": { "read": ["./data"], "write": ["./data"] }
+//! }
+//! ```
+//!
+//! `read`, `write`, `net`, `env` and `run` are either `true` or a whitelist
+//! using the syntax of the corresponding `--allow-*` flag, `plugin` and
+//! `hrtime` are booleans. Omitted permissions are denied. A module matched by
+//! several entries gets the permissions of the most specific one, and modules
+//! that don't match any entry are only restricted by the global permissions.
+//! Ops dispatched without a known calling module, e.g. from a runtime function
+//! passed to `setTimeout()`, are restricted by every entry.
+use crate::permissions::DenoPermissions;
+use crate::permissions::PermissionsDescriptor;
+use deno_core::ErrBox;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use url::Url;
+
+#[derive(Debug)]
+struct PolicyScope {
+  /// The policy key as written in the file, used in error messages.
+  key: String,
+  url: Url,
+  /// Keys without a path, e.g. `https://deno.land`, match every module of
+  /// that origin. Other keys match modules whose URL starts with the key.
+  is_origin: bool,
+  permissions: DenoPermissions,
+}
+
+impl PolicyScope {
+  fn matches(&self, module: &Url) -> bool {
+    if self.is_origin {
+      module.scheme() == self.url.scheme()
+        && module.host() == self.url.host()
+        && module.port_or_known_default() == self.url.port_or_known_default()
+    } else {
+      module.as_str().starts_with(self.url.as_str())
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct PermissionPolicy {
+  /// Sorted from the most to the least specific scope.
+  scopes: Vec<PolicyScope>,
+}
+
+impl PermissionPolicy {
+  pub fn load(file_path: &Path) -> Result<Self, ErrBox> {
+    debug!("Attempt to load permission policy: {}", file_path.display());
+    let json_string = fs::read_to_string(file_path).map_err(|err| {
+      invalid_policy(format!(
+        "Error retrieving permission policy file at \"{}\": {}",
+        file_path.display(),
+        err
+      ))
+    })?;
+    // Paths in the policy are relative to the policy file.
+    let base_dir = file_path.parent().unwrap_or_else(|| Path::new("/"));
+    Self::from_json(base_dir, &json_string).map_err(ErrBox::from)
+  }
+
+  pub fn from_json(
+    base_dir: &Path,
+    json_string: &str,
+  ) -> Result<Self, io::Error> {
+    let entries: HashMap<String, PermissionsDescriptor> =
+      serde_json::from_str(json_string).map_err(|err| {
+        invalid_policy(format!("Unable to parse permission policy: {}", err))
+      })?;
+    let mut scopes = entries
+      .into_iter()
+      .map(|(key, entry)| {
+        let url = Url::parse(&key).map_err(|err| {
+          invalid_policy(format!("Invalid module prefix \"{}\": {}", key, err))
+        })?;
+        let is_origin = url.path() == "/" && !key.ends_with('/');
+        let permissions =
+          entry.to_permissions(base_dir).map_err(invalid_policy)?;
+        Ok(PolicyScope {
+          key,
+          url,
+          is_origin,
+          permissions,
+        })
+      })
+      .collect::<Result<Vec<PolicyScope>, io::Error>>()?;
+    scopes.sort_by_key(|scope| (!scope.is_origin, scope.url.as_str().len()));
+    scopes.reverse();
+    Ok(Self { scopes })
+  }
+
+  /// Returns the policy key and the permissions for the module with the given
+  /// specifier, or `None` if the policy doesn't restrict the module.
+  pub fn find(&self, module: &str) -> Option<(&str, &DenoPermissions)> {
+    let module = Url::parse(module).ok()?;
+    self
+      .scopes
+      .iter()
+      .find(|scope| scope.matches(&module))
+      .map(|scope| (scope.key.as_str(), &scope.permissions))
+  }
+
+  /// Returns the policy key and the permissions of every entry.
+  pub fn entries(&self) -> Vec<(&str, &DenoPermissions)> {
+    self
+      .scopes
+      .iter()
+      .map(|scope| (scope.key.as_str(), &scope.permissions))
+      .collect()
+  }
+}
+
+fn invalid_policy(msg: String) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn find_most_specific_scope() {
+    let policy = PermissionPolicy::from_json(
+      Path::new("/app"),
+      r#"{
+        "https://deno.land": { "net": true },
+        "https://deno.land/x/": { "net": ["deno.land"] },
+        "https://deno.land/x/sqlite/": { "read": ["data"] }
+      }"#,
+    )
+    .unwrap();
+
+    let (key, permissions) =
+      policy.find("https://deno.land/x/sqlite/mod.ts").unwrap();
+    assert_eq!(key, "https://deno.land/x/sqlite/");
+    assert!(permissions.check_read(Path::new("/app/data/db")).is_ok());
+    assert!(permissions.check_read(Path::new("/app/other")).is_err());
+    assert!(permissions.check_net("deno.land", 443).is_err());
+
+    let (key, permissions) =
+      policy.find("https://deno.land/x/oak/mod.ts").unwrap();
+    assert_eq!(key, "https://deno.land/x/");
+    assert!(permissions.check_net("deno.land", 443).is_ok());
+    assert!(permissions.check_net("example.com", 443).is_err());
+
+    let (key, permissions) =
+      policy.find("https://deno.land/std/http/server.ts").unwrap();
+    assert_eq!(key, "https://deno.land");
+    assert!(permissions.check_net("example.com", 443).is_ok());
+    assert!(permissions.check_env_var("HOME").is_err());
+
+    assert!(policy
+      .find("https://deno.land.example.com/mod.ts")
+      .is_none());
+    assert!(policy.find("file:///app/main.ts").is_none());
+  }
+
+  #[test]
+  fn invalid_policies() {
+    let base_dir = Path::new("/app");
+    assert!(PermissionPolicy::from_json(base_dir, "[]").is_err());
+    assert!(
+      PermissionPolicy::from_json(base_dir, r#"{ "deno.land": {} }"#).is_err()
+    );
+    assert!(PermissionPolicy::from_json(
+      base_dir,
+      r#"{ "https://deno.land/": { "network": true } }"#
+    )
+    .is_err());
+    assert!(PermissionPolicy::from_json(
+      base_dir,
+      r#"{ "https://deno.land/": { "net": ["10.0.0.0/33"] } }"#
+    )
+    .is_err());
+  }
+}
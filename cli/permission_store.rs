@@ -0,0 +1,166 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+//! Permissions granted with "always grant" at the permission prompt. They are
+//! stored in `permissions.json` in the Deno directory, keyed by the URL of the
+//! main module, and granted again whenever the same main module is run.
+//! `deno permissions` lists and clears them.
+use crate::flags::DenoSubcommand;
+use crate::flags::Flags;
+use crate::fs as deno_fs;
+use crate::permissions::PermissionsDescriptor;
+use deno_core::ErrBox;
+use deno_core::ModuleSpecifier;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Stored grants, by main module URL.
+pub type StoredGrants = BTreeMap<String, PermissionsDescriptor>;
+
+#[derive(Clone, Debug)]
+pub struct PermissionStore {
+  path: PathBuf,
+  main_module: String,
+}
+
+impl PermissionStore {
+  pub fn new(deno_dir_root: &Path, main_module: &ModuleSpecifier) -> Self {
+    Self {
+      path: store_path(deno_dir_root),
+      main_module: main_module.to_string(),
+    }
+  }
+
+  /// Returns the store of the main module of `deno run`. Other subcommands
+  /// don't remember grants.
+  pub fn from_flags(flags: &Flags, deno_dir_root: &Path) -> Option<Self> {
+    let script = match &flags.subcommand {
+      DenoSubcommand::Run { script } => script,
+      _ => return None,
+    };
+    let main_module = ModuleSpecifier::resolve_url_or_path(script).ok()?;
+    Some(Self::new(deno_dir_root, &main_module))
+  }
+
+  /// Returns the grants remembered for the main module.
+  pub fn load(&self) -> Result<Option<PermissionsDescriptor>, ErrBox> {
+    Ok(read_grants(&self.path)?.remove(&self.main_module))
+  }
+
+  /// Remembers a grant for the main module. `scope` is an entry of the
+  /// whitelist of the permission, `None` grants the whole permission.
+  pub fn remember(
+    &self,
+    name: &str,
+    scope: Option<&str>,
+  ) -> Result<(), ErrBox> {
+    let mut grants = read_grants(&self.path)?;
+    grants
+      .entry(self.main_module.clone())
+      .or_default()
+      .grant(name, scope);
+    write_grants(&self.path, &grants)
+  }
+}
+
+fn store_path(deno_dir_root: &Path) -> PathBuf {
+  deno_dir_root.join("permissions.json")
+}
+
+fn read_grants(path: &Path) -> Result<StoredGrants, ErrBox> {
+  match fs::read_to_string(path) {
+    Ok(json_string) => serde_json::from_str(&json_string).map_err(|err| {
+      ErrBox::from(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!(
+          "Unable to parse stored permissions at \"{}\": {}",
+          path.display(),
+          err
+        ),
+      ))
+    }),
+    Err(err) if err.kind() == io::ErrorKind::NotFound => {
+      Ok(StoredGrants::new())
+    }
+    Err(err) => Err(ErrBox::from(err)),
+  }
+}
+
+fn write_grants(path: &Path, grants: &StoredGrants) -> Result<(), ErrBox> {
+  if let Some(parent) = path.parent() {
+    fs::create_dir_all(parent)?;
+  }
+  let json_string = serde_json::to_string_pretty(grants)?;
+  deno_fs::write_file(path, json_string, 0o600)?;
+  Ok(())
+}
+
+/// Returns the stored grants of every main module, or only of `main_module`.
+pub fn list(
+  deno_dir_root: &Path,
+  main_module: Option<&ModuleSpecifier>,
+) -> Result<StoredGrants, ErrBox> {
+  let mut grants = read_grants(&store_path(deno_dir_root))?;
+  if let Some(main_module) = main_module {
+    grants.retain(|module, _| module == main_module.as_str());
+  }
+  Ok(grants)
+}
+
+/// Forgets the stored grants of every main module, or only of `main_module`.
+/// Returns the number of main modules whose grants were removed.
+pub fn clear(
+  deno_dir_root: &Path,
+  main_module: Option<&ModuleSpecifier>,
+) -> Result<usize, ErrBox> {
+  let path = store_path(deno_dir_root);
+  let mut grants = read_grants(&path)?;
+  let count = grants.len();
+  match main_module {
+    Some(main_module) => {
+      grants.remove(main_module.as_str());
+    }
+    None => grants.clear(),
+  }
+  let removed = count - grants.len();
+  if removed > 0 {
+    write_grants(&path, &grants)?;
+  }
+  Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn remember_list_and_clear() {
+    let temp_dir = tempfile::TempDir::new().expect("tempdir fail");
+    let root = temp_dir.path();
+    let tool = ModuleSpecifier::resolve_url("file:///tool.ts").unwrap();
+    let other = ModuleSpecifier::resolve_url("https://deno.land/x.ts").unwrap();
+
+    let store = PermissionStore::new(root, &tool);
+    assert!(store.load().unwrap().is_none());
+    store.remember("read", Some("/data")).unwrap();
+    store.remember("read", Some("/data")).unwrap();
+    store.remember("net", Some("deno.land")).unwrap();
+    store.remember("hrtime", None).unwrap();
+    PermissionStore::new(root, &other)
+      .remember("env", None)
+      .unwrap();
+
+    let grants = store.load().unwrap().unwrap();
+    assert_eq!(grants.read.list(), ["/data".to_string()]);
+    assert_eq!(grants.net.list(), ["deno.land".to_string()]);
+    assert!(grants.hrtime);
+    assert!(!grants.env.is_all());
+
+    assert_eq!(list(root, None).unwrap().len(), 2);
+    assert_eq!(list(root, Some(&other)).unwrap().len(), 1);
+    assert_eq!(clear(root, Some(&tool)).unwrap(), 1);
+    assert!(store.load().unwrap().is_none());
+    assert_eq!(clear(root, None).unwrap(), 1);
+    assert!(list(root, None).unwrap().is_empty());
+  }
+}
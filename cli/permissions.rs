@@ -1,18 +1,24 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 use crate::colors;
 use crate::flags::Flags;
+use crate::fs::resolve_program;
+use crate::fs::{resolve_real_parent, resolve_real_path};
+use crate::js::RUNTIME_SCRIPT_NAMES;
 use crate::op_error::OpError;
+use crate::permission_policy::PermissionPolicy;
+use crate::permission_store::PermissionStore;
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fmt;
+use std::fs::{File, OpenOptions};
 #[cfg(not(test))]
 use std::io;
+use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::path::{Path, PathBuf};
-#[cfg(test)]
-use std::sync::atomic::AtomicBool;
-#[cfg(test)]
-use std::sync::atomic::Ordering;
-#[cfg(test)]
-use std::sync::Mutex;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
 use url::Url;
 
 const PERMISSION_EMOJI: &str = "⚠️";
@@ -45,18 +51,6 @@ impl PermissionState {
       *self = PermissionState::Ask;
     }
   }
-  /// Requests the permission.
-  pub fn request(&mut self, msg: &str) -> PermissionState {
-    if *self != PermissionState::Ask {
-      return *self;
-    }
-    if permission_prompt(msg) {
-      *self = PermissionState::Allow;
-    } else {
-      *self = PermissionState::Deny;
-    }
-    *self
-  }
 }
 
 impl From<usize> for PermissionState {
@@ -101,67 +95,489 @@ pub struct DenoPermissions {
   // Keep in sync with cli/js/permissions.ts
   pub allow_read: PermissionState,
   pub read_whitelist: HashSet<PathBuf>,
+  pub read_blacklist: HashSet<PathBuf>,
   pub allow_write: PermissionState,
   pub write_whitelist: HashSet<PathBuf>,
+  pub write_blacklist: HashSet<PathBuf>,
   pub allow_net: PermissionState,
-  pub net_whitelist: HashSet<String>,
+  pub net_whitelist: Vec<NetRule>,
+  pub net_blacklist: Vec<NetRule>,
   pub allow_env: PermissionState,
+  pub env_whitelist: HashSet<String>,
+  pub env_blacklist: HashSet<String>,
   pub allow_run: PermissionState,
+  pub run_whitelist: HashSet<PathBuf>,
+  pub run_blacklist: HashSet<PathBuf>,
   pub allow_plugin: PermissionState,
   pub allow_hrtime: PermissionState,
+  pub audit: Option<PermissionAudit>,
+  pub policy: Option<Arc<PermissionPolicy>>,
+  /// Where "always grant" answers at the prompt are remembered.
+  pub store: Option<PermissionStore>,
+  /// The op being dispatched, see `enter_op()`.
+  op_name: Option<String>,
+  /// The modules on the JavaScript stack of the op being dispatched,
+  /// innermost first, see `enter_op()`.
+  calling_modules: Vec<String>,
+  /// Whether modules that called the op being dispatched may be missing
+  /// from `calling_modules`, see `enter_op()`.
+  calling_modules_unknown: bool,
 }
 
 impl DenoPermissions {
   pub fn from_flags(flags: &Flags) -> Self {
     Self {
       allow_read: PermissionState::from(flags.allow_read),
-      read_whitelist: flags.read_whitelist.iter().cloned().collect(),
+      read_whitelist: resolve_path_whitelist(&flags.read_whitelist),
+      read_blacklist: resolve_path_blacklist(&flags.read_blacklist),
       allow_write: PermissionState::from(flags.allow_write),
-      write_whitelist: flags.write_whitelist.iter().cloned().collect(),
+      write_whitelist: resolve_path_whitelist(&flags.write_whitelist),
+      write_blacklist: resolve_path_blacklist(&flags.write_blacklist),
       allow_net: PermissionState::from(flags.allow_net),
-      net_whitelist: flags.net_whitelist.iter().cloned().collect(),
+      net_whitelist: parse_net_rules(&flags.net_whitelist),
+      net_blacklist: parse_net_rules(&flags.net_blacklist),
       allow_env: PermissionState::from(flags.allow_env),
+      env_whitelist: flags.env_whitelist.iter().cloned().collect(),
+      env_blacklist: flags.env_blacklist.iter().cloned().collect(),
       allow_run: PermissionState::from(flags.allow_run),
+      run_whitelist: resolve_run_whitelist(&flags.run_whitelist),
+      run_blacklist: resolve_run_blacklist(&flags.run_blacklist),
       allow_plugin: PermissionState::from(flags.allow_plugin),
       allow_hrtime: PermissionState::from(flags.allow_hrtime),
+      audit: None,
+      policy: None,
+      store: None,
+      op_name: None,
+      calling_modules: vec![],
+      calling_modules_unknown: false,
+    }
+  }
+
+  /// Grants what `grants` describes in addition to the current permissions.
+  pub fn add_grants(&mut self, grants: &PermissionsDescriptor) {
+    for (name, grant) in &[
+      ("read", &grants.read),
+      ("write", &grants.write),
+      ("net", &grants.net),
+      ("env", &grants.env),
+      ("run", &grants.run),
+    ] {
+      if grant.is_all() {
+        self.grant_scope(name, None);
+      }
+      for scope in grant.list() {
+        self.grant_scope(name, Some(scope));
+      }
+    }
+    if grants.plugin {
+      self.grant_scope("plugin", None);
+    }
+    if grants.hrtime {
+      self.grant_scope("hrtime", None);
+    }
+  }
+
+  fn state_mut(&mut self, name: &str) -> &mut PermissionState {
+    match name {
+      "read" => &mut self.allow_read,
+      "write" => &mut self.allow_write,
+      "net" => &mut self.allow_net,
+      "env" => &mut self.allow_env,
+      "run" => &mut self.allow_run,
+      "plugin" => &mut self.allow_plugin,
+      "hrtime" => &mut self.allow_hrtime,
+      n => unreachable!("No such permission name: {}", n),
+    }
+  }
+
+  /// Grants the part of a permission described by `scope`, an entry of its
+  /// whitelist, or the whole permission if `scope` is `None`. Permissions
+  /// that were denied stay denied.
+  fn grant_scope(&mut self, name: &str, scope: Option<&str>) {
+    let scope = match scope {
+      Some(scope) => scope,
+      None => {
+        let state = self.state_mut(name);
+        if *state == PermissionState::Ask {
+          *state = PermissionState::Allow;
+        }
+        return;
+      }
+    };
+    match name {
+      "read" => {
+        self
+          .read_whitelist
+          .insert(resolve_real_path(Path::new(scope)));
+      }
+      "write" => {
+        self
+          .write_whitelist
+          .insert(resolve_real_path(Path::new(scope)));
+      }
+      "net" => match scope.parse() {
+        Ok(rule) => self.net_whitelist.push(rule),
+        Err(e) => warn!("Ignoring stored net rule {}: {}", scope, e),
+      },
+      "env" => {
+        self.env_whitelist.insert(scope.to_string());
+      }
+      "run" => {
+        self.run_whitelist.insert(PathBuf::from(scope));
+      }
+      _ => {}
+    }
+  }
+
+  /// Prompts for a permission that is in the `Ask` state. `scope` is the part
+  /// of the permission that was asked for, as an entry of its whitelist, and
+  /// `scope_label` describes it to the user. Answering "s" only grants the
+  /// scope, answering "a" also remembers it for future runs of the main
+  /// module. "g" and "d" grant and deny the whole permission.
+  fn prompt(
+    &mut self,
+    name: &str,
+    msg: &str,
+    scope: Option<(String, String)>,
+  ) -> PermissionState {
+    let state = *self.state_mut(name);
+    if state != PermissionState::Ask {
+      return state;
+    }
+    let scope_label = scope.as_ref().map(|(_, label)| label.as_str());
+    let answer = permission_prompt(msg, scope_label);
+    let scope = scope.as_ref().map(|(scope, _)| scope.as_str());
+    match answer {
+      PromptAnswer::Grant => {
+        *self.state_mut(name) = PermissionState::Allow;
+      }
+      PromptAnswer::GrantScope => self.grant_scope(name, scope),
+      PromptAnswer::AlwaysGrant => {
+        self.grant_scope(name, scope);
+        if let Some(store) = &self.store {
+          if let Err(e) = store.remember(name, scope) {
+            warn!("Failed to remember the permission: {}", e);
+          }
+        }
+      }
+      PromptAnswer::Deny => {
+        *self.state_mut(name) = PermissionState::Deny;
+        return PermissionState::Deny;
+      }
+    }
+    PermissionState::Allow
+  }
+
+  /// Whether `enter_op()` needs to be called for every op, which requires
+  /// capturing the JavaScript stack of the op.
+  pub fn needs_op_context(&self) -> bool {
+    self.audit.is_some() || self.policy.is_some()
+  }
+
+  /// Records the op being dispatched and the modules that called it, so that
+  /// checks made while the op runs are attributed to them in the audit log
+  /// and restricted by their permission policy. `call_stack` holds the script
+  /// names of the calling frames, innermost first; scripts that are part of
+  /// the runtime are skipped. `truncated` tells that the outermost frames of
+  /// the stack are missing.
+  ///
+  /// An op dispatched without any module on the stack, e.g. a runtime
+  /// function passed to `then()` or `setTimeout()`, or with a truncated
+  /// stack may have been set up by any module, so it gets the permissions
+  /// granted by every entry of the permission policy.
+  pub fn enter_op(
+    &mut self,
+    op_name: Option<&str>,
+    call_stack: &[String],
+    truncated: bool,
+  ) {
+    self.op_name = op_name.map(str::to_string);
+    self.calling_modules.clear();
+    // Code evaluated at runtime has no name, and could have been created by
+    // any module.
+    let mut evaluated = false;
+    for script_name in call_stack {
+      if script_name.is_empty() {
+        evaluated = true;
+      } else if !RUNTIME_SCRIPT_NAMES.contains(script_name)
+        && !self.calling_modules.contains(script_name)
+      {
+        self.calling_modules.push(script_name.clone());
+      }
     }
+    self.calling_modules_unknown = op_name.is_some()
+      && (truncated || evaluated || self.calling_modules.is_empty());
+  }
+
+  pub fn exit_op(&mut self) {
+    self.op_name = None;
+    self.calling_modules.clear();
+    self.calling_modules_unknown = false;
+  }
+
+  /// Checks an access that the global permissions allow against the policy
+  /// of every module on the stack, so that a module can't gain access by
+  /// calling into a more privileged one.
+  fn check_policy(
+    &self,
+    msg: &str,
+    check: impl Fn(&DenoPermissions) -> Result<(), OpError>,
+  ) -> Result<(), OpError> {
+    for (key, permissions) in self.calling_module_policies() {
+      if check(permissions).is_err() {
+        let reason = if self.calling_modules_unknown {
+          " as the calling module is unknown"
+        } else {
+          ""
+        };
+        return Err(OpError::permission_denied(format!(
+          "{}, denied by the permission policy for \"{}\"{}",
+          msg, key, reason
+        )));
+      }
+    }
+    Ok(())
+  }
+
+  /// Returns `Deny` if the permission policy of a module on the stack doesn't
+  /// grant the permission, and `Allow` otherwise.
+  pub fn get_policy_state(
+    &self,
+    name: &str,
+    url: &Option<&str>,
+    path: &Option<&Path>,
+    variable: &Option<&str>,
+    program: &Option<&Path>,
+  ) -> Result<PermissionState, OpError> {
+    for (_, permissions) in self.calling_module_policies() {
+      let state =
+        permissions.get_permission_state(name, url, path, variable, program)?;
+      if state != PermissionState::Allow {
+        return Ok(PermissionState::Deny);
+      }
+    }
+    Ok(PermissionState::Allow)
+  }
+
+  /// Returns the policy key and permissions of every module on the stack
+  /// that the permission policy restricts, or of every entry of the policy
+  /// if the calling modules are unknown.
+  fn calling_module_policies(&self) -> Vec<(&str, &DenoPermissions)> {
+    match &self.policy {
+      Some(policy) if self.calling_modules_unknown => policy.entries(),
+      Some(policy) => self
+        .calling_modules
+        .iter()
+        .filter_map(|module| policy.find(module))
+        .collect(),
+      None => vec![],
+    }
+  }
+
+  fn audit_check<T>(
+    &self,
+    permission: &str,
+    resource: Option<impl fmt::Display>,
+    result: &Result<T, OpError>,
+  ) {
+    if let Some(audit) = &self.audit {
+      let decision = match result {
+        Ok(_) => PermissionState::Allow,
+        Err(_) => PermissionState::Deny,
+      };
+      audit.log(self, "check", permission, resource, decision);
+    }
+  }
+
+  fn audit_request(
+    &self,
+    permission: &str,
+    resource: Option<impl fmt::Display>,
+    decision: PermissionState,
+  ) {
+    if let Some(audit) = &self.audit {
+      audit.log(self, "request", permission, resource, decision);
+    }
+  }
+
+  fn get_state_run(&self, program: &Option<&Path>) -> PermissionState {
+    if program.map_or(false, |p| {
+      find_program_in_blacklist(p, &self.run_blacklist).is_some()
+    }) {
+      return PermissionState::Deny;
+    }
+    if program.map_or(false, |p| self.run_whitelist.contains(p)) {
+      return PermissionState::Allow;
+    }
+    self.allow_run
   }
 
   pub fn check_run(&self) -> Result<(), OpError> {
-    self
+    let msg = "access to run a subprocess";
+    let result = self
       .allow_run
-      .check("access to run a subprocess", "--allow-run")
+      .check(msg, "--allow-run")
+      .and_then(|()| self.check_policy(msg, |p| p.check_run()));
+    self.audit_check("run", None::<&str>, &result);
+    result
+  }
+
+  /// `program` must already be resolved with `fs::resolve_program`, so that
+  /// it is compared against the whitelist as the file that will be executed.
+  pub fn check_run_program(&self, program: &Path) -> Result<(), OpError> {
+    let msg = format!("access to run a subprocess \"{}\"", program.display());
+    let result = if let Some(rule) =
+      find_program_in_blacklist(program, &self.run_blacklist)
+    {
+      Err(deny_rule_error(&msg, "--deny-run", rule.display()))
+    } else {
+      self
+        .get_state_run(&Some(program))
+        .check(&msg, "--allow-run")
+        .and_then(|()| {
+          self.check_policy(&msg, |p| p.check_run_program(program))
+        })
+    };
+    self.audit_check("run", Some(program.display()), &result);
+    result
   }
 
   fn get_state_read(&self, path: &Option<&Path>) -> PermissionState {
-    if path.map_or(false, |f| check_path_white_list(f, &self.read_whitelist)) {
+    self.get_state_read_at(path, resolve_real_path)
+  }
+
+  fn get_state_read_at(
+    &self,
+    path: &Option<&Path>,
+    resolve: fn(&Path) -> PathBuf,
+  ) -> PermissionState {
+    if path.map_or(false, |f| is_path_in_list(f, &self.read_blacklist, resolve))
+    {
+      return PermissionState::Deny;
+    }
+    if path.map_or(false, |f| {
+      check_path_white_list(f, &self.read_whitelist, resolve)
+    }) {
       return PermissionState::Allow;
     }
     self.allow_read
   }
 
   pub fn check_read(&self, path: &Path) -> Result<(), OpError> {
-    self.get_state_read(&Some(path)).check(
-      &format!("read access to \"{}\"", path.display()),
-      "--allow-read",
-    )
+    self.check_read_at(path, resolve_real_path)
+  }
+
+  /// Like `check_read()`, but for the directory entry `path` itself rather
+  /// than the target of a symlink it names.
+  pub fn check_read_entry(&self, path: &Path) -> Result<(), OpError> {
+    self.check_read_at(path, resolve_real_parent)
+  }
+
+  fn check_read_at(
+    &self,
+    path: &Path,
+    resolve: fn(&Path) -> PathBuf,
+  ) -> Result<(), OpError> {
+    let msg = format!("read access to \"{}\"", path.display());
+    let result = if let Some(rule) =
+      find_path_in_list(path, &self.read_blacklist, resolve)
+    {
+      Err(deny_rule_error(&msg, "--deny-read", rule.display()))
+    } else {
+      self
+        .get_state_read_at(&Some(path), resolve)
+        .check(&msg, "--allow-read")
+        .and_then(|()| {
+          self.check_policy(&msg, |p| p.check_read_at(path, resolve))
+        })
+    };
+    self.audit_check("read", Some(path.display()), &result);
+    result
+  }
+
+  /// Checks read access to `what`, whose path isn't known, e.g. a descriptor
+  /// received from another process. Only `--allow-read` for every path
+  /// grants it.
+  pub fn check_read_all(&self, what: &str) -> Result<(), OpError> {
+    let msg = format!("read access to {}", what);
+    let result = self
+      .allow_read
+      .check(&msg, "--allow-read")
+      .and_then(|()| self.check_policy(&msg, |p| p.check_read_all(what)));
+    self.audit_check("read", Some(what), &result);
+    result
   }
 
   fn get_state_write(&self, path: &Option<&Path>) -> PermissionState {
-    if path.map_or(false, |f| check_path_white_list(f, &self.write_whitelist)) {
+    self.get_state_write_at(path, resolve_real_path)
+  }
+
+  fn get_state_write_at(
+    &self,
+    path: &Option<&Path>,
+    resolve: fn(&Path) -> PathBuf,
+  ) -> PermissionState {
+    if path.map_or(false, |f| {
+      is_path_in_list(f, &self.write_blacklist, resolve)
+    }) {
+      return PermissionState::Deny;
+    }
+    if path.map_or(false, |f| {
+      check_path_white_list(f, &self.write_whitelist, resolve)
+    }) {
       return PermissionState::Allow;
     }
     self.allow_write
   }
 
   pub fn check_write(&self, path: &Path) -> Result<(), OpError> {
-    self.get_state_write(&Some(path)).check(
-      &format!("write access to \"{}\"", path.display()),
-      "--allow-write",
-    )
+    self.check_write_at(path, resolve_real_path)
+  }
+
+  /// Like `check_write()`, but for the directory entry `path` itself rather
+  /// than the target of a symlink it names.
+  pub fn check_write_entry(&self, path: &Path) -> Result<(), OpError> {
+    self.check_write_at(path, resolve_real_parent)
+  }
+
+  fn check_write_at(
+    &self,
+    path: &Path,
+    resolve: fn(&Path) -> PathBuf,
+  ) -> Result<(), OpError> {
+    let msg = format!("write access to \"{}\"", path.display());
+    let result = if let Some(rule) =
+      find_path_in_list(path, &self.write_blacklist, resolve)
+    {
+      Err(deny_rule_error(&msg, "--deny-write", rule.display()))
+    } else {
+      self
+        .get_state_write_at(&Some(path), resolve)
+        .check(&msg, "--allow-write")
+        .and_then(|()| {
+          self.check_policy(&msg, |p| p.check_write_at(path, resolve))
+        })
+    };
+    self.audit_check("write", Some(path.display()), &result);
+    result
+  }
+
+  /// Checks write access to `what`, whose path isn't known. Only
+  /// `--allow-write` for every path grants it.
+  pub fn check_write_all(&self, what: &str) -> Result<(), OpError> {
+    let msg = format!("write access to {}", what);
+    let result = self
+      .allow_write
+      .check(&msg, "--allow-write")
+      .and_then(|()| self.check_policy(&msg, |p| p.check_write_all(what)));
+    self.audit_check("write", Some(what), &result);
+    result
   }
 
   fn get_state_net(&self, host: &str, port: Option<u16>) -> PermissionState {
+    if find_host_and_port_in_list(host, port, &self.net_blacklist).is_some() {
+      return PermissionState::Deny;
+    }
     if check_host_and_port_whitelist(host, port, &self.net_whitelist) {
       return PermissionState::Allow;
     }
@@ -178,206 +594,1210 @@ impl DenoPermissions {
     let url: &str = url.unwrap();
     // If url is invalid, then throw a TypeError.
     let parsed = Url::parse(url).map_err(OpError::from)?;
-    Ok(
-      self.get_state_net(&format!("{}", parsed.host().unwrap()), parsed.port()),
+    let host = format!("{}", parsed.host().unwrap());
+    // Deny rules with a port also apply to URLs using the scheme's default.
+    if find_host_and_port_in_list(
+      &host,
+      parsed.port_or_known_default(),
+      &self.net_blacklist,
     )
+    .is_some()
+    {
+      return Ok(PermissionState::Deny);
+    }
+    Ok(self.get_state_net(&host, parsed.port()))
   }
 
   pub fn check_net(&self, hostname: &str, port: u16) -> Result<(), OpError> {
-    self.get_state_net(hostname, Some(port)).check(
-      &format!("network access to \"{}:{}\"", hostname, port),
-      "--allow-net",
-    )
+    let msg = format!("network access to \"{}:{}\"", hostname, port);
+    let result = if let Some(rule) =
+      find_host_and_port_in_list(hostname, Some(port), &self.net_blacklist)
+    {
+      Err(deny_rule_error(&msg, "--deny-net", rule))
+    } else {
+      self
+        .get_state_net(hostname, Some(port))
+        .check(&msg, "--allow-net")
+        .and_then(|()| self.check_policy(&msg, |p| p.check_net(hostname, port)))
+    };
+    let resource = format!("{}:{}", hostname, port);
+    self.audit_check("net", Some(resource), &result);
+    result
+  }
+
+  /// Checks access to `hostname` without a port, e.g. to look it up in the
+  /// DNS. A host allowed on any port is allowed, a host is only denied by a
+  /// deny rule without a port.
+  pub fn check_net_host(&self, hostname: &str) -> Result<(), OpError> {
+    let msg = format!("network access to \"{}\"", hostname);
+    let result = if let Some(rule) = self
+      .net_blacklist
+      .iter()
+      .find(|rule| rule.ports.is_none() && rule.matches_host(hostname))
+    {
+      Err(deny_rule_error(&msg, "--deny-net", rule))
+    } else {
+      let allowed = self
+        .net_whitelist
+        .iter()
+        .any(|rule| rule.matches_host(hostname));
+      let state = if allowed {
+        PermissionState::Allow
+      } else {
+        self.allow_net
+      };
+      state
+        .check(&msg, "--allow-net")
+        .and_then(|()| self.check_policy(&msg, |p| p.check_net_host(hostname)))
+    };
+    self.audit_check("net", Some(hostname), &result);
+    result
+  }
+
+  /// Checks the address a hostname resolved to against the deny rules, so
+  /// that a name pointing into a denied network can't be used to reach it.
+  /// The allow rules were already checked against the hostname.
+  pub fn check_net_addr(&self, addr: &SocketAddr) -> Result<(), OpError> {
+    let host = addr.ip().to_string();
+    let result = match find_host_and_port_in_list(
+      &host,
+      Some(addr.port()),
+      &self.net_blacklist,
+    ) {
+      Some(rule) => {
+        let msg = format!("network access to \"{}\"", addr);
+        Err(deny_rule_error(&msg, "--deny-net", rule))
+      }
+      None => return Ok(()),
+    };
+    self.audit_check("net", Some(addr), &result);
+    result
   }
 
   pub fn check_net_url(&self, url: &url::Url) -> Result<(), OpError> {
     let host = url
       .host_str()
       .ok_or_else(|| OpError::uri_error("missing host".to_owned()))?;
-    self
-      .get_state_net(host, url.port())
-      .check(&format!("network access to \"{}\"", url), "--allow-net")
+    let msg = format!("network access to \"{}\"", url);
+    // Deny rules with a port also apply to URLs using the scheme's default.
+    let result = if let Some(rule) = find_host_and_port_in_list(
+      host,
+      url.port_or_known_default(),
+      &self.net_blacklist,
+    ) {
+      Err(deny_rule_error(&msg, "--deny-net", rule))
+    } else {
+      self
+        .get_state_net(host, url.port())
+        .check(&msg, "--allow-net")
+        .and_then(|()| self.check_policy(&msg, |p| p.check_net_url(url)))
+    };
+    self.audit_check("net", Some(url), &result);
+    result
+  }
+
+  fn get_state_env(&self, key: &Option<&str>) -> PermissionState {
+    if key.map_or(false, |k| {
+      find_env_in_list(k, &self.env_blacklist).is_some()
+    }) {
+      return PermissionState::Deny;
+    }
+    if key.map_or(false, |k| check_env_white_list(k, &self.env_whitelist)) {
+      return PermissionState::Allow;
+    }
+    self.allow_env
   }
 
   pub fn check_env(&self) -> Result<(), OpError> {
-    self
+    let msg = "access to environment variables";
+    let result = self
       .allow_env
-      .check("access to environment variables", "--allow-env")
+      .check(msg, "--allow-env")
+      .and_then(|()| self.check_policy(msg, |p| p.check_env()));
+    self.audit_check("env", None::<&str>, &result);
+    result
+  }
+
+  pub fn check_env_var(&self, key: &str) -> Result<(), OpError> {
+    let msg = format!("access to environment variable \"{}\"", key);
+    let result = if let Some(rule) = find_env_in_list(key, &self.env_blacklist)
+    {
+      Err(deny_rule_error(&msg, "--deny-env", rule))
+    } else {
+      self
+        .get_state_env(&Some(key))
+        .check(&msg, "--allow-env")
+        .and_then(|()| self.check_policy(&msg, |p| p.check_env_var(key)))
+    };
+    self.audit_check("env", Some(key), &result);
+    result
+  }
+
+  /// Returns whether reading the environment variable `key` is permitted,
+  /// without logging the access or producing an error.
+  pub fn is_env_var_allowed(&self, key: &str) -> bool {
+    self.get_state_env(&Some(key)).is_allow()
+      && self
+        .get_policy_state("env", &None, &None, &Some(key), &None)
+        .map_or(false, PermissionState::is_allow)
+  }
+
+  /// Revokes access to the environment, or only to the variable `key`. A
+  /// variable that would still be granted by a pattern or by `--allow-env`
+  /// after removing its own whitelist entry can't be revoked on its own.
+  pub fn revoke_env(&mut self, key: &Option<&str>) -> Result<(), OpError> {
+    let key = match key {
+      None => {
+        self.allow_env.revoke();
+        return Ok(());
+      }
+      Some(key) => normalize_env_key(key),
+    };
+    let mut env_whitelist = self.env_whitelist.clone();
+    env_whitelist.retain(|entry| normalize_env_key(entry) != key);
+    if self.allow_env.is_allow() || check_env_white_list(&key, &env_whitelist) {
+      return Err(OpError::other(format!(
+        "Can't revoke access to environment variable \"{}\", it is granted by a broader permission",
+        key
+      )));
+    }
+    self.env_whitelist = env_whitelist;
+    Ok(())
   }
 
   pub fn check_plugin(&self, path: &Path) -> Result<(), OpError> {
-    self.allow_plugin.check(
-      &format!("access to open a plugin: {}", path.display()),
-      "--allow-plugin",
-    )
+    let msg = format!("access to open a plugin: {}", path.display());
+    let result = self
+      .allow_plugin
+      .check(&msg, "--allow-plugin")
+      .and_then(|()| self.check_policy(&msg, |p| p.check_plugin(path)));
+    self.audit_check("plugin", Some(path.display()), &result);
+    result
   }
 
-  pub fn request_run(&mut self) -> PermissionState {
-    self
-      .allow_run
-      .request("Deno requests to access to run a subprocess")
+  pub fn request_run(&mut self, program: &Option<&Path>) -> PermissionState {
+    let state = if program.map_or(false, |p| {
+      find_program_in_blacklist(p, &self.run_blacklist).is_some()
+    }) {
+      PermissionState::Deny
+    } else if program.map_or(false, |p| self.run_whitelist.contains(p)) {
+      PermissionState::Allow
+    } else {
+      let msg = match program {
+        None => "Deno requests to access to run a subprocess".to_string(),
+        Some(program) => format!(
+          "Deno requests to access to run a subprocess \"{}\"",
+          program.display()
+        ),
+      };
+      let scope = program.map(|program| {
+        let program = program.display().to_string();
+        let label = format!("only \"{}\"", program);
+        (program, label)
+      });
+      self.prompt("run", &msg, scope)
+    };
+    self.audit_request("run", program.map(Path::display), state);
+    state
   }
 
   pub fn request_read(&mut self, path: &Option<&Path>) -> PermissionState {
-    if path.map_or(false, |f| check_path_white_list(f, &self.read_whitelist)) {
-      return PermissionState::Allow;
+    let state = if path.map_or(false, |f| {
+      is_path_in_list(f, &self.read_blacklist, resolve_real_path)
+    }) {
+      PermissionState::Deny
+    } else if path.map_or(false, |f| {
+      check_path_white_list(f, &self.read_whitelist, resolve_real_path)
+    }) {
+      PermissionState::Allow
+    } else {
+      let msg = match path {
+        None => "Deno requests read access".to_string(),
+        Some(path) => {
+          format!("Deno requests read access to \"{}\"", path.display())
+        }
+      };
+      self.prompt("read", &msg, path.map(directory_tree_scope))
     };
-    self.allow_read.request(&match path {
-      None => "Deno requests read access".to_string(),
-      Some(path) => {
-        format!("Deno requests read access to \"{}\"", path.display())
-      }
-    })
+    self.audit_request("read", path.map(Path::display), state);
+    state
   }
 
   pub fn request_write(&mut self, path: &Option<&Path>) -> PermissionState {
-    if path.map_or(false, |f| check_path_white_list(f, &self.write_whitelist)) {
-      return PermissionState::Allow;
+    let state = if path.map_or(false, |f| {
+      is_path_in_list(f, &self.write_blacklist, resolve_real_path)
+    }) {
+      PermissionState::Deny
+    } else if path.map_or(false, |f| {
+      check_path_white_list(f, &self.write_whitelist, resolve_real_path)
+    }) {
+      PermissionState::Allow
+    } else {
+      let msg = match path {
+        None => "Deno requests write access".to_string(),
+        Some(path) => {
+          format!("Deno requests write access to \"{}\"", path.display())
+        }
+      };
+      self.prompt("write", &msg, path.map(directory_tree_scope))
     };
-    self.allow_write.request(&match path {
-      None => "Deno requests write access".to_string(),
-      Some(path) => {
-        format!("Deno requests write access to \"{}\"", path.display())
-      }
-    })
+    self.audit_request("write", path.map(Path::display), state);
+    state
   }
 
   pub fn request_net(
     &mut self,
     url: &Option<&str>,
   ) -> Result<PermissionState, OpError> {
-    if self.get_state_net_url(url)? == PermissionState::Ask {
-      return Ok(self.allow_net.request(&match url {
+    let state = if self.get_state_net_url(url)? == PermissionState::Ask {
+      let msg = match url {
         None => "Deno requests network access".to_string(),
         Some(url) => format!("Deno requests network access to \"{}\"", url),
-      }));
+      };
+      let scope = match url {
+        Some(url) => Some(host_scope(url)?),
+        None => None,
+      };
+      self.prompt("net", &msg, scope)
+    } else {
+      self.get_state_net_url(url)?
     };
-    self.get_state_net_url(url)
+    self.audit_request("net", *url, state);
+    Ok(state)
   }
 
-  pub fn request_env(&mut self) -> PermissionState {
-    self
-      .allow_env
-      .request("Deno requests to access to environment variables")
+  pub fn request_env(&mut self, key: &Option<&str>) -> PermissionState {
+    let state = if key.map_or(false, |k| {
+      find_env_in_list(k, &self.env_blacklist).is_some()
+    }) {
+      PermissionState::Deny
+    } else if key
+      .map_or(false, |k| check_env_white_list(k, &self.env_whitelist))
+    {
+      PermissionState::Allow
+    } else {
+      let msg = match key {
+        None => "Deno requests to access to environment variables".to_string(),
+        Some(key) => format!(
+          "Deno requests to access to environment variable \"{}\"",
+          key
+        ),
+      };
+      let scope = key.map(|key| (key.to_string(), format!("only \"{}\"", key)));
+      self.prompt("env", &msg, scope)
+    };
+    self.audit_request("env", *key, state);
+    state
+  }
+
+  pub fn request_hrtime(&mut self) -> PermissionState {
+    let state = self.prompt(
+      "hrtime",
+      "Deno requests to access to high precision time",
+      None,
+    );
+    self.audit_request("hrtime", None::<&str>, state);
+    state
+  }
+
+  pub fn request_plugin(&mut self) -> PermissionState {
+    let state = self.prompt("plugin", "Deno requests to open plugins", None);
+    self.audit_request("plugin", None::<&str>, state);
+    state
+  }
+
+  pub fn get_permission_state(
+    &self,
+    name: &str,
+    url: &Option<&str>,
+    path: &Option<&Path>,
+    variable: &Option<&str>,
+    program: &Option<&Path>,
+  ) -> Result<PermissionState, OpError> {
+    let state = match name {
+      "run" => Ok(self.get_state_run(program)),
+      "read" => Ok(self.get_state_read(path)),
+      "write" => Ok(self.get_state_write(path)),
+      "net" => self.get_state_net_url(url),
+      "env" => Ok(self.get_state_env(variable)),
+      "plugin" => Ok(self.allow_plugin),
+      "hrtime" => Ok(self.allow_hrtime),
+      n => Err(OpError::other(format!("No such permission name: {}", n))),
+    }?;
+    if state == PermissionState::Allow {
+      return self.get_policy_state(name, url, path, variable, program);
+    }
+    Ok(state)
+  }
+
+  /// Builds the permissions of a web worker from the `permissions` passed to
+  /// `new Worker()`. Every permission granted by the descriptor must already
+  /// be granted to the creating worker, including by the permission policy of
+  /// the calling modules. The deny rules, audit log and policy of the creating
+  /// worker carry over to the new one.
+  pub fn narrow(
+    &self,
+    descriptor: &PermissionsDescriptor,
+    base_dir: &Path,
+  ) -> Result<DenoPermissions, OpError> {
+    let mut permissions = descriptor
+      .to_permissions(base_dir)
+      .map_err(OpError::type_error)?;
+    let not_granted = |what: String| {
+      OpError::permission_denied(format!(
+        "Can't grant {} to a worker, the creating worker doesn't have it",
+        what
+      ))
+    };
+    let is_allowed = |name: &str,
+                      path: &Option<&Path>,
+                      variable: &Option<&str>,
+                      program: &Option<&Path>|
+     -> Result<bool, OpError> {
+      self
+        .get_permission_state(name, &None, path, variable, program)
+        .map(PermissionState::is_allow)
+    };
+
+    if permissions.allow_read.is_allow()
+      && !is_allowed("read", &None, &None, &None)?
+    {
+      return Err(not_granted("read access".to_string()));
+    }
+    for path in &permissions.read_whitelist {
+      if !is_allowed("read", &Some(path.as_path()), &None, &None)? {
+        return Err(not_granted(format!(
+          "read access to \"{}\"",
+          path.display()
+        )));
+      }
+    }
+    if permissions.allow_write.is_allow()
+      && !is_allowed("write", &None, &None, &None)?
+    {
+      return Err(not_granted("write access".to_string()));
+    }
+    for path in &permissions.write_whitelist {
+      if !is_allowed("write", &Some(path.as_path()), &None, &None)? {
+        return Err(not_granted(format!(
+          "write access to \"{}\"",
+          path.display()
+        )));
+      }
+    }
+    if permissions.allow_net.is_allow()
+      && !is_allowed("net", &None, &None, &None)?
+    {
+      return Err(not_granted("network access".to_string()));
+    }
+    for rule in &permissions.net_whitelist {
+      let covered = |p: &DenoPermissions| {
+        p.allow_net.is_allow()
+          || p
+            .net_whitelist
+            .iter()
+            .any(|other| rule.is_covered_by(other))
+      };
+      if !covered(self)
+        || !self
+          .calling_module_policies()
+          .into_iter()
+          .all(|(_, p)| covered(p))
+      {
+        return Err(not_granted(format!("network access to \"{}\"", rule)));
+      }
+    }
+    if permissions.allow_env.is_allow()
+      && !is_allowed("env", &None, &None, &None)?
+    {
+      return Err(not_granted("access to the environment".to_string()));
+    }
+    for key in &permissions.env_whitelist {
+      let covered = |p: &DenoPermissions| {
+        p.allow_env.is_allow() || is_env_entry_covered(key, &p.env_whitelist)
+      };
+      if !covered(self)
+        || !self
+          .calling_module_policies()
+          .into_iter()
+          .all(|(_, p)| covered(p))
+      {
+        return Err(not_granted(format!(
+          "access to the environment variable \"{}\"",
+          key
+        )));
+      }
+    }
+    if permissions.allow_run.is_allow()
+      && !is_allowed("run", &None, &None, &None)?
+    {
+      return Err(not_granted("access to run subprocesses".to_string()));
+    }
+    for program in &permissions.run_whitelist {
+      if !is_allowed("run", &None, &None, &Some(program.as_path()))? {
+        return Err(not_granted(format!(
+          "access to run \"{}\"",
+          program.display()
+        )));
+      }
+    }
+    if permissions.allow_plugin.is_allow()
+      && !is_allowed("plugin", &None, &None, &None)?
+    {
+      return Err(not_granted("access to open plugins".to_string()));
+    }
+    if permissions.allow_hrtime.is_allow()
+      && !is_allowed("hrtime", &None, &None, &None)?
+    {
+      return Err(not_granted("access to high precision time".to_string()));
+    }
+
+    permissions.read_blacklist = self.read_blacklist.clone();
+    permissions.write_blacklist = self.write_blacklist.clone();
+    permissions.net_blacklist = self.net_blacklist.clone();
+    permissions.env_blacklist = self.env_blacklist.clone();
+    permissions.run_blacklist = self.run_blacklist.clone();
+    permissions.audit = self.audit.clone();
+    permissions.policy = self.policy.clone();
+    Ok(permissions)
+  }
+}
+
+/// A permission of a `PermissionsDescriptor`: either `true`, `false` or a
+/// whitelist using the syntax of the corresponding `--allow-*` flag.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum PermissionGrant {
+  All(bool),
+  List(Vec<String>),
+}
+
+impl Default for PermissionGrant {
+  fn default() -> Self {
+    PermissionGrant::All(false)
+  }
+}
+
+impl PermissionGrant {
+  pub fn is_all(&self) -> bool {
+    match self {
+      PermissionGrant::All(all) => *all,
+      PermissionGrant::List(_) => false,
+    }
+  }
+
+  pub fn list(&self) -> &[String] {
+    match self {
+      PermissionGrant::All(_) => &[],
+      PermissionGrant::List(list) => list,
+    }
+  }
+
+  /// Adds `scope` to the whitelist, or grants everything if it's `None`.
+  fn add(&mut self, scope: Option<&str>) {
+    match (&mut *self, scope) {
+      (PermissionGrant::All(true), _) => {}
+      (PermissionGrant::List(list), Some(scope)) => {
+        if !list.iter().any(|entry| entry == scope) {
+          list.push(scope.to_string());
+        }
+      }
+      (PermissionGrant::All(false), Some(scope)) => {
+        *self = PermissionGrant::List(vec![scope.to_string()]);
+      }
+      (_, None) => *self = PermissionGrant::All(true),
+    }
+  }
+}
+
+/// Permissions described in JSON, as found in the `--permission-policy` file
+/// and passed to `new Worker()`. Omitted permissions are denied.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PermissionsDescriptor {
+  pub read: PermissionGrant,
+  pub write: PermissionGrant,
+  pub net: PermissionGrant,
+  pub env: PermissionGrant,
+  pub run: PermissionGrant,
+  pub plugin: bool,
+  pub hrtime: bool,
+}
+
+impl PermissionsDescriptor {
+  /// Adds a grant, see `DenoPermissions::grant_scope()`.
+  pub fn grant(&mut self, name: &str, scope: Option<&str>) {
+    match name {
+      "read" => self.read.add(scope),
+      "write" => self.write.add(scope),
+      "net" => self.net.add(scope),
+      "env" => self.env.add(scope),
+      "run" => self.run.add(scope),
+      "plugin" => self.plugin = true,
+      "hrtime" => self.hrtime = true,
+      _ => {}
+    }
+  }
+
+  /// Relative paths in `read` and `write` are resolved against `base_dir`.
+  /// Permissions that aren't granted are denied rather than prompted for.
+  pub fn to_permissions(
+    &self,
+    base_dir: &Path,
+  ) -> Result<DenoPermissions, String> {
+    let resolve_paths = |grant: &PermissionGrant| -> Vec<PathBuf> {
+      grant
+        .list()
+        .iter()
+        .map(|path| base_dir.join(path))
+        .collect()
+    };
+    for rule in self.net.list() {
+      rule
+        .parse::<NetRule>()
+        .map_err(|err| format!("Invalid net rule \"{}\": {}", rule, err))?;
+    }
+    let mut permissions = DenoPermissions::from_flags(&Flags {
+      allow_read: self.read.is_all(),
+      read_whitelist: resolve_paths(&self.read),
+      allow_write: self.write.is_all(),
+      write_whitelist: resolve_paths(&self.write),
+      allow_net: self.net.is_all(),
+      net_whitelist: self.net.list().to_vec(),
+      allow_env: self.env.is_all(),
+      env_whitelist: self.env.list().to_vec(),
+      allow_run: self.run.is_all(),
+      run_whitelist: self.run.list().to_vec(),
+      allow_plugin: self.plugin,
+      allow_hrtime: self.hrtime,
+      ..Flags::default()
+    });
+    for state in &mut [
+      &mut permissions.allow_read,
+      &mut permissions.allow_write,
+      &mut permissions.allow_net,
+      &mut permissions.allow_env,
+      &mut permissions.allow_run,
+      &mut permissions.allow_plugin,
+      &mut permissions.allow_hrtime,
+    ] {
+      if !state.is_allow() {
+        **state = PermissionState::Deny;
+      }
+    }
+    Ok(permissions)
+  }
+}
+
+/// Audit log enabled with `--permission-audit=<file>`. Every permission check
+/// and request is appended to the file as a line of JSON. The file is shared
+/// by the permissions of the main worker and of all web workers.
+#[derive(Clone, Debug)]
+pub struct PermissionAudit {
+  file: Arc<Mutex<File>>,
+}
+
+impl PermissionAudit {
+  pub fn open(path: &Path) -> Result<Self, std::io::Error> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    Ok(Self {
+      file: Arc::new(Mutex::new(file)),
+    })
+  }
+
+  /// Logs a check or request made by `permissions`, attributed to the op it
+  /// is currently dispatching and the innermost module that called the op.
+  fn log(
+    &self,
+    permissions: &DenoPermissions,
+    action: &str,
+    permission: &str,
+    resource: Option<impl fmt::Display>,
+    decision: PermissionState,
+  ) {
+    let timestamp = SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .unwrap()
+      .as_millis() as u64;
+    let entry = json!({
+      "timestamp": timestamp,
+      "action": action,
+      "permission": permission,
+      "resource": resource.map(|r| r.to_string()),
+      "decision": decision.to_string(),
+      "op": permissions.op_name,
+      "module": permissions.calling_modules.first(),
+    });
+    let mut file = self.file.lock().unwrap();
+    if let Err(e) = writeln!(file, "{}", entry) {
+      eprintln!("Failed to write to the permission audit log: {}", e);
+    }
+  }
+}
+
+/// An answer at the permission prompt, see `DenoPermissions::prompt()`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PromptAnswer {
+  Grant,
+  GrantScope,
+  AlwaysGrant,
+  Deny,
+}
+
+/// Shows the permission prompt and returns the answer according to the user input.
+/// This loops until the user gives the proper input. The "s" answer is only
+/// offered if there is a `scope_label`.
+#[cfg(not(test))]
+fn permission_prompt(message: &str, scope_label: Option<&str>) -> PromptAnswer {
+  if !atty::is(atty::Stream::Stdin) || !atty::is(atty::Stream::Stderr) {
+    return PromptAnswer::Deny;
+  };
+  let options = match scope_label {
+    Some(label) => format!(
+      "[g/s/a/d (g = grant, s = grant {label}, a = always grant {label}, d = deny)]",
+      label = label
+    ),
+    None => "[g/a/d (g = grant, a = always grant, d = deny)]".to_string(),
+  };
+  let msg = format!("️{}  {}. Grant? {} ", PERMISSION_EMOJI, message, options);
+  // print to stderr so that if deno is > to a file this is still displayed.
+  eprint!("{}", colors::bold(msg));
+  loop {
+    let mut input = String::new();
+    let stdin = io::stdin();
+    let result = stdin.read_line(&mut input);
+    if result.is_err() {
+      return PromptAnswer::Deny;
+    };
+    let ch = input.chars().next().unwrap();
+    match ch.to_ascii_lowercase() {
+      'g' => return PromptAnswer::Grant,
+      's' if scope_label.is_some() => return PromptAnswer::GrantScope,
+      'a' => return PromptAnswer::AlwaysGrant,
+      'd' => return PromptAnswer::Deny,
+      _ => {
+        // If we don't get a recognized option try again.
+        let msg_again = format!("Unrecognized option '{}' {} ", ch, options);
+        eprint!("{}", colors::bold(msg_again));
+      }
+    };
+  }
+}
+
+#[cfg(test)]
+lazy_static! {
+  /// Lock this when you use `set_prompt_result` in a test case.
+  static ref PERMISSION_PROMPT_GUARD: Mutex<()> = Mutex::new(());
+  static ref STUB_PROMPT_VALUE: Mutex<PromptAnswer> =
+    Mutex::new(PromptAnswer::Grant);
+}
+
+#[cfg(test)]
+fn set_prompt_result(value: bool) {
+  set_prompt_answer(if value {
+    PromptAnswer::Grant
+  } else {
+    PromptAnswer::Deny
+  });
+}
+
+#[cfg(test)]
+fn set_prompt_answer(answer: PromptAnswer) {
+  *STUB_PROMPT_VALUE.lock().unwrap() = answer;
+}
+
+// When testing, permission prompt returns the value of STUB_PROMPT_VALUE
+// which we set from the test functions.
+#[cfg(test)]
+fn permission_prompt(
+  _message: &str,
+  _scope_label: Option<&str>,
+) -> PromptAnswer {
+  *STUB_PROMPT_VALUE.lock().unwrap()
+}
+
+/// The scope offered at the prompt for a path: the directory tree containing
+/// it, or rooted at it if it is a directory.
+fn directory_tree_scope(path: &Path) -> (String, String) {
+  let path = resolve_real_path(path);
+  let dir = if path.is_dir() {
+    path.as_path()
+  } else {
+    path.parent().unwrap_or(&path)
+  };
+  let dir = dir.display().to_string();
+  let label = format!("\"{}\" and everything in it", dir);
+  (dir, label)
+}
+
+/// The scope offered at the prompt for a URL: its host on any port.
+fn host_scope(url: &str) -> Result<(String, String), OpError> {
+  let url = Url::parse(url).map_err(OpError::from)?;
+  let host = format!("{}", url.host().unwrap());
+  let label = format!("\"{}\" on any port", host);
+  Ok((host, label))
+}
+
+fn log_perm_access(message: &str) {
+  debug!(
+    "{}",
+    colors::bold(format!("{}️  Granted {}", PERMISSION_EMOJI, message))
+  );
+}
+
+/// Builds the error for an access matched by a `--deny-*` rule. The message
+/// names the rule instead of suggesting an allow flag, since deny rules take
+/// precedence over every allow rule.
+fn deny_rule_error(
+  msg: &str,
+  flag_name: &str,
+  rule: impl fmt::Display,
+) -> OpError {
+  OpError::permission_denied(format!(
+    "{}, denied by the {}={} rule",
+    msg, flag_name, rule
+  ))
+}
+
+/// Returns the entry of `list` that is `path` or one of its ancestors.
+/// Whitelisted paths are resolved to real paths once, at startup, and only
+/// match the real path of the accessed file. A symlink inside a whitelisted
+/// directory therefore doesn't grant access to its target.
+fn resolve_path_whitelist(paths: &[PathBuf]) -> HashSet<PathBuf> {
+  paths.iter().map(|path| resolve_real_path(path)).collect()
+}
+
+/// Denied paths are kept as given in addition to their real path, and match
+/// both the path as given and the real path of the accessed file, so they
+/// can't be bypassed through a symlink in either direction.
+fn resolve_path_blacklist(paths: &[PathBuf]) -> HashSet<PathBuf> {
+  paths
+    .iter()
+    .flat_map(|path| vec![path.clone(), resolve_real_path(path)])
+    .collect()
+}
+
+fn find_ancestor_in_list<'a>(
+  path: &Path,
+  list: &'a HashSet<PathBuf>,
+) -> Option<&'a PathBuf> {
+  path.ancestors().find_map(|p| list.get(p))
+}
+
+/// Matches `path` as given and as resolved by `resolve`, which is
+/// `resolve_real_parent()` for accesses to a symlink itself and
+/// `resolve_real_path()` otherwise.
+fn find_path_in_list<'a>(
+  path: &Path,
+  list: &'a HashSet<PathBuf>,
+  resolve: fn(&Path) -> PathBuf,
+) -> Option<&'a PathBuf> {
+  if list.is_empty() {
+    return None;
+  }
+  find_ancestor_in_list(path, list)
+    .or_else(|| find_ancestor_in_list(&resolve(path), list))
+}
+
+fn is_path_in_list(
+  path: &Path,
+  list: &HashSet<PathBuf>,
+  resolve: fn(&Path) -> PathBuf,
+) -> bool {
+  find_path_in_list(path, list, resolve).is_some()
+}
+
+fn check_path_white_list(
+  path: &Path,
+  white_list: &HashSet<PathBuf>,
+  resolve: fn(&Path) -> PathBuf,
+) -> bool {
+  !white_list.is_empty()
+    && find_ancestor_in_list(&resolve(path), white_list).is_some()
+}
+
+/// Resolves the executables named in `--allow-run=<programs>` once, against
+/// the `PATH` at startup. Changing `PATH` at runtime therefore can't redirect
+/// a whitelisted name to a different binary. Entries that can't be resolved
+/// are kept as given and only match an identical resolved path.
+fn resolve_run_whitelist(programs: &[String]) -> HashSet<PathBuf> {
+  let cwd = std::env::current_dir().unwrap();
+  let path_env = std::env::var_os("PATH");
+  programs
+    .iter()
+    .map(|program| {
+      resolve_program(program, &cwd, path_env.as_deref())
+        .unwrap_or_else(|| PathBuf::from(program))
+    })
+    .collect()
+}
+
+/// Programs in `--deny-run=<programs>` are resolved like the whitelist, and
+/// additionally canonicalized so a denied binary can't be reached through a
+/// symlink or an alternative `PATH` entry (e.g. `/usr/bin/sh` vs `/bin/sh`).
+fn resolve_run_blacklist(programs: &[String]) -> HashSet<PathBuf> {
+  resolve_run_whitelist(programs)
+    .into_iter()
+    .map(|program| program.canonicalize().unwrap_or(program))
+    .collect()
+}
+
+fn find_program_in_blacklist<'a>(
+  program: &Path,
+  black_list: &'a HashSet<PathBuf>,
+) -> Option<&'a PathBuf> {
+  if let Some(entry) = black_list.get(program) {
+    return Some(entry);
+  }
+  let canonical = program.canonicalize().ok()?;
+  black_list.get(&canonical)
+}
+
+/// Matches an environment variable name against the whitelist. An entry
+/// ending in `*` matches every variable starting with the preceding prefix,
+/// e.g. `APP_*` matches `APP_PORT` and `APP_HOST`. On Windows, environment
+/// variable names are case insensitive and are compared as such.
+fn find_env_in_list<'a>(
+  key: &str,
+  list: &'a HashSet<String>,
+) -> Option<&'a String> {
+  let key = normalize_env_key(key);
+  list.iter().find(|entry| {
+    let entry = normalize_env_key(entry);
+    if entry.ends_with('*') {
+      key.starts_with(&entry[..entry.len() - 1])
+    } else {
+      key == entry
+    }
+  })
+}
+
+fn check_env_white_list(key: &str, white_list: &HashSet<String>) -> bool {
+  find_env_in_list(key, white_list).is_some()
+}
+
+/// Whether every variable matched by the whitelist entry `entry` is also
+/// matched by `white_list`.
+fn is_env_entry_covered(entry: &str, white_list: &HashSet<String>) -> bool {
+  let entry = normalize_env_key(entry);
+  if !entry.ends_with('*') {
+    return check_env_white_list(&entry, white_list);
+  }
+  white_list.iter().any(|other| {
+    let other = normalize_env_key(other);
+    other.ends_with('*') && entry.starts_with(&other[..other.len() - 1])
+  })
+}
+
+#[cfg(windows)]
+fn normalize_env_key(key: &str) -> String {
+  key.to_uppercase()
+}
+
+#[cfg(not(windows))]
+fn normalize_env_key(key: &str) -> String {
+  key.to_string()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum NetHost {
+  /// A hostname, e.g. `deno.land`.
+  Name(String),
+  /// Every subdomain of a domain, e.g. `*.deno.land`. Holds the suffix
+  /// including the leading dot, `.deno.land`.
+  Subdomains(String),
+  /// An IP address, e.g. `127.0.0.1` or `[::1]`.
+  Ip(IpAddr),
+  /// An IP network in CIDR notation, e.g. `10.0.0.0/8` or `[fd00::]/8`.
+  Cidr(IpAddr, u8),
+}
+
+/// An entry of `--allow-net` or `--deny-net`, parsed once at startup.
+///
+/// The host is a hostname, a `*.` wildcard matching every subdomain, an IP
+/// address or a CIDR network. IPv6 addresses must be enclosed in brackets.
+/// It is followed by an optional port or inclusive port range, e.g.
+/// `localhost:8000-8099`; an entry without a port matches every port.
+/// IP addresses and networks only match IP literals, hostnames are never
+/// resolved.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NetRule {
+  host: NetHost,
+  ports: Option<(u16, u16)>,
+  raw: String,
+}
+
+impl NetRule {
+  pub fn matches(&self, host: &str, port: Option<u16>) -> bool {
+    let port_matches = match (self.ports, port) {
+      (None, _) => true,
+      (Some((start, end)), Some(port)) => start <= port && port <= end,
+      (Some(_), None) => false,
+    };
+    port_matches && self.matches_host(host)
+  }
+
+  /// Whether the rule matches `host` on at least one port.
+  pub fn matches_host(&self, host: &str) -> bool {
+    let host = normalize_host(host);
+    match &self.host {
+      NetHost::Name(name) => host == *name,
+      NetHost::Subdomains(suffix) => {
+        host.len() > suffix.len() && host.ends_with(suffix.as_str())
+      }
+      NetHost::Ip(ip) => parse_host_ip(&host) == Some(*ip),
+      NetHost::Cidr(network, prefix_len) => parse_host_ip(&host)
+        .map_or(false, |ip| cidr_contains(*network, *prefix_len, ip)),
+    }
+  }
+
+  /// Whether every host and port matched by this rule is also matched by
+  /// `other`.
+  pub fn is_covered_by(&self, other: &NetRule) -> bool {
+    let ports_covered = match (self.ports, other.ports) {
+      (_, None) => true,
+      (None, Some(_)) => false,
+      (Some((start, end)), Some((other_start, other_end))) => {
+        other_start <= start && end <= other_end
+      }
+    };
+    ports_covered
+      && match (&self.host, &other.host) {
+        (NetHost::Name(name), NetHost::Name(other)) => name == other,
+        (NetHost::Name(name), NetHost::Subdomains(suffix)) => {
+          name.len() > suffix.len() && name.ends_with(suffix.as_str())
+        }
+        (NetHost::Subdomains(suffix), NetHost::Subdomains(other)) => {
+          suffix.ends_with(other.as_str())
+        }
+        (NetHost::Ip(ip), NetHost::Ip(other)) => ip == other,
+        (NetHost::Ip(ip), NetHost::Cidr(network, prefix_len)) => {
+          cidr_contains(*network, *prefix_len, *ip)
+        }
+        (
+          NetHost::Cidr(network, prefix_len),
+          NetHost::Cidr(other, other_prefix_len),
+        ) => {
+          other_prefix_len <= prefix_len
+            && cidr_contains(*other, *other_prefix_len, *network)
+        }
+        _ => false,
+      }
+  }
+}
+
+impl FromStr for NetRule {
+  type Err = String;
+
+  fn from_str(s: &str) -> Result<Self, String> {
+    // The port is separated by the first colon after the host. IPv6
+    // addresses contain colons and must therefore be enclosed in brackets.
+    let host_end = if s.starts_with('[') {
+      s.find(']').ok_or("missing closing bracket")? + 1
+    } else {
+      0
+    };
+    let (host, port) = match s[host_end..].find(':') {
+      Some(i) => (&s[..host_end + i], Some(&s[host_end + i + 1..])),
+      None => (s, None),
+    };
+    let ports = match port {
+      Some(port) => Some(parse_port_range(port)?),
+      None => None,
+    };
+    Ok(Self {
+      host: parse_net_host(host)?,
+      ports,
+      raw: s.to_string(),
+    })
+  }
+}
+
+impl fmt::Display for NetRule {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(&self.raw)
   }
+}
 
-  pub fn request_hrtime(&mut self) -> PermissionState {
-    self
-      .allow_hrtime
-      .request("Deno requests to access to high precision time")
+fn parse_net_host(host: &str) -> Result<NetHost, String> {
+  if let Some(slash) = host.find('/') {
+    let ip = parse_ip(&host[..slash])?;
+    let max_prefix_len = if ip.is_ipv4() { 32 } else { 128 };
+    let prefix_len = host[slash + 1..]
+      .parse::<u8>()
+      .ok()
+      .filter(|len| *len <= max_prefix_len)
+      .ok_or_else(|| format!("invalid prefix length in {}", host))?;
+    return Ok(NetHost::Cidr(ip, prefix_len));
   }
-
-  pub fn request_plugin(&mut self) -> PermissionState {
-    self.allow_plugin.request("Deno requests to open plugins")
+  if host.starts_with('[') || host.parse::<IpAddr>().is_ok() {
+    return Ok(NetHost::Ip(canonical_ip(parse_ip(host)?)));
   }
-
-  pub fn get_permission_state(
-    &self,
-    name: &str,
-    url: &Option<&str>,
-    path: &Option<&Path>,
-  ) -> Result<PermissionState, OpError> {
-    match name {
-      "run" => Ok(self.allow_run),
-      "read" => Ok(self.get_state_read(path)),
-      "write" => Ok(self.get_state_write(path)),
-      "net" => self.get_state_net_url(url),
-      "env" => Ok(self.allow_env),
-      "plugin" => Ok(self.allow_plugin),
-      "hrtime" => Ok(self.allow_hrtime),
-      n => Err(OpError::other(format!("No such permission name: {}", n))),
-    }
+  let (name, is_wildcard) = if host.starts_with("*.") {
+    (&host[1..], true)
+  } else {
+    (host, false)
+  };
+  if name.is_empty() || name == "." || name.contains('*') || name.contains(':')
+  {
+    return Err(format!("invalid host {}", host));
   }
+  let name = normalize_host(name);
+  Ok(if is_wildcard {
+    NetHost::Subdomains(name)
+  } else {
+    NetHost::Name(name)
+  })
 }
 
-/// Shows the permission prompt and returns the answer according to the user input.
-/// This loops until the user gives the proper input.
-#[cfg(not(test))]
-fn permission_prompt(message: &str) -> bool {
-  if !atty::is(atty::Stream::Stdin) || !atty::is(atty::Stream::Stderr) {
-    return false;
+/// Lowercases `host` and strips the brackets around an IPv6 address and the
+/// trailing dot of a fully qualified name, so that `Evil.com.` and `evil.com`
+/// match the same rules.
+fn normalize_host(host: &str) -> String {
+  let host = host.trim_start_matches('[').trim_end_matches(']');
+  let host = if host.len() > 1 && host.ends_with('.') {
+    &host[..host.len() - 1]
+  } else {
+    host
   };
-  let msg = format!(
-    "️{}  {}. Grant? [g/d (g = grant, d = deny)] ",
-    PERMISSION_EMOJI, message
-  );
-  // print to stderr so that if deno is > to a file this is still displayed.
-  eprint!("{}", colors::bold(msg));
-  loop {
-    let mut input = String::new();
-    let stdin = io::stdin();
-    let result = stdin.read_line(&mut input);
-    if result.is_err() {
-      return false;
-    };
-    let ch = input.chars().next().unwrap();
-    match ch.to_ascii_lowercase() {
-      'g' => return true,
-      'd' => return false,
-      _ => {
-        // If we don't get a recognized option try again.
-        let msg_again =
-          format!("Unrecognized option '{}' [g/d (g = grant, d = deny)] ", ch);
-        eprint!("{}", colors::bold(msg_again));
-      }
-    };
-  }
+  host.to_ascii_lowercase()
 }
 
-#[cfg(test)]
-lazy_static! {
-  /// Lock this when you use `set_prompt_result` in a test case.
-  static ref PERMISSION_PROMPT_GUARD: Mutex<()> = Mutex::new(());
+/// Parses an IPv4 address, or an IPv6 address enclosed in brackets.
+fn parse_ip(s: &str) -> Result<IpAddr, String> {
+  let ip = if s.starts_with('[') && s.ends_with(']') {
+    s[1..s.len() - 1].parse().map(IpAddr::V6)
+  } else {
+    s.parse().map(IpAddr::V4)
+  };
+  ip.map_err(|_| format!("invalid IP address {}", s))
 }
 
-#[cfg(test)]
-static STUB_PROMPT_VALUE: AtomicBool = AtomicBool::new(true);
+/// Parses a normalized host as an IP address, accepting every spelling the
+/// system resolver accepts for an IPv4 address: `2130706433`, `0x7f.1` and
+/// `0177.0.0.1` are all `127.0.0.1`.
+fn parse_host_ip(host: &str) -> Option<IpAddr> {
+  if let Ok(ip) = host.parse::<IpAddr>() {
+    return Some(canonical_ip(ip));
+  }
+  parse_inet_aton(host).map(IpAddr::V4)
+}
 
-#[cfg(test)]
-fn set_prompt_result(value: bool) {
-  STUB_PROMPT_VALUE.store(value, Ordering::SeqCst);
+/// Parses the numbers-and-dots notation of inet_aton(3): up to four parts
+/// in decimal, octal (leading `0`) or hex (leading `0x`), where the last part
+/// fills the remaining bytes of the address.
+fn parse_inet_aton(host: &str) -> Option<Ipv4Addr> {
+  let parts = host.split('.').collect::<Vec<_>>();
+  if parts.len() > 4 {
+    return None;
+  }
+  let mut numbers = Vec::with_capacity(parts.len());
+  for part in parts {
+    let number = if part.starts_with("0x") || part.starts_with("0X") {
+      u32::from_str_radix(&part[2..], 16).ok()
+    } else if part.len() > 1 && part.starts_with('0') {
+      u32::from_str_radix(&part[1..], 8).ok()
+    } else if !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()) {
+      part.parse::<u32>().ok()
+    } else {
+      None
+    }?;
+    numbers.push(number);
+  }
+  let (last, init) = numbers.split_last()?;
+  let last_bits = 8 * (4 - init.len() as u32);
+  if init.iter().any(|n| *n > 0xff)
+    || (last_bits < 32 && *last >= 1 << last_bits)
+  {
+    return None;
+  }
+  let high = init
+    .iter()
+    .enumerate()
+    .fold(0u32, |acc, (i, n)| acc | n << (24 - 8 * i as u32));
+  Some(Ipv4Addr::from(high | last))
 }
 
-// When testing, permission prompt returns the value of STUB_PROMPT_VALUE
-// which we set from the test functions.
-#[cfg(test)]
-fn permission_prompt(_message: &str) -> bool {
-  STUB_PROMPT_VALUE.load(Ordering::SeqCst)
+/// Maps an IPv4-mapped IPv6 address such as `::ffff:10.0.0.1` to the IPv4
+/// address it stands for.
+fn canonical_ip(ip: IpAddr) -> IpAddr {
+  match ip {
+    IpAddr::V6(v6) => match v6.segments() {
+      [0, 0, 0, 0, 0, 0xffff, ..] => IpAddr::V4(v6.to_ipv4().unwrap()),
+      _ => ip,
+    },
+    IpAddr::V4(_) => ip,
+  }
 }
 
-fn log_perm_access(message: &str) {
-  debug!(
-    "{}",
-    colors::bold(format!("{}️  Granted {}", PERMISSION_EMOJI, message))
-  );
+fn parse_port_range(s: &str) -> Result<(u16, u16), String> {
+  let parse_port = |port: &str| {
+    port
+      .parse::<u16>()
+      .map_err(|_| format!("invalid port {}", port))
+  };
+  let (start, end) = match s.find('-') {
+    Some(i) => (parse_port(&s[..i])?, parse_port(&s[i + 1..])?),
+    None => (parse_port(s)?, parse_port(s)?),
+  };
+  if start > end {
+    return Err(format!("invalid port range {}", s));
+  }
+  Ok((start, end))
 }
 
-fn check_path_white_list(path: &Path, white_list: &HashSet<PathBuf>) -> bool {
-  let mut path_buf = PathBuf::from(path);
-  loop {
-    if white_list.contains(&path_buf) {
-      return true;
+fn cidr_contains(network: IpAddr, prefix_len: u8, ip: IpAddr) -> bool {
+  match (network, ip) {
+    (IpAddr::V4(network), IpAddr::V4(ip)) => {
+      let mask = (!0u32).checked_shl(32 - prefix_len as u32).unwrap_or(0);
+      u32::from(network) & mask == u32::from(ip) & mask
+    }
+    (IpAddr::V6(network), IpAddr::V6(ip)) => {
+      let mask = (!0u128).checked_shl(128 - prefix_len as u32).unwrap_or(0);
+      u128::from(network) & mask == u128::from(ip) & mask
     }
-    if !path_buf.pop() {
-      break;
+    // Hosts are canonical, so an IPv4 host may still be inside an IPv6
+    // network covering the IPv4-mapped range, e.g. `[::ffff:0:0]/96`.
+    (IpAddr::V6(_), IpAddr::V4(ip)) => {
+      cidr_contains(network, prefix_len, IpAddr::V6(ip.to_ipv6_mapped()))
     }
+    (IpAddr::V4(_), IpAddr::V6(_)) => false,
   }
-  false
+}
+
+/// Entries are validated when the flags are parsed, so an invalid entry here
+/// means `Flags` was constructed by hand.
+fn parse_net_rules(entries: &[String]) -> Vec<NetRule> {
+  entries
+    .iter()
+    .map(|entry| {
+      entry
+        .parse()
+        .unwrap_or_else(|e| panic!("Bad net rule {}: {}", entry, e))
+    })
+    .collect()
+}
+
+fn find_host_and_port_in_list<'a>(
+  host: &str,
+  port: Option<u16>,
+  list: &'a [NetRule],
+) -> Option<&'a NetRule> {
+  list.iter().find(|rule| rule.matches(host, port))
 }
 
 fn check_host_and_port_whitelist(
   host: &str,
   port: Option<u16>,
-  whitelist: &HashSet<String>,
+  whitelist: &[NetRule],
 ) -> bool {
-  whitelist.contains(host)
-    || (port.is_some()
-      && whitelist.contains(&format!("{}:{}", host, port.unwrap())))
+  find_host_and_port_in_list(host, port, whitelist).is_some()
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
+  use deno_core::ModuleSpecifier;
 
   // Creates vector of strings, Vec<String>
   macro_rules! svec {
@@ -520,6 +1940,362 @@ mod tests {
     }
   }
 
+  #[test]
+  fn test_check_net_patterns() {
+    let perms = DenoPermissions::from_flags(&Flags {
+      net_whitelist: svec![
+        "*.svc.internal",
+        "10.0.0.0/8",
+        "[fd00::]/8",
+        "[::1]",
+        "localhost:8000-8099",
+        "192.168.1.0/24:443"
+      ],
+      ..Default::default()
+    });
+
+    let domain_tests = vec![
+      ("api.svc.internal", 80, true),
+      ("a.b.svc.internal", 443, true),
+      ("API.SVC.INTERNAL", 443, true),
+      ("svc.internal", 80, false),
+      ("evilsvc.internal", 80, false),
+      ("10.0.0.1", 80, true),
+      ("10.255.255.255", 8080, true),
+      ("11.0.0.1", 80, false),
+      ("fd12:3456::1", 80, true),
+      ("[fd12:3456::1]", 80, true),
+      ("fe80::1", 80, false),
+      ("::1", 4000, true),
+      ("localhost", 8000, true),
+      ("localhost", 8050, true),
+      ("localhost", 8099, true),
+      ("localhost", 7999, false),
+      ("localhost", 8100, false),
+      ("192.168.1.20", 443, true),
+      ("192.168.1.20", 80, false),
+      ("192.168.2.20", 443, false),
+    ];
+
+    let url_tests = vec![
+      ("https://api.svc.internal/v1", true),
+      ("https://svc.internal/v1", false),
+      ("http://10.1.2.3:9000/", true),
+      ("http://[fd00::1]:8080/", true),
+      ("http://[::1]/", true),
+      ("http://localhost:8080/", true),
+      ("http://localhost/", false),
+      ("https://192.168.1.1:443/", false),
+    ];
+
+    for (host, port, is_ok) in domain_tests.iter() {
+      assert_eq!(*is_ok, perms.check_net(host, *port).is_ok(), "{}", host);
+    }
+
+    for (url_str, is_ok) in url_tests.iter() {
+      let u = url::Url::parse(url_str).unwrap();
+      assert_eq!(*is_ok, perms.check_net_url(&u).is_ok(), "{}", url_str);
+    }
+  }
+
+  #[test]
+  fn test_parse_net_rule() {
+    for rule in &[
+      "deno.land",
+      "deno.land:443",
+      "*.deno.land",
+      "127.0.0.1:8000-8099",
+      "0.0.0.0/0",
+      "[::]/0",
+      "[2001:db8::1]:443",
+    ] {
+      assert!(rule.parse::<NetRule>().is_ok(), "{}", rule);
+    }
+
+    for rule in &[
+      "",
+      "*",
+      "deno.*",
+      "::1",
+      "[::1",
+      "10.0.0.0/33",
+      "[fd00::]/129",
+      "localhost:",
+      "localhost:99999",
+      "localhost:8099-8000",
+    ] {
+      assert!(rule.parse::<NetRule>().is_err(), "{}", rule);
+    }
+  }
+
+  #[test]
+  fn test_check_env() {
+    let perms = DenoPermissions::from_flags(&Flags {
+      env_whitelist: svec!["HOME", "PORT", "APP_*"],
+      ..Default::default()
+    });
+
+    assert!(perms.check_env_var("HOME").is_ok());
+    assert!(perms.check_env_var("PORT").is_ok());
+    assert!(perms.check_env_var("APP_").is_ok());
+    assert!(perms.check_env_var("APP_HOST").is_ok());
+    assert!(perms.check_env_var("HOMEPATH").is_err());
+    assert!(perms.check_env_var("AWS_SECRET_ACCESS_KEY").is_err());
+    assert!(perms.check_env_var("MY_APP_HOST").is_err());
+    // Access to the environment as a whole still requires --allow-env.
+    assert!(perms.check_env().is_err());
+
+    let perms = DenoPermissions::from_flags(&Flags {
+      allow_env: true,
+      ..Default::default()
+    });
+    assert!(perms.check_env_var("AWS_SECRET_ACCESS_KEY").is_ok());
+    assert!(perms.check_env().is_ok());
+  }
+
+  #[test]
+  fn test_revoke_env() {
+    let mut perms = DenoPermissions::from_flags(&Flags {
+      env_whitelist: svec!["HOME", "PORT", "APP_*"],
+      ..Default::default()
+    });
+    assert!(perms.revoke_env(&Some("HOME")).is_ok());
+    assert!(perms.check_env_var("HOME").is_err());
+    assert!(perms.check_env_var("PORT").is_ok());
+    // Covered by "APP_*", revoking it alone would be a no-op.
+    assert!(perms.revoke_env(&Some("APP_HOST")).is_err());
+    assert!(perms.check_env_var("APP_HOST").is_ok());
+
+    let mut perms = DenoPermissions::from_flags(&Flags {
+      allow_env: true,
+      ..Default::default()
+    });
+    assert!(perms.revoke_env(&Some("HOME")).is_err());
+    assert!(perms.check_env().is_ok());
+    assert!(perms.revoke_env(&None).is_ok());
+    assert!(perms.check_env().is_err());
+    assert!(perms.check_env_var("HOME").is_err());
+  }
+
+  #[test]
+  fn test_check_run() {
+    let perms = DenoPermissions::from_flags(&Flags {
+      run_whitelist: svec!["/usr/bin/convert", "surely-not-on-path"],
+      ..Default::default()
+    });
+
+    assert!(perms
+      .check_run_program(Path::new("/usr/bin/convert"))
+      .is_ok());
+    assert!(perms.check_run_program(Path::new("/bin/sh")).is_err());
+    // Unresolvable names never match a resolved program.
+    assert!(perms
+      .check_run_program(Path::new("/usr/bin/surely-not-on-path"))
+      .is_err());
+    assert!(perms.check_run().is_err());
+
+    let perms = DenoPermissions::from_flags(&Flags {
+      allow_run: true,
+      ..Default::default()
+    });
+    assert!(perms.check_run_program(Path::new("/bin/sh")).is_ok());
+    assert!(perms.check_run().is_ok());
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_check_paths_through_symlinks() {
+    use std::os::unix::fs::symlink;
+    let temp_dir = tempfile::TempDir::new().expect("tempdir fail");
+    let root = temp_dir.path().canonicalize().unwrap();
+    let allowed = root.join("allowed");
+    let outside = root.join("outside");
+    std::fs::create_dir(&allowed).unwrap();
+    std::fs::create_dir(&outside).unwrap();
+    std::fs::create_dir(allowed.join("secrets")).unwrap();
+    symlink(&outside, allowed.join("escape")).unwrap();
+    symlink(outside.join("new_file"), allowed.join("dangling")).unwrap();
+    symlink(allowed.join("secrets"), outside.join("secrets")).unwrap();
+
+    let perms = DenoPermissions::from_flags(&Flags {
+      read_whitelist: vec![allowed.clone()],
+      read_blacklist: vec![allowed.join("secrets")],
+      write_whitelist: vec![allowed.clone()],
+      ..Default::default()
+    });
+
+    assert!(perms.check_read(&allowed.join("file")).is_ok());
+    assert!(perms.check_write(&allowed.join("new/file")).is_ok());
+    // A symlink inside an allowed directory doesn't grant access to its
+    // target, whether the target exists or not.
+    assert!(perms.check_read(&allowed.join("escape")).is_err());
+    assert!(perms.check_read(&allowed.join("escape/passwd")).is_err());
+    assert!(perms.check_write(&allowed.join("escape/new_file")).is_err());
+    assert!(perms.check_write(&allowed.join("dangling")).is_err());
+    // A denied directory can't be reached through a symlink either.
+    assert!(perms.check_read(&allowed.join("secrets/key")).is_err());
+    assert!(perms.check_read(&outside.join("secrets/key")).is_err());
+    // The symlink itself can still be accessed, e.g. to lstat or remove it,
+    // but not the path of its target.
+    assert!(perms.check_read_entry(&allowed.join("escape")).is_ok());
+    assert!(perms.check_write_entry(&allowed.join("dangling")).is_ok());
+    assert!(perms
+      .check_read_entry(&allowed.join("escape/passwd"))
+      .is_err());
+    assert!(perms.check_read_entry(&allowed.join("secrets")).is_err());
+
+    // Whitelisting a symlink allows access to its target.
+    let perms = DenoPermissions::from_flags(&Flags {
+      read_whitelist: vec![allowed.join("escape")],
+      ..Default::default()
+    });
+    assert!(perms.check_read(&outside.join("file")).is_ok());
+    assert!(perms.check_read(&allowed.join("escape/file")).is_ok());
+  }
+
+  #[test]
+  fn test_deny_lists() {
+    let perms = DenoPermissions::from_flags(&Flags {
+      allow_read: true,
+      read_blacklist: vec![PathBuf::from("/home/user/.ssh")],
+      write_whitelist: vec![PathBuf::from("/tmp")],
+      write_blacklist: vec![PathBuf::from("/tmp/secret")],
+      allow_net: true,
+      net_blacklist: svec!["evil.com", "*.evil.com", "deno.land:443"],
+      allow_env: true,
+      env_blacklist: svec!["AWS_*"],
+      allow_run: true,
+      run_blacklist: svec!["/surely/not/a/shell"],
+      ..Default::default()
+    });
+
+    assert!(perms.check_read(Path::new("/home/user/file")).is_ok());
+    assert!(perms.check_read(Path::new("/home/user/.ssh")).is_err());
+    assert!(perms
+      .check_read(Path::new("/home/user/.ssh/id_rsa"))
+      .is_err());
+
+    assert!(perms.check_write(Path::new("/tmp/file")).is_ok());
+    assert!(perms.check_write(Path::new("/tmp/secret/file")).is_err());
+
+    assert!(perms.check_net("deno.land", 80).is_ok());
+    assert!(perms.check_net("deno.land", 443).is_err());
+    assert!(perms.check_net("evil.com", 80).is_err());
+    // Neither case nor a trailing dot escape a deny rule.
+    assert!(perms.check_net("Evil.COM", 80).is_err());
+    assert!(perms.check_net("evil.com.", 80).is_err());
+    assert!(perms.check_net("www.evil.com.", 80).is_err());
+    assert!(perms.check_net("WWW.Evil.com", 80).is_err());
+    let u = url::Url::parse("http://evil.com./").unwrap();
+    assert!(perms.check_net_url(&u).is_err());
+    let u = url::Url::parse("https://deno.land/std/").unwrap();
+    assert!(perms.check_net_url(&u).is_err());
+    let u = url::Url::parse("http://deno.land/std/").unwrap();
+    assert!(perms.check_net_url(&u).is_ok());
+
+    assert!(perms.check_env_var("HOME").is_ok());
+    assert!(perms.check_env_var("AWS_SECRET_ACCESS_KEY").is_err());
+    assert!(!perms.is_env_var_allowed("AWS_SECRET_ACCESS_KEY"));
+
+    assert!(perms
+      .check_run_program(Path::new("/surely/not/a/shell"))
+      .is_err());
+    assert!(perms.check_run_program(Path::new("/usr/bin/git")).is_ok());
+
+    // The error names the rule that denied the access.
+    let err = perms
+      .check_read(Path::new("/home/user/.ssh/id_rsa"))
+      .unwrap_err();
+    assert!(err.msg.contains("--deny-read=/home/user/.ssh"));
+
+    assert_eq!(
+      perms
+        .get_permission_state(
+          "read",
+          &None,
+          &Some(Path::new("/home/user/.ssh")),
+          &None,
+          &None
+        )
+        .unwrap(),
+      PermissionState::Deny
+    );
+    assert_eq!(
+      perms
+        .get_permission_state(
+          "net",
+          &Some("https://evil.com"),
+          &None,
+          &None,
+          &None
+        )
+        .unwrap(),
+      PermissionState::Deny
+    );
+  }
+
+  #[test]
+  fn test_check_net_host() {
+    let perms = DenoPermissions::from_flags(&Flags {
+      net_whitelist: svec!["api.deno.land:443", "*.example.com", "10.0.0.0/8"],
+      net_blacklist: svec!["evil.example.com", "db.example.com:5432"],
+      ..Default::default()
+    });
+    assert!(perms.check_net_host("api.deno.land").is_ok());
+    assert!(perms.check_net_host("www.example.com").is_ok());
+    assert!(perms.check_net_host("db.example.com").is_ok());
+    assert!(perms.check_net_host("10.1.2.3").is_ok());
+    assert!(perms.check_net_host("evil.example.com").is_err());
+    assert!(perms.check_net_host("deno.land").is_err());
+  }
+
+  #[test]
+  fn test_deny_net_ip_spellings() {
+    let perms = DenoPermissions::from_flags(&Flags {
+      allow_net: true,
+      net_blacklist: svec!["10.0.0.0/8", "127.0.0.1", "[::ffff:0:0]/96:22"],
+      ..Default::default()
+    });
+
+    for host in &[
+      "10.0.0.1",
+      "[::ffff:10.0.0.1]",
+      "[::ffff:a00:1]",
+      "127.0.0.1",
+      "2130706433",
+      "0x7f000001",
+      "0177.0.0.1",
+      "127.1",
+      "0x7f.0.0.1",
+      "[::ffff:127.0.0.1]",
+    ] {
+      assert!(perms.check_net(host, 80).is_err(), "{}", host);
+    }
+    assert!(perms.check_net("192.168.0.1", 22).is_err());
+    assert!(perms.check_net("192.168.0.1", 80).is_ok());
+    assert!(perms.check_net("127.0.0.2", 80).is_ok());
+    assert!(perms.check_net("[::1]", 80).is_ok());
+    assert!(perms.check_net("1234abcd", 80).is_ok());
+    assert!(perms.check_net("0x1.0x2.0x3.0x4.0x5", 80).is_ok());
+    assert!(perms.check_net("256.0.0.1", 80).is_ok());
+
+    // A name resolving into a denied network is caught by the address check.
+    let addr: SocketAddr = "10.1.2.3:443".parse().unwrap();
+    assert!(perms.check_net_addr(&addr).is_err());
+    let addr: SocketAddr = "[::ffff:10.1.2.3]:443".parse().unwrap();
+    assert!(perms.check_net_addr(&addr).is_err());
+    let addr: SocketAddr = "93.184.216.34:443".parse().unwrap();
+    assert!(perms.check_net_addr(&addr).is_ok());
+
+    assert_eq!(parse_inet_aton("1.2.3"), Some(Ipv4Addr::new(1, 2, 0, 3)));
+    assert_eq!(
+      parse_inet_aton("1.0x10203"),
+      Some(Ipv4Addr::new(1, 1, 2, 3))
+    );
+    assert_eq!(parse_inet_aton("1.2.0x10000"), None);
+    assert_eq!(parse_inet_aton("08.0.0.1"), None);
+  }
+
   #[test]
   fn test_permissions_request_run() {
     let guard = PERMISSION_PROMPT_GUARD.lock().unwrap();
@@ -527,13 +2303,29 @@ mod tests {
       ..Default::default()
     });
     set_prompt_result(true);
-    assert_eq!(perms0.request_run(), PermissionState::Allow);
+    assert_eq!(perms0.request_run(&None), PermissionState::Allow);
 
     let mut perms1 = DenoPermissions::from_flags(&Flags {
       ..Default::default()
     });
     set_prompt_result(false);
-    assert_eq!(perms1.request_run(), PermissionState::Deny);
+    assert_eq!(perms1.request_run(&None), PermissionState::Deny);
+
+    let mut perms2 = DenoPermissions::from_flags(&Flags {
+      run_whitelist: svec!["/usr/bin/convert"],
+      ..Default::default()
+    });
+    set_prompt_result(false);
+    // If the whitelist contains the program, then the result is `allow`
+    // regardless of prompt result
+    assert_eq!(
+      perms2.request_run(&Some(Path::new("/usr/bin/convert"))),
+      PermissionState::Allow
+    );
+    assert_eq!(
+      perms2.request_run(&Some(Path::new("/bin/sh"))),
+      PermissionState::Deny
+    );
     drop(guard);
   }
 
@@ -572,6 +2364,19 @@ mod tests {
       perms2.request_read(&Some(Path::new("/foo/baz"))),
       PermissionState::Deny
     );
+
+    let mut perms3 = DenoPermissions::from_flags(&Flags {
+      read_blacklist: vec![PathBuf::from("/foo/bar/secret")],
+      ..Default::default()
+    });
+    set_prompt_result(true);
+    // If the blacklist contains the path, then the result is `deny`
+    // regardless of prompt result
+    assert_eq!(
+      perms3.request_read(&Some(Path::new("/foo/bar/secret"))),
+      PermissionState::Deny
+    );
+    assert_eq!(perms3.allow_read, PermissionState::Ask);
     drop(guard);
   }
 
@@ -672,13 +2477,23 @@ mod tests {
       ..Default::default()
     });
     set_prompt_result(true);
-    assert_eq!(perms0.request_env(), PermissionState::Allow);
+    assert_eq!(perms0.request_env(&None), PermissionState::Allow);
 
     let mut perms1 = DenoPermissions::from_flags(&Flags {
       ..Default::default()
     });
     set_prompt_result(false);
-    assert_eq!(perms1.request_env(), PermissionState::Deny);
+    assert_eq!(perms1.request_env(&None), PermissionState::Deny);
+
+    let mut perms2 = DenoPermissions::from_flags(&Flags {
+      env_whitelist: svec!["HOME"],
+      ..Default::default()
+    });
+    set_prompt_result(false);
+    // If the whitelist contains the variable, then the result is `allow`
+    // regardless of prompt result
+    assert_eq!(perms2.request_env(&Some("HOME")), PermissionState::Allow);
+    assert_eq!(perms2.request_env(&Some("PATH")), PermissionState::Deny);
     drop(guard);
   }
 
@@ -715,4 +2530,252 @@ mod tests {
     assert_eq!(perms1.request_hrtime(), PermissionState::Deny);
     drop(guard);
   }
+
+  #[test]
+  fn test_permissions_request_scoped() {
+    let guard = PERMISSION_PROMPT_GUARD.lock().unwrap();
+    let temp_dir = tempfile::TempDir::new().expect("tempdir fail");
+    let root = temp_dir.path().canonicalize().unwrap();
+    std::fs::create_dir(root.join("dir")).unwrap();
+    let main_module = ModuleSpecifier::resolve_url("file:///tool.ts").unwrap();
+    let mut perms = DenoPermissions::from_flags(&Flags::default());
+    perms.store = Some(PermissionStore::new(&root, &main_module));
+
+    // "s" grants the directory tree of the file.
+    set_prompt_answer(PromptAnswer::GrantScope);
+    let file = root.join("dir/file.txt");
+    assert_eq!(
+      perms.request_read(&Some(file.as_path())),
+      PermissionState::Allow
+    );
+    assert_eq!(perms.allow_read, PermissionState::Ask);
+    assert!(perms.check_read(&root.join("dir/other/file")).is_ok());
+    assert!(perms.check_read(&root.join("file")).is_err());
+
+    // And the host of a URL on any port.
+    assert_eq!(
+      perms.request_net(&Some("https://deno.land:8443/")).unwrap(),
+      PermissionState::Allow
+    );
+    assert!(perms.check_net("deno.land", 443).is_ok());
+    assert!(perms.check_net("example.com", 443).is_err());
+
+    // "a" also remembers the grant for the main module.
+    set_prompt_answer(PromptAnswer::AlwaysGrant);
+    assert_eq!(perms.request_env(&Some("HOME")), PermissionState::Allow);
+    assert_eq!(perms.request_hrtime(), PermissionState::Allow);
+    assert!(perms.check_env_var("HOME").is_ok());
+    assert!(perms.check_env_var("PATH").is_err());
+    let mut perms = DenoPermissions::from_flags(&Flags::default());
+    let grants = PermissionStore::new(&root, &main_module)
+      .load()
+      .unwrap()
+      .unwrap();
+    perms.add_grants(&grants);
+    assert!(perms.check_env_var("HOME").is_ok());
+    assert!(perms.check_env_var("PATH").is_err());
+    assert_eq!(perms.allow_hrtime, PermissionState::Allow);
+    assert!(perms.check_read(&file).is_err());
+    drop(guard);
+  }
+
+  #[test]
+  fn test_permission_audit() {
+    let guard = PERMISSION_PROMPT_GUARD.lock().unwrap();
+    let temp_dir = tempfile::TempDir::new().expect("tempdir fail");
+    let audit_path = temp_dir.path().join("audit.log");
+    let mut perms = DenoPermissions::from_flags(&Flags {
+      read_whitelist: vec![PathBuf::from("/a")],
+      ..Default::default()
+    });
+    perms.audit = Some(PermissionAudit::open(&audit_path).unwrap());
+
+    perms.enter_op(
+      Some("op_read_file"),
+      &svec![RUNTIME_SCRIPT_NAMES[2], "file:///main.ts"],
+      false,
+    );
+    assert!(perms.check_read(Path::new("/a/file")).is_ok());
+    perms.exit_op();
+    assert!(perms.check_net("deno.land", 443).is_err());
+    set_prompt_result(false);
+    assert_eq!(perms.request_env(&Some("HOME")), PermissionState::Deny);
+    drop(guard);
+
+    let log = std::fs::read_to_string(&audit_path).unwrap();
+    let entries: Vec<serde_json::Value> = log
+      .lines()
+      .map(|line| serde_json::from_str(line).unwrap())
+      .collect();
+    assert_eq!(entries.len(), 3);
+
+    assert_eq!(entries[0]["action"], "check");
+    assert_eq!(entries[0]["permission"], "read");
+    assert_eq!(entries[0]["resource"], "/a/file");
+    assert_eq!(entries[0]["decision"], "granted");
+    assert_eq!(entries[0]["op"], "op_read_file");
+    assert_eq!(entries[0]["module"], "file:///main.ts");
+    assert!(entries[0]["timestamp"].is_u64());
+
+    assert_eq!(entries[1]["permission"], "net");
+    assert_eq!(entries[1]["resource"], "deno.land:443");
+    assert_eq!(entries[1]["decision"], "denied");
+    assert!(entries[1]["op"].is_null());
+    assert!(entries[1]["module"].is_null());
+
+    assert_eq!(entries[2]["action"], "request");
+    assert_eq!(entries[2]["permission"], "env");
+    assert_eq!(entries[2]["resource"], "HOME");
+    assert_eq!(entries[2]["decision"], "denied");
+  }
+
+  #[test]
+  fn test_permission_policy() {
+    let policy = PermissionPolicy::from_json(
+      Path::new("/app"),
+      r#"{ "https://deno.land/x/": { "net": ["deno.land"] } }"#,
+    )
+    .unwrap();
+    let mut perms = DenoPermissions::from_flags(&Flags {
+      allow_read: true,
+      allow_net: true,
+      ..Default::default()
+    });
+    perms.policy = Some(Arc::new(policy));
+
+    // Modules that aren't in the policy only need the global permissions.
+    perms.enter_op(Some("op_open"), &svec!["file:///app/main.ts"], false);
+    assert!(perms.check_read(Path::new("/etc/passwd")).is_ok());
+    assert!(perms.check_net("example.com", 443).is_ok());
+
+    // A module in the policy gets the intersection of both.
+    perms.enter_op(
+      Some("op_open"),
+      &svec!["https://deno.land/x/mod.ts", "file:///app/main.ts"],
+      false,
+    );
+    let err = perms.check_read(Path::new("/etc/passwd")).unwrap_err();
+    assert!(err
+      .msg
+      .contains("permission policy for \"https://deno.land/x/\""));
+    assert!(perms.check_net("deno.land", 443).is_ok());
+    assert!(perms.check_net("example.com", 443).is_err());
+    assert_eq!(
+      perms
+        .get_permission_state("read", &None, &None, &None, &None)
+        .unwrap(),
+      PermissionState::Deny
+    );
+
+    // So does a more privileged module called by it.
+    perms.enter_op(
+      Some("op_open"),
+      &svec![
+        "file:///app/util.ts",
+        "https://deno.land/x/mod.ts",
+        "file:///app/main.ts"
+      ],
+      false,
+    );
+    assert!(perms.check_read(Path::new("/etc/passwd")).is_err());
+
+    // An op without a module on the stack, e.g. `Deno.readFileSync` passed
+    // to `then()`, could have been set up by any module.
+    perms.enter_op(Some("op_open"), &svec![RUNTIME_SCRIPT_NAMES[2]], false);
+    let err = perms.check_read(Path::new("/etc/passwd")).unwrap_err();
+    assert!(err.msg.contains("the calling module is unknown"));
+    assert!(perms.check_net("deno.land", 443).is_ok());
+
+    // So could code evaluated at runtime, e.g. a `new Function()` created by
+    // a restricted module and called by the main module.
+    perms.enter_op(Some("op_open"), &svec!["", "file:///app/main.ts"], false);
+    let err = perms.check_read(Path::new("/etc/passwd")).unwrap_err();
+    assert!(err.msg.contains("the calling module is unknown"));
+
+    // Scripts that merely look like the runtime's are modules of their own.
+    perms.enter_op(
+      Some("op_open"),
+      &svec!["$deno$/ops/fs/open.ts", "file:///app/main.ts"],
+      false,
+    );
+    assert!(perms.check_read(Path::new("/etc/passwd")).is_ok());
+
+    // So could an op whose outermost frames were cut off.
+    perms.enter_op(Some("op_open"), &svec!["file:///app/util.ts"], true);
+    assert!(perms.check_read(Path::new("/etc/passwd")).is_err());
+    assert!(perms.check_net("example.com", 443).is_err());
+
+    perms.exit_op();
+    assert!(perms.check_read(Path::new("/etc/passwd")).is_ok());
+  }
+
+  #[test]
+  fn test_narrow_permissions() {
+    let mut perms = DenoPermissions::from_flags(&Flags {
+      read_whitelist: vec![PathBuf::from("/app")],
+      read_blacklist: vec![PathBuf::from("/app/secrets")],
+      net_whitelist: svec!["*.deno.land", "10.0.0.0/8:8000-8999"],
+      env_whitelist: svec!["APP_*"],
+      allow_hrtime: true,
+      ..Default::default()
+    });
+    perms.policy = Some(Arc::new(
+      PermissionPolicy::from_json(
+        Path::new("/app"),
+        r#"{ "https://deno.land/x/": { "env": ["APP_PORT"] } }"#,
+      )
+      .unwrap(),
+    ));
+    let narrow = |perms: &DenoPermissions, json: &str| {
+      let descriptor: PermissionsDescriptor =
+        serde_json::from_str(json).unwrap();
+      perms.narrow(&descriptor, Path::new("/app"))
+    };
+
+    let worker = narrow(
+      &perms,
+      r#"{
+        "read": ["data"],
+        "net": ["cdn.deno.land", "10.1.0.0/16:8080"],
+        "env": ["APP_HOST"]
+      }"#,
+    )
+    .unwrap();
+    assert!(worker.check_read(Path::new("/app/data/db")).is_ok());
+    assert!(worker.check_read(Path::new("/app/main.ts")).is_err());
+    assert!(worker.check_net("cdn.deno.land", 443).is_ok());
+    assert!(worker.check_net("10.1.2.3", 8080).is_ok());
+    assert!(worker.check_net("10.1.2.3", 8081).is_err());
+    assert!(worker.check_env_var("APP_HOST").is_ok());
+    assert!(worker.check_env_var("APP_PORT").is_err());
+    // Permissions that weren't granted can't be requested either.
+    assert_eq!(worker.allow_hrtime, PermissionState::Deny);
+    assert_eq!(worker.allow_write, PermissionState::Deny);
+    // The deny rules and policy of the parent carry over.
+    assert!(narrow(&perms, r#"{ "read": ["secrets"] }"#).is_err());
+    let worker = narrow(&perms, r#"{ "read": ["."] }"#).unwrap();
+    assert!(worker.check_read(Path::new("/app/secrets/key")).is_err());
+    assert!(worker.policy.is_some());
+
+    assert!(narrow(&perms, r#"{ "read": true }"#).is_err());
+    assert!(narrow(&perms, r#"{ "read": ["/etc"] }"#).is_err());
+    assert!(narrow(&perms, r#"{ "net": ["deno.land"] }"#).is_err());
+    assert!(narrow(&perms, r#"{ "net": ["10.0.0.0/8"] }"#).is_err());
+    assert!(narrow(&perms, r#"{ "net": ["*.deno.land:443"] }"#).is_ok());
+    assert!(narrow(&perms, r#"{ "env": ["*"] }"#).is_err());
+    assert!(narrow(&perms, r#"{ "env": ["APP_DB_*"] }"#).is_ok());
+    assert!(narrow(&perms, r#"{ "hrtime": true }"#).is_ok());
+    assert!(narrow(&perms, r#"{ "plugin": true }"#).is_err());
+    assert!(narrow(&perms, r#"{ "net": ["10.0.0.0/33"] }"#).is_err());
+
+    // A module restricted by the policy can't hand out more than it has.
+    perms.enter_op(
+      Some("op_create_worker"),
+      &svec!["https://deno.land/x/mod.ts"],
+      false,
+    );
+    assert!(narrow(&perms, r#"{ "env": ["APP_PORT"] }"#).is_ok());
+    assert!(narrow(&perms, r#"{ "env": ["APP_HOST"] }"#).is_err());
+    assert!(narrow(&perms, r#"{ "hrtime": true }"#).is_err());
+  }
 }
@@ -1,4 +1,5 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+use crate::coarse_time;
 use crate::compilers::TargetLib;
 use crate::global_state::GlobalState;
 use crate::global_timer::GlobalTimer;
@@ -21,13 +22,16 @@ use rand::SeedableRng;
 use serde_json::Value;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::ops::Deref;
 use std::path::Path;
 use std::pin::Pin;
 use std::rc::Rc;
 use std::str;
 use std::thread::JoinHandle;
+use std::time::Duration;
 use std::time::Instant;
+use trust_dns_resolver::TokioAsyncResolver;
 
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub enum DebugType {
@@ -65,6 +69,10 @@ pub struct StateInner {
   pub seeded_rng: Option<StdRng>,
   pub target_lib: TargetLib,
   pub debug_type: DebugType,
+  /// The resolver using the system configuration, created by the first
+  /// `Deno.resolveDns()` without a name server. It spawns its connections on
+  /// the runtime of the isolate, so it isn't shared with other isolates.
+  pub dns_resolver: Option<TokioAsyncResolver>,
 }
 
 impl State {
@@ -95,7 +103,8 @@ impl State {
     self.core_op(json_op(self.stateful_op2(dispatcher)))
   }
 
-  /// Wrap core `OpDispatcher` to collect metrics.
+  /// Wrap core `OpDispatcher` to collect metrics and to attribute permission
+  /// checks to the op and the modules that called it.
   // TODO(ry) this should be private. Is called by stateful_json_op or
   // stateful_minimal_op
   pub fn core_op<D>(
@@ -115,8 +124,21 @@ impl State {
       let bytes_sent_zero_copy =
         zero_copy.as_ref().map(|b| b.len()).unwrap_or(0) as u64;
 
+      let needs_op_context = state.borrow().permissions.needs_op_context();
+      if needs_op_context {
+        state.borrow_mut().permissions.enter_op(
+          isolate.current_op_name(),
+          isolate.current_op_call_stack(),
+          isolate.current_op_call_stack_truncated(),
+        );
+      }
+
       let op = dispatcher(isolate, control, zero_copy);
 
+      if needs_op_context {
+        state.borrow_mut().permissions.exit_op();
+      }
+
       match op {
         Op::Sync(buf) => {
           let mut state_ = state.borrow_mut();
@@ -262,7 +284,9 @@ impl ModuleLoader for State {
   ) -> Pin<Box<deno_core::ModuleSourceFuture>> {
     let module_specifier = module_specifier.clone();
     if is_dyn_import {
-      if let Err(e) = self.check_dyn_import(&module_specifier) {
+      if let Err(e) =
+        self.check_dyn_import(&module_specifier, maybe_referrer.as_ref())
+      {
         return async move { Err(e.into()) }.boxed_local();
       }
     }
@@ -328,6 +352,7 @@ impl State {
       seeded_rng,
       target_lib: TargetLib::Main,
       debug_type,
+      dns_resolver: None,
     }));
 
     Ok(Self(state))
@@ -363,6 +388,7 @@ impl State {
       seeded_rng,
       target_lib: TargetLib::Worker,
       debug_type: DebugType::Dependent,
+      dns_resolver: None,
     }));
 
     Ok(Self(state))
@@ -373,21 +399,56 @@ impl State {
     self.borrow().permissions.check_read(path)
   }
 
+  #[inline]
+  pub fn check_read_entry(&self, path: &Path) -> Result<(), OpError> {
+    self.borrow().permissions.check_read_entry(path)
+  }
+
+  #[inline]
+  pub fn check_read_all(&self, what: &str) -> Result<(), OpError> {
+    self.borrow().permissions.check_read_all(what)
+  }
+
   #[inline]
   pub fn check_write(&self, path: &Path) -> Result<(), OpError> {
     self.borrow().permissions.check_write(path)
   }
 
+  #[inline]
+  pub fn check_write_entry(&self, path: &Path) -> Result<(), OpError> {
+    self.borrow().permissions.check_write_entry(path)
+  }
+
+  #[inline]
+  pub fn check_write_all(&self, what: &str) -> Result<(), OpError> {
+    self.borrow().permissions.check_write_all(what)
+  }
+
   #[inline]
   pub fn check_env(&self) -> Result<(), OpError> {
     self.borrow().permissions.check_env()
   }
 
+  #[inline]
+  pub fn check_env_var(&self, key: &str) -> Result<(), OpError> {
+    self.borrow().permissions.check_env_var(key)
+  }
+
   #[inline]
   pub fn check_net(&self, hostname: &str, port: u16) -> Result<(), OpError> {
     self.borrow().permissions.check_net(hostname, port)
   }
 
+  #[inline]
+  pub fn check_net_host(&self, hostname: &str) -> Result<(), OpError> {
+    self.borrow().permissions.check_net_host(hostname)
+  }
+
+  #[inline]
+  pub fn check_net_addr(&self, addr: &SocketAddr) -> Result<(), OpError> {
+    self.borrow().permissions.check_net_addr(addr)
+  }
+
   #[inline]
   pub fn check_net_url(&self, url: &url::Url) -> Result<(), OpError> {
     self.borrow().permissions.check_net_url(url)
@@ -398,21 +459,48 @@ impl State {
     self.borrow().permissions.check_run()
   }
 
+  #[inline]
+  pub fn check_run_program(&self, program: &Path) -> Result<(), OpError> {
+    self.borrow().permissions.check_run_program(program)
+  }
+
   #[inline]
   pub fn check_plugin(&self, filename: &Path) -> Result<(), OpError> {
     self.borrow().permissions.check_plugin(filename)
   }
 
+  /// The resolution that times exposed to JavaScript are clamped to, or
+  /// `None` if the high resolution time permission is granted.
+  pub fn timer_resolution(&self) -> Option<Duration> {
+    let state = self.borrow();
+    if state.permissions.allow_hrtime.is_allow() {
+      None
+    } else {
+      Some(
+        state
+          .global_state
+          .flags
+          .timer_resolution
+          .unwrap_or(coarse_time::DEFAULT_RESOLUTION),
+      )
+    }
+  }
+
   pub fn check_dyn_import(
     &self,
     module_specifier: &ModuleSpecifier,
+    maybe_referrer: Option<&ModuleSpecifier>,
   ) -> Result<(), OpError> {
     let u = module_specifier.as_url();
-    match u.scheme() {
-      "http" | "https" => {
-        self.check_net_url(u)?;
-        Ok(())
-      }
+    // Dynamic imports aren't dispatched by an op, so the importing module is
+    // recorded as the caller for the permission policy and audit log.
+    let calling_modules: Vec<String> =
+      maybe_referrer.iter().map(|r| r.to_string()).collect();
+    let mut state = self.borrow_mut();
+    let permissions = &mut state.permissions;
+    permissions.enter_op(None, &calling_modules, false);
+    let result = match u.scheme() {
+      "http" | "https" => permissions.check_net_url(u),
       "file" => {
         let path = u
           .to_file_path()
@@ -420,11 +508,12 @@ impl State {
           .into_os_string()
           .into_string()
           .unwrap();
-        self.check_read(Path::new(&path))?;
-        Ok(())
+        permissions.check_read(Path::new(&path))
       }
       _ => unreachable!(),
-    }
+    };
+    permissions.exit_op();
+    result
   }
 
   #[cfg(test)]
@@ -1,3 +1,4 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
 const res = await fetch("http://localhost:4545/std/examples/colors.ts");
-console.log(`Response http: ${await res.text()}`);
+await res.text();
+console.log(`Response status: ${res.status}`);
@@ -3,6 +3,7 @@ import { serve, ServerRequest } from "../../std/http/server.ts";
 import { assertEquals } from "../../std/testing/asserts.ts";
 
 const addr = Deno.args[1] || "127.0.0.1:4555";
+const auth = "deno:proxy";
 
 async function proxyServer(): Promise<void> {
   const server = serve(addr);
@@ -14,39 +15,81 @@ async function proxyServer(): Promise<void> {
 }
 
 async function proxyRequest(req: ServerRequest): Promise<void> {
+  if (req.headers.get("proxy-authorization") !== `Basic ${btoa(auth)}`) {
+    console.log(`Proxy authentication required for: ${req.url}`);
+    req.respond({
+      status: 407,
+      headers: new Headers({ "proxy-authenticate": 'Basic realm="deno"' }),
+    });
+    return;
+  }
   console.log(`Proxy request to: ${req.url}`);
+  const headers = new Headers(req.headers);
+  headers.delete("proxy-authorization");
   const resp = await fetch(req.url, {
     method: req.method,
-    headers: req.headers,
+    headers,
   });
   req.respond(resp);
 }
 
-async function testFetch(): Promise<void> {
+async function runClient(
+  args: string[],
+  env: { [key: string]: string }
+): Promise<string> {
   const c = Deno.run({
-    cmd: [Deno.execPath(), "--reload", "--allow-net", "045_proxy_client.ts"],
+    cmd: [Deno.execPath(), "run", "--reload", "--allow-net", ...args],
     stdout: "piped",
-    env: {
-      HTTP_PROXY: `http://${addr}`,
-    },
+    env,
   });
-
   const status = await c.status();
   assertEquals(status.code, 0);
+  const output = new TextDecoder().decode(await c.output());
   c.close();
+  return output.trim();
+}
+
+async function testFetch(): Promise<void> {
+  const output = await runClient(["045_proxy_client.ts"], {
+    HTTP_PROXY: `http://${auth}@${addr}`,
+  });
+  assertEquals(output, "Response status: 200");
+}
+
+async function testFetchWithoutAuth(): Promise<void> {
+  const output = await runClient(["045_proxy_client.ts"], {
+    HTTP_PROXY: `http://${addr}`,
+  });
+  assertEquals(output, "Response status: 407");
+}
+
+async function testNoProxy(): Promise<void> {
+  const output = await runClient(["045_proxy_client.ts"], {
+    HTTP_PROXY: `http://${auth}@${addr}`,
+    NO_PROXY: "example.com,localhost",
+  });
+  assertEquals(output, "Response status: 200");
+}
+
+async function testProxyFlag(): Promise<void> {
+  const output = await runClient(
+    [`--proxy=http://${auth}@${addr}`, "045_proxy_client.ts"],
+    {}
+  );
+  assertEquals(output, "Response status: 200");
 }
 
 async function testModuleDownload(): Promise<void> {
   const http = Deno.run({
     cmd: [
       Deno.execPath(),
-      "--reload",
       "cache",
+      "--reload",
       "http://localhost:4545/std/examples/colors.ts",
     ],
     stdout: "piped",
     env: {
-      HTTP_PROXY: `http://${addr}`,
+      HTTP_PROXY: `http://${auth}@${addr}`,
     },
   });
 
@@ -57,5 +100,8 @@ async function testModuleDownload(): Promise<void> {
 
 proxyServer();
 await testFetch();
+await testFetchWithoutAuth();
+await testNoProxy();
+await testProxyFlag();
 await testModuleDownload();
 Deno.exit(0);
@@ -1,5 +1,5 @@
 // Copyright 2018-2019 the Deno authors. All rights reserved. MIT license.
-const { args, readFileSync, writeFileSync, exit } = Deno;
+const { args, readFileSync, writeFileSync, exit, env } = Deno;
 
 const name = args[0];
 const test: { [key: string]: Function } = {
@@ -11,6 +11,16 @@ const test: { [key: string]: Function } = {
       writeFileSync(file, new Uint8Array(0), { append: true })
     );
   },
+  env(keys: string[]): void {
+    keys.forEach((key) => env(key));
+  },
+  async run(cmds: string[]): Promise<void> {
+    for (const cmd of cmds) {
+      const p = Deno.run({ cmd: [cmd, "--version"], stdout: "null" });
+      await p.status();
+      p.close();
+    }
+  },
   netFetch(hosts: string[]): void {
     hosts.forEach((host) => fetch(host));
   },
@@ -0,0 +1,4 @@
+const response = await fetch("http://localhost:4545/cli/tests/fixture.json", {
+  timeout: 60000,
+});
+console.log(response.status);
@@ -0,0 +1,6 @@
+// The proxy isn't covered by --allow-net, even though the target is.
+try {
+  Deno.createHttpClient({ proxy: "http://localhost:4555" });
+} catch (e) {
+  console.log(e.name);
+}
@@ -81,6 +81,100 @@ fn deno_dir_test() {
   drop(g);
 }
 
+#[test]
+fn fetch_timeout_unread_body() {
+  let g = util::http_server();
+  let start = std::time::Instant::now();
+  let output = util::deno_cmd()
+    .current_dir(util::root_path())
+    .arg("run")
+    .arg("--allow-net")
+    .arg("cli/tests/fetch_timeout_unread_body.ts")
+    .output()
+    .expect("failed to spawn script");
+  assert!(output.status.success());
+  let stdout = std::str::from_utf8(&output.stdout).unwrap();
+  assert_eq!(stdout.trim(), "200");
+  // The timeout of the fetch doesn't keep the program running while nothing
+  // reads the response body.
+  assert!(start.elapsed() < std::time::Duration::from_secs(30));
+  drop(g);
+}
+
+#[test]
+fn http_client_proxy_perm() {
+  let output = util::deno_cmd()
+    .current_dir(util::root_path())
+    .arg("run")
+    .arg("--allow-net=localhost:4545")
+    .arg("cli/tests/http_client_proxy_perm.ts")
+    .output()
+    .expect("failed to spawn script");
+  assert!(output.status.success());
+  let stdout = std::str::from_utf8(&output.stdout).unwrap();
+  assert_eq!(stdout.trim(), "PermissionDenied");
+}
+
+#[cfg(unix)]
+#[test]
+fn receive_resources_perm() {
+  let t = TempDir::new().expect("tempdir fail");
+  std::fs::write(t.path().join("file.txt"), "hello").unwrap();
+  let output = util::deno_cmd()
+    .current_dir(util::root_path())
+    .arg("run")
+    .arg(format!("--allow-read={}", t.path().display()))
+    .arg("cli/tests/receive_resources_perm.ts")
+    .arg(t.path())
+    .output()
+    .expect("failed to spawn script");
+  assert!(output.status.success());
+  let stdout = std::str::from_utf8(&output.stdout).unwrap();
+  assert_eq!(stdout.trim(), "PermissionDenied");
+}
+
+#[cfg(unix)]
+#[test]
+fn symlink_escape() {
+  use std::os::unix::fs::symlink;
+  let t = TempDir::new().expect("tempdir fail");
+  let allowed = t.path().join("allowed");
+  let outside = t.path().join("outside");
+  std::fs::create_dir(&allowed).unwrap();
+  std::fs::create_dir(&outside).unwrap();
+  std::fs::write(outside.join("secret.txt"), "secret").unwrap();
+  symlink(outside.join("secret.txt"), allowed.join("file_link")).unwrap();
+  symlink(&outside, allowed.join("dir_link")).unwrap();
+
+  let output = util::deno_cmd()
+    .current_dir(util::root_path())
+    .arg("run")
+    .arg(format!("--allow-read={}", allowed.display()))
+    .arg(format!("--allow-write={}", allowed.display()))
+    .arg("cli/tests/symlink_escape.ts")
+    .arg(t.path())
+    .output()
+    .expect("failed to spawn script");
+  assert!(output.status.success());
+  let stdout = std::str::from_utf8(&output.stdout).unwrap();
+  assert_eq!(
+    stdout.lines().collect::<Vec<_>>(),
+    vec![
+      "open: PermissionDenied",
+      "copyFile: PermissionDenied",
+      "rename: PermissionDenied",
+      "link: PermissionDenied",
+      "lstat: ok",
+      "readlink: ok",
+      "renameLink: ok",
+      "remove: ok",
+    ]
+  );
+  assert!(outside.join("secret.txt").exists());
+  assert!(!allowed.join("copy.txt").exists());
+  assert!(!allowed.join("renamed_link").exists());
+}
+
 #[test]
 fn fetch_test() {
   use deno::http_cache::url_to_filename;
@@ -1087,7 +1181,7 @@ itest!(_044_bad_resource {
   exit_code: 1,
 });
 
-itest_ignore!(_045_proxy {
+itest!(_045_proxy {
   args: "run --allow-net --allow-env --allow-run --reload 045_proxy_test.ts",
   output: "045_proxy_test.ts.out",
   http_server: true,
@@ -1482,6 +1576,11 @@ itest!(seed_random {
   output: "seed_random.js.out",
 });
 
+itest!(timer_resolution {
+  args: "run --reload --allow-read --timer-resolution=2000 timer_resolution.ts",
+  output: "timer_resolution.ts.out",
+});
+
 itest!(type_definitions {
   args: "run --reload type_definitions.ts",
   output: "type_definitions.ts.out",
@@ -2093,6 +2192,265 @@ fn test_permissions_net_listen_allow_localhost() {
   assert!(!err.contains(util::PERMISSION_DENIED_PATTERN));
 }
 
+#[test]
+fn test_permissions_env_allow_whitelisted() {
+  let (_, err) = util::run_and_collect_output(
+    true,
+    "run --allow-env=HOME,DENO_* complex_permissions_test.ts env HOME DENO_DIR",
+    None,
+    None,
+    false,
+  );
+  assert!(!err.contains(util::PERMISSION_DENIED_PATTERN));
+}
+
+#[test]
+fn test_permissions_env_deny_not_whitelisted() {
+  let (_, err) = util::run_and_collect_output(
+    false,
+    "run --allow-env=HOME complex_permissions_test.ts env HOME PATH",
+    None,
+    None,
+    false,
+  );
+  assert!(err.contains(util::PERMISSION_DENIED_PATTERN));
+}
+
+#[test]
+fn test_permissions_run_allow_whitelisted() {
+  let deno_exe = util::deno_exe_path()
+    .into_os_string()
+    .into_string()
+    .unwrap();
+  let (_, err) = util::run_and_collect_output(
+    true,
+    &format!(
+      "run --allow-run={0} complex_permissions_test.ts run {0}",
+      deno_exe
+    ),
+    None,
+    None,
+    false,
+  );
+  assert!(!err.contains(util::PERMISSION_DENIED_PATTERN));
+}
+
+#[test]
+fn test_permissions_run_deny_not_whitelisted() {
+  let deno_exe = util::deno_exe_path()
+    .into_os_string()
+    .into_string()
+    .unwrap();
+  let (_, err) = util::run_and_collect_output(
+    false,
+    &format!(
+      "run --allow-run={} complex_permissions_test.ts run python",
+      deno_exe
+    ),
+    None,
+    None,
+    false,
+  );
+  assert!(err.contains(util::PERMISSION_DENIED_PATTERN));
+}
+
+#[test]
+fn test_permissions_deny_read_overrides_allow_read() {
+  let (_, err) = util::run_and_collect_output(
+    false,
+    "run --allow-read --deny-read=tls complex_permissions_test.ts read tls/localhost.crt",
+    None,
+    None,
+    false,
+  );
+  assert!(err.contains(util::PERMISSION_DENIED_PATTERN));
+  assert!(err.contains("--deny-read="));
+}
+
+#[test]
+fn test_permissions_deny_read_outside_denied_dir() {
+  let (_, err) = util::run_and_collect_output(
+    true,
+    "run --allow-read --deny-read=tls complex_permissions_test.ts read complex_permissions_test.ts",
+    None,
+    None,
+    false,
+  );
+  assert!(!err.contains(util::PERMISSION_DENIED_PATTERN));
+}
+
+#[test]
+fn test_permissions_deny_net_overrides_allow_net() {
+  let (_, err) = util::run_and_collect_output(
+    false,
+    "run --allow-net --deny-net=localhost:4545 complex_permissions_test.ts netFetch http://localhost:4545/",
+    None,
+    None,
+    true,
+  );
+  assert!(err.contains(util::PERMISSION_DENIED_PATTERN));
+  assert!(err.contains("--deny-net=localhost:4545"));
+}
+
+#[test]
+fn test_permissions_deny_env_overrides_allow_env() {
+  let (_, err) = util::run_and_collect_output(
+    false,
+    "run --allow-env --deny-env=DENO_* complex_permissions_test.ts env HOME DENO_DIR",
+    None,
+    None,
+    false,
+  );
+  assert!(err.contains(util::PERMISSION_DENIED_PATTERN));
+  assert!(err.contains("--deny-env=DENO_*"));
+}
+
+#[test]
+fn test_permission_audit_log() {
+  let temp_dir = TempDir::new().expect("tempdir fail");
+  let audit_path = temp_dir.path().join("audit.log");
+  util::run_and_collect_output(
+    false,
+    &format!(
+      "run --allow-env=HOME --permission-audit={} complex_permissions_test.ts env HOME PATH",
+      audit_path.display()
+    ),
+    None,
+    None,
+    false,
+  );
+  let log = std::fs::read_to_string(&audit_path).unwrap();
+  let entries: Vec<serde_json::Value> = log
+    .lines()
+    .map(|line| serde_json::from_str(line).unwrap())
+    .filter(|entry: &serde_json::Value| entry["permission"] == "env")
+    .collect();
+  assert_eq!(entries.len(), 2);
+  assert_eq!(entries[0]["resource"], "HOME");
+  assert_eq!(entries[0]["decision"], "granted");
+  assert_eq!(entries[0]["op"], "op_get_env");
+  assert!(entries[0]["module"]
+    .as_str()
+    .unwrap()
+    .ends_with("complex_permissions_test.ts"));
+  assert_eq!(entries[1]["resource"], "PATH");
+  assert_eq!(entries[1]["decision"], "denied");
+}
+
+#[test]
+fn test_permission_policy_restricts_module() {
+  let temp_dir = TempDir::new().expect("tempdir fail");
+  let policy_path = temp_dir.path().join("policy.json");
+  let module_url = url::Url::from_file_path(
+    util::root_path().join("cli/tests/complex_permissions_test.ts"),
+  )
+  .unwrap();
+  std::fs::write(
+    &policy_path,
+    format!(r#"{{ "{}": {{ "env": ["HOME"] }} }}"#, module_url),
+  )
+  .unwrap();
+  let (_, err) = util::run_and_collect_output(
+    false,
+    &format!(
+      "run --allow-env --permission-policy={} complex_permissions_test.ts env HOME PATH",
+      policy_path.display()
+    ),
+    None,
+    None,
+    false,
+  );
+  assert!(err.contains(util::PERMISSION_DENIED_PATTERN));
+  assert!(err.contains("access to environment variable \"PATH\""));
+  assert!(err.contains("denied by the permission policy"));
+}
+
+#[test]
+fn test_permission_policy_unknown_caller() {
+  let temp_dir = TempDir::new().expect("tempdir fail");
+  let policy_path = temp_dir.path().join("policy.json");
+  let module_url = url::Url::from_file_path(
+    util::root_path().join("cli/tests/permission_policy_bypass.ts"),
+  )
+  .unwrap();
+  std::fs::write(
+    &policy_path,
+    format!(r#"{{ "{}": {{ "env": ["HOME"] }} }}"#, module_url),
+  )
+  .unwrap();
+  // A restricted module can't escape its policy by having the runtime call
+  // `Deno.env()` for it, or by hiding below a deep stack.
+  for test in &["then", "timeout", "recursion"] {
+    let (_, err) = util::run_and_collect_output(
+      false,
+      &format!(
+        "run --allow-env --permission-policy={} permission_policy_bypass.ts {} PATH",
+        policy_path.display(),
+        test
+      ),
+      None,
+      None,
+      false,
+    );
+    assert!(err.contains(util::PERMISSION_DENIED_PATTERN), "{}", test);
+    assert!(err.contains("the calling module is unknown"), "{}", test);
+
+    let (_, err) = util::run_and_collect_output(
+      true,
+      &format!(
+        "run --allow-env --permission-policy={} permission_policy_bypass.ts {} HOME",
+        policy_path.display(),
+        test
+      ),
+      None,
+      None,
+      false,
+    );
+    assert!(!err.contains(util::PERMISSION_DENIED_PATTERN), "{}", test);
+  }
+}
+
+#[test]
+fn test_permission_policy_eval() {
+  let temp_dir = TempDir::new().expect("tempdir fail");
+  let policy_path = temp_dir.path().join("policy.json");
+  let module_url = url::Url::from_file_path(
+    util::root_path().join("cli/tests/permission_policy_eval_helper.ts"),
+  )
+  .unwrap();
+  std::fs::write(
+    &policy_path,
+    format!(r#"{{ "{}": {{ "env": ["HOME"] }} }}"#, module_url),
+  )
+  .unwrap();
+  // Code evaluated by a restricted module doesn't run under the policy of
+  // the module that calls it.
+  let (_, err) = util::run_and_collect_output(
+    false,
+    &format!(
+      "run --allow-env --permission-policy={} permission_policy_eval.ts PATH",
+      policy_path.display(),
+    ),
+    None,
+    None,
+    false,
+  );
+  assert!(err.contains(util::PERMISSION_DENIED_PATTERN));
+  assert!(err.contains("the calling module is unknown"));
+
+  let (_, err) = util::run_and_collect_output(
+    true,
+    &format!(
+      "run --allow-env --permission-policy={} permission_policy_eval.ts HOME",
+      policy_path.display(),
+    ),
+    None,
+    None,
+    false,
+  );
+  assert!(!err.contains(util::PERMISSION_DENIED_PATTERN));
+}
+
 fn extract_ws_url_from_stderr(
   stderr: &mut std::process::ChildStderr,
 ) -> url::Url {
@@ -0,0 +1,19 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+import { callDeep } from "./permission_policy_bypass_helper.ts";
+
+const { args, env } = Deno;
+
+const [name, key] = args;
+const test: { [key: string]: Function } = {
+  then(): void {
+    Promise.resolve(key).then(env);
+  },
+  timeout(): void {
+    setTimeout(env, 0, key);
+  },
+  recursion(): void {
+    callDeep(100, env, key);
+  },
+};
+
+test[name]();
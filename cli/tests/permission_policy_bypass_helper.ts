@@ -0,0 +1,8 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+export function callDeep<T>(
+  depth: number,
+  fn: (arg: T) => unknown,
+  arg: T
+): unknown {
+  return depth === 0 ? fn(arg) : callDeep(depth - 1, fn, arg);
+}
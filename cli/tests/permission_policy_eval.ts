@@ -0,0 +1,7 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+import { envGetter } from "./permission_policy_eval_helper.ts";
+
+// Only this module is on the stack when the function created by the
+// restricted helper calls `Deno.env()`.
+const get = envGetter();
+console.log(get(Deno.args[0]));
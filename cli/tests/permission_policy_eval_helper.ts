@@ -0,0 +1,6 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+export function envGetter(): (key: string) => string | undefined {
+  return new Function("key", "return Deno.env(key);") as (
+    key: string
+  ) => string | undefined;
+}
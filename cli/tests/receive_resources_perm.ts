@@ -0,0 +1,18 @@
+// A received file needs --allow-read for every path, as its path isn't known.
+const [dir] = Deno.args;
+const address = `${dir}/socket`;
+const listener = Deno.listen({ address, transport: "unix" });
+const acceptedPromise = listener.accept();
+const conn = await Deno.connect({ address, transport: "unix" });
+const accepted = await acceptedPromise;
+const file = await Deno.open(`${dir}/file.txt`);
+await Deno.sendResources(conn.rid, [file.rid]);
+file.close();
+try {
+  await Deno.receiveResources(accepted.rid);
+} catch (e) {
+  console.log(e.name);
+}
+conn.close();
+accepted.close();
+listener.close();
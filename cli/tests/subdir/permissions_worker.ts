@@ -0,0 +1,8 @@
+onmessage = async function (e): Promise<void> {
+  const states = [];
+  for (const descriptor of e.data) {
+    const { state } = await Deno.permissions.query(descriptor);
+    states.push(state);
+  }
+  postMessage(states);
+};
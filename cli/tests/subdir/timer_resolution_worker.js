@@ -0,0 +1,4 @@
+onmessage = function (e) {
+  postMessage(e.timeStamp);
+  close();
+};
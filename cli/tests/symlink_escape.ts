@@ -0,0 +1,37 @@
+// Run with read and write access to `${dir}/allowed` only, in which
+// "file_link" points at "outside/secret.txt" and "dir_link" at "outside".
+const allowed = `${Deno.args[0]}/allowed`;
+
+async function attempt(
+  name: string,
+  f: () => Promise<unknown>
+): Promise<void> {
+  try {
+    await f();
+    console.log(`${name}: ok`);
+  } catch (e) {
+    console.log(`${name}: ${e.name}`);
+  }
+}
+
+// Following a symlink out of the allowed directory is denied...
+await attempt("open", async () => {
+  (await Deno.open(`${allowed}/file_link`)).close();
+});
+await attempt("copyFile", () =>
+  Deno.copyFile(`${allowed}/dir_link/secret.txt`, `${allowed}/copy.txt`)
+);
+await attempt("rename", () =>
+  Deno.rename(`${allowed}/dir_link/secret.txt`, `${allowed}/moved.txt`)
+);
+await attempt("link", () =>
+  Deno.link(`${allowed}/dir_link/secret.txt`, `${allowed}/hard_link`)
+);
+
+// ...while the symlinks themselves can be accessed.
+await attempt("lstat", () => Deno.lstat(`${allowed}/file_link`));
+await attempt("readlink", () => Deno.readlink(`${allowed}/file_link`));
+await attempt("renameLink", () =>
+  Deno.rename(`${allowed}/file_link`, `${allowed}/renamed_link`)
+);
+await attempt("remove", () => Deno.remove(`${allowed}/renamed_link`));
@@ -0,0 +1,28 @@
+// Run with --timer-resolution=2000 and without --allow-hrtime, every time
+// exposed to the program must be a multiple of 2 seconds.
+function assertResolution(name: string, millis: number): void {
+  if (millis % 2000 !== 0) {
+    throw new Error(`${name} isn't clamped to 2s: ${millis}`);
+  }
+  console.log(`${name} ok`);
+}
+
+assertResolution("performance.now()", performance.now());
+
+const { modified, accessed } = Deno.statSync("timer_resolution.ts");
+assertResolution("stat modified", modified! * 1000);
+assertResolution("stat accessed", accessed! * 1000);
+for (const entry of Deno.readdirSync("subdir")) {
+  assertResolution(`readdir ${entry.name} modified`, entry.modified! * 1000);
+  break;
+}
+
+const worker = new Worker("./subdir/timer_resolution_worker.js", {
+  type: "module",
+});
+worker.onmessage = (e: MessageEvent): void => {
+  assertResolution("worker received message timeStamp", e.data);
+  assertResolution("host received message timeStamp", e.timeStamp);
+  worker.terminate();
+};
+worker.postMessage("hello");
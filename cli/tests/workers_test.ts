@@ -290,3 +290,43 @@ Deno.test({
     await promise2;
   },
 });
+
+Deno.test({
+  name: "worker with narrowed permissions",
+  fn: async function (): Promise<void> {
+    const promise = createResolvable();
+    const worker = new Worker("../tests/subdir/permissions_worker.ts", {
+      type: "module",
+      deno: { permissions: { net: ["localhost:4545"] } },
+    });
+
+    worker.onmessage = (e): void => {
+      assertEquals(e.data, ["granted", "denied", "denied"]);
+      worker.terminate();
+      promise.resolve();
+    };
+
+    worker.postMessage([
+      { name: "net", url: "http://localhost:4545" },
+      { name: "net", url: "http://deno.land" },
+      { name: "hrtime" },
+    ]);
+    await promise;
+  },
+});
+
+Deno.test({
+  name: "worker can't get more permissions than its parent",
+  fn: function (): void {
+    let err;
+    try {
+      new Worker("../tests/subdir/permissions_worker.ts", {
+        type: "module",
+        deno: { permissions: { read: true } },
+      });
+    } catch (e) {
+      err = e;
+    }
+    assert(err instanceof Deno.errors.PermissionDenied);
+  },
+});
@@ -15,5 +15,17 @@ where
   F: std::future::Future<Output = R> + 'static,
 {
   let mut rt = create_basic_runtime();
-  rt.block_on(future)
+  block_on_local(&mut rt, future)
+}
+
+/// Runs `future` on `rt` so that ops can spawn futures that aren't `Send`
+/// with `tokio::task::spawn_local()`.
+pub fn block_on_local<F>(
+  rt: &mut tokio::runtime::Runtime,
+  future: F,
+) -> F::Output
+where
+  F: std::future::Future,
+{
+  tokio::task::LocalSet::new().block_on(rt, future)
 }
@@ -1,4 +1,5 @@
 // Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+use crate::coarse_time;
 use crate::ops;
 use crate::state::State;
 use crate::worker::Worker;
@@ -131,6 +132,7 @@ impl WebWorker {
       ops::errors::init(isolate, &state);
       ops::timers::init(isolate, &state);
       ops::fetch::init(isolate, &state);
+      ops::websocket::init(isolate, &state);
 
       if has_deno_namespace {
         ops::runtime_compiler::init(isolate, &state);
@@ -139,6 +141,7 @@ impl WebWorker {
         ops::plugins::init(isolate, &state);
         ops::net::init(isolate, &state);
         ops::tls::init(isolate, &state);
+        ops::http::init(isolate, &state);
         ops::os::init(isolate, &state);
         ops::permissions::init(isolate, &state);
         ops::process::init(isolate, &state);
@@ -218,7 +221,10 @@ impl Future for WebWorker {
         Some(msg) => {
           let msg = String::from_utf8(msg.to_vec()).unwrap();
           debug!("received message from host: {}", msg);
-          let script = format!("workerMessageRecvCallback({})", msg);
+          let time_stamp =
+            coarse_time::unix_time_millis(worker.state.timer_resolution());
+          let script =
+            format!("workerMessageRecvCallback({}, {})", msg, time_stamp);
 
           if let Err(e) = worker.execute(&script) {
             // If execution was terminated during message callback then
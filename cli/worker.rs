@@ -99,6 +99,9 @@ impl Worker {
   pub fn new(name: String, startup_data: StartupData, state: State) -> Self {
     let loader = Rc::new(state.clone());
     let mut isolate = deno_core::EsIsolate::new(loader, startup_data, false);
+    if state.borrow().permissions.needs_op_context() {
+      isolate.set_capture_op_call_stack(true);
+    }
 
     let global_state = state.borrow().global_state.clone();
 
@@ -246,6 +249,7 @@ impl MainWorker {
       ops::plugins::init(isolate, &state);
       ops::net::init(isolate, &state);
       ops::tls::init(isolate, &state);
+      ops::http::init(isolate, &state);
       ops::os::init(isolate, &state);
       ops::permissions::init(isolate, &state);
       ops::process::init(isolate, &state);
@@ -255,6 +259,7 @@ impl MainWorker {
       ops::signal::init(isolate, &state);
       ops::timers::init(isolate, &state);
       ops::tty::init(isolate, &state);
+      ops::websocket::init(isolate, &state);
       ops::worker_host::init(isolate, &state);
     }
     Self(worker)
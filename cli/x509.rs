@@ -0,0 +1,365 @@
+// Copyright 2018-2020 the Deno authors. All rights reserved. MIT license.
+//! Just enough DER and X.509 to describe the certificates of TLS peers,
+//! which rustls and webpki only verify.
+use std::convert::TryFrom;
+use std::net::IpAddr;
+
+const BOOLEAN: u8 = 0x01;
+const INTEGER: u8 = 0x02;
+const OCTET_STRING: u8 = 0x04;
+const OID: u8 = 0x06;
+const BMP_STRING: u8 = 0x1e;
+const UTC_TIME: u8 = 0x17;
+const GENERALIZED_TIME: u8 = 0x18;
+const SEQUENCE: u8 = 0x30;
+const SET: u8 = 0x31;
+const VERSION: u8 = 0xa0;
+const ISSUER_UNIQUE_ID: u8 = 0x81;
+const SUBJECT_UNIQUE_ID: u8 = 0x82;
+const EXTENSIONS: u8 = 0xa3;
+
+// GeneralName choices.
+const RFC822_NAME: u8 = 0x81;
+const DNS_NAME: u8 = 0x82;
+const URI: u8 = 0x86;
+const IP_ADDRESS: u8 = 0x87;
+
+/// id-ce-subjectAltName (2.5.29.17).
+const SUBJECT_ALT_NAME: &[u8] = &[0x55, 0x1d, 0x11];
+
+/// Splits the first DER element off `input`, returning its tag, its contents
+/// and the remaining input.
+pub fn der_split(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+  let (&tag, input) = input.split_first()?;
+  let (&first, mut input) = input.split_first()?;
+  let len = if first < 0x80 {
+    first as usize
+  } else {
+    let len_bytes = (first & 0x7f) as usize;
+    if len_bytes == 0 || len_bytes > 4 || input.len() < len_bytes {
+      return None;
+    }
+    let len = input[..len_bytes]
+      .iter()
+      .fold(0, |len, &byte| (len << 8) | byte as usize);
+    input = &input[len_bytes..];
+    len
+  };
+  if input.len() < len {
+    return None;
+  }
+  Some((tag, &input[..len], &input[len..]))
+}
+
+pub fn der_encode(tag: u8, contents: &[u8]) -> Vec<u8> {
+  let mut der = vec![tag];
+  let len = contents.len();
+  if len < 0x80 {
+    der.push(len as u8);
+  } else {
+    let len_bytes = len.to_be_bytes();
+    let skip = len_bytes.iter().take_while(|&&byte| byte == 0).count();
+    der.push(0x80 | (len_bytes.len() - skip) as u8);
+    der.extend_from_slice(&len_bytes[skip..]);
+  }
+  der.extend_from_slice(contents);
+  der
+}
+
+/// Reads the elements of a DER encoded SEQUENCE or SET in order.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+  fn is_empty(&self) -> bool {
+    self.0.is_empty()
+  }
+
+  fn read_any(&mut self) -> Option<(u8, &'a [u8])> {
+    let (tag, contents, rest) = der_split(self.0)?;
+    self.0 = rest;
+    Some((tag, contents))
+  }
+
+  fn read(&mut self, tag: u8) -> Option<&'a [u8]> {
+    match self.read_any()? {
+      (actual, contents) if actual == tag => Some(contents),
+      _ => None,
+    }
+  }
+
+  fn read_optional(&mut self, tag: u8) -> Option<&'a [u8]> {
+    if self.0.first() == Some(&tag) {
+      self.read(tag)
+    } else {
+      None
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct CertificateInfo {
+  /// The subject, like "C=US, O=Example, CN=example.com".
+  pub subject: String,
+  pub issuer: String,
+  /// The DNS names, IP addresses, email addresses and URIs the certificate
+  /// is valid for.
+  pub subject_alt_names: Vec<String>,
+  /// Hex encoded.
+  pub serial_number: String,
+  /// Milliseconds since the epoch.
+  pub not_before: i64,
+  pub not_after: i64,
+}
+
+/// Describes a DER encoded X.509 certificate, or returns `None` if it can't
+/// be parsed.
+pub fn parse_certificate(der: &[u8]) -> Option<CertificateInfo> {
+  let mut certificate = Reader(Reader(der).read(SEQUENCE)?);
+  let mut tbs_certificate = Reader(certificate.read(SEQUENCE)?);
+  tbs_certificate.read_optional(VERSION);
+  let serial_number = tbs_certificate.read(INTEGER)?;
+  let _signature = tbs_certificate.read(SEQUENCE)?;
+  let issuer = format_name(tbs_certificate.read(SEQUENCE)?)?;
+  let mut validity = Reader(tbs_certificate.read(SEQUENCE)?);
+  let not_before = read_time(&mut validity)?;
+  let not_after = read_time(&mut validity)?;
+  let subject = format_name(tbs_certificate.read(SEQUENCE)?)?;
+  let _subject_public_key_info = tbs_certificate.read(SEQUENCE)?;
+  tbs_certificate.read_optional(ISSUER_UNIQUE_ID);
+  tbs_certificate.read_optional(SUBJECT_UNIQUE_ID);
+
+  let mut subject_alt_names = Vec::new();
+  if let Some(extensions) = tbs_certificate.read_optional(EXTENSIONS) {
+    let mut extensions = Reader(Reader(extensions).read(SEQUENCE)?);
+    while !extensions.is_empty() {
+      let mut extension = Reader(extensions.read(SEQUENCE)?);
+      let id = extension.read(OID)?;
+      extension.read_optional(BOOLEAN);
+      let value = extension.read(OCTET_STRING)?;
+      if id == SUBJECT_ALT_NAME {
+        subject_alt_names = parse_subject_alt_names(value)?;
+      }
+    }
+  }
+
+  Some(CertificateInfo {
+    subject,
+    issuer,
+    subject_alt_names,
+    serial_number: format_serial_number(serial_number),
+    not_before,
+    not_after,
+  })
+}
+
+fn format_serial_number(serial_number: &[u8]) -> String {
+  // Drop the sign padding, but keep a single zero.
+  let skip = serial_number
+    .iter()
+    .take_while(|&&byte| byte == 0)
+    .count()
+    .min(serial_number.len().saturating_sub(1));
+  serial_number[skip..]
+    .iter()
+    .map(|byte| format!("{:02x}", byte))
+    .collect()
+}
+
+fn format_name(name: &[u8]) -> Option<String> {
+  let mut parts = Vec::new();
+  let mut relative_names = Reader(name);
+  while !relative_names.is_empty() {
+    let mut attributes = Reader(relative_names.read(SET)?);
+    while !attributes.is_empty() {
+      let mut attribute = Reader(attributes.read(SEQUENCE)?);
+      let attribute_type = attribute.read(OID)?;
+      let (tag, value) = attribute.read_any()?;
+      parts.push(format!(
+        "{}={}",
+        attribute_name(attribute_type),
+        decode_string(tag, value)
+      ));
+    }
+  }
+  Some(parts.join(", "))
+}
+
+fn attribute_name(oid: &[u8]) -> String {
+  let name = match oid {
+    [0x55, 0x04, 0x03] => "CN",
+    [0x55, 0x04, 0x06] => "C",
+    [0x55, 0x04, 0x07] => "L",
+    [0x55, 0x04, 0x08] => "ST",
+    [0x55, 0x04, 0x0a] => "O",
+    [0x55, 0x04, 0x0b] => "OU",
+    [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x09, 0x01] => "emailAddress",
+    _ => return format_oid(oid),
+  };
+  name.to_string()
+}
+
+/// Formats an object identifier in dotted notation, like "2.5.4.3".
+fn format_oid(oid: &[u8]) -> String {
+  let mut arcs = Vec::new();
+  let mut arc: u64 = 0;
+  for &byte in oid {
+    arc = (arc << 7) | u64::from(byte & 0x7f);
+    if byte & 0x80 == 0 {
+      if arcs.is_empty() {
+        // The first byte encodes the first two arcs.
+        let first = (arc / 40).min(2);
+        arcs.push(first);
+        arcs.push(arc - first * 40);
+      } else {
+        arcs.push(arc);
+      }
+      arc = 0;
+    }
+  }
+  let arcs: Vec<String> = arcs.iter().map(u64::to_string).collect();
+  arcs.join(".")
+}
+
+fn decode_string(tag: u8, value: &[u8]) -> String {
+  if tag == BMP_STRING {
+    let units: Vec<u16> = value
+      .chunks(2)
+      .map(|unit| u16::from_be_bytes([unit[0], *unit.get(1).unwrap_or(&0)]))
+      .collect();
+    String::from_utf16_lossy(&units)
+  } else {
+    String::from_utf8_lossy(value).into_owned()
+  }
+}
+
+fn parse_subject_alt_names(value: &[u8]) -> Option<Vec<String>> {
+  let mut names = Reader(Reader(value).read(SEQUENCE)?);
+  let mut subject_alt_names = Vec::new();
+  while !names.is_empty() {
+    let (tag, name) = names.read_any()?;
+    let name = match tag {
+      RFC822_NAME | DNS_NAME | URI => {
+        String::from_utf8_lossy(name).into_owned()
+      }
+      IP_ADDRESS => match name.len() {
+        4 => IpAddr::from(<[u8; 4]>::try_from(name).unwrap()).to_string(),
+        16 => IpAddr::from(<[u8; 16]>::try_from(name).unwrap()).to_string(),
+        _ => continue,
+      },
+      _ => continue,
+    };
+    subject_alt_names.push(name);
+  }
+  Some(subject_alt_names)
+}
+
+/// Reads a UTCTime or GeneralizedTime, in milliseconds since the epoch.
+fn read_time(reader: &mut Reader) -> Option<i64> {
+  let (tag, value) = reader.read_any()?;
+  let value = std::str::from_utf8(value).ok()?;
+  if !value.ends_with('Z') {
+    return None;
+  }
+  let (year, rest) = match tag {
+    UTC_TIME => {
+      let year: i64 = value.get(..2)?.parse().ok()?;
+      let year = if year >= 50 { 1900 + year } else { 2000 + year };
+      (year, value.get(2..value.len() - 1)?)
+    }
+    GENERALIZED_TIME => (
+      value.get(..4)?.parse().ok()?,
+      value.get(4..value.len() - 1)?,
+    ),
+    _ => return None,
+  };
+  // MMDDHHMMSS
+  if rest.len() != 10 {
+    return None;
+  }
+  let field = |i: usize| -> Option<i64> { rest.get(i..i + 2)?.parse().ok() };
+  let days = days_from_civil(year, field(0)?, field(2)?);
+  let seconds = ((days * 24 + field(4)?) * 60 + field(6)?) * 60 + field(8)?;
+  Some(seconds * 1000)
+}
+
+/// Counts the days from 1970-01-01 to a date of the proleptic Gregorian
+/// calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+  let year = if month <= 2 { year - 1 } else { year };
+  let era = if year >= 0 { year } else { year - 399 } / 400;
+  let year_of_era = year - era * 400;
+  let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+  let day_of_era =
+    year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+  era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn pem_to_der(pem: &str) -> Vec<u8> {
+    let encoded: String = pem
+      .lines()
+      .filter(|line| !line.starts_with("-----"))
+      .collect();
+    base64::decode(&encoded).unwrap()
+  }
+
+  #[test]
+  fn test_der_encode_split() {
+    let short = der_encode(OCTET_STRING, &[1, 2, 3]);
+    assert_eq!(short, vec![OCTET_STRING, 3, 1, 2, 3]);
+    let long = der_encode(OCTET_STRING, &[7; 300]);
+    assert_eq!(&long[..4], &[OCTET_STRING, 0x82, 0x01, 0x2c]);
+    let (tag, contents, rest) = der_split(&long).unwrap();
+    assert_eq!(tag, OCTET_STRING);
+    assert_eq!(contents, &[7; 300][..]);
+    assert!(rest.is_empty());
+    assert!(der_split(&long[..100]).is_none());
+  }
+
+  #[test]
+  fn test_parse_certificate() {
+    let der = pem_to_der(include_str!("tests/tls/localhost.crt"));
+    let info = parse_certificate(&der).unwrap();
+    assert_eq!(
+      info.subject,
+      "C=US, ST=YourState, L=YourCity, O=Example-Certificates, CN=localhost.local"
+    );
+    assert_eq!(info.issuer, "C=US, CN=Example-Root-CA");
+    assert_eq!(info.subject_alt_names, vec!["localhost".to_string()]);
+    assert!(!info.serial_number.is_empty());
+    assert!(info.not_before < info.not_after);
+  }
+
+  #[test]
+  fn test_parse_certificate_invalid() {
+    assert!(parse_certificate(&[]).is_none());
+    assert!(parse_certificate(&[SEQUENCE, 2, INTEGER, 0]).is_none());
+  }
+
+  #[test]
+  fn test_read_time() {
+    let utc_time = der_encode(UTC_TIME, b"700101000001Z");
+    assert_eq!(read_time(&mut Reader(&utc_time)), Some(1000));
+    let utc_time = der_encode(UTC_TIME, b"491231235959Z");
+    assert_eq!(read_time(&mut Reader(&utc_time)), Some(2_524_607_999_000));
+    let generalized_time = der_encode(GENERALIZED_TIME, b"21180927120000Z");
+    assert_eq!(
+      read_time(&mut Reader(&generalized_time)),
+      Some(4_693_723_200_000)
+    );
+    let local_time = der_encode(UTC_TIME, b"700101000001");
+    assert_eq!(read_time(&mut Reader(&local_time)), None);
+  }
+
+  #[test]
+  fn test_format_oid() {
+    assert_eq!(format_oid(&[0x55, 0x04, 0x03]), "2.5.4.3");
+    assert_eq!(
+      format_oid(&[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01]),
+      "1.2.840.10045.2.1"
+    );
+  }
+}
@@ -181,6 +181,10 @@ pub struct Isolate {
   have_unpolled_ops: bool,
   startup_script: Option<OwnedScript>,
   pub op_registry: OpRegistry,
+  current_op_id: Option<OpId>,
+  capture_op_call_stack: bool,
+  current_op_call_stack: Vec<String>,
+  current_op_call_stack_truncated: bool,
   waker: AtomicWaker,
   error_handler: Option<Box<IsolateErrorHandleFn>>,
 }
@@ -209,6 +213,9 @@ impl Drop for Isolate {
 
 static DENO_INIT: Once = Once::new();
 
+const EXCEPTION_STACK_FRAME_LIMIT: i32 = 10;
+const OP_CALL_STACK_FRAME_LIMIT: i32 = 64;
+
 #[allow(clippy::missing_safety_doc)]
 pub unsafe fn v8_init() {
   let platform = v8::new_default_platform();
@@ -318,6 +325,10 @@ impl Isolate {
       have_unpolled_ops: false,
       startup_script,
       op_registry: OpRegistry::new(),
+      current_op_id: None,
+      capture_op_call_stack: false,
+      current_op_call_stack: vec![],
+      current_op_call_stack_truncated: false,
       waker: AtomicWaker::new(),
       error_handler: None,
     };
@@ -337,7 +348,10 @@ impl Isolate {
   }
 
   pub fn setup_isolate(mut isolate: v8::OwnedIsolate) -> v8::OwnedIsolate {
-    isolate.set_capture_stack_trace_for_uncaught_exceptions(true, 10);
+    isolate.set_capture_stack_trace_for_uncaught_exceptions(
+      true,
+      EXCEPTION_STACK_FRAME_LIMIT,
+    );
     isolate.set_promise_reject_callback(bindings::promise_reject_callback);
     isolate
   }
@@ -354,6 +368,49 @@ impl Isolate {
     self.op_registry.register(name, op)
   }
 
+  /// Returns the name of the op that is currently being dispatched, if any.
+  /// This is only set while the op's dispatcher is running, so it can be
+  /// consulted from code called by a dispatcher but not from async op futures.
+  pub fn current_op_name(&self) -> Option<&str> {
+    self
+      .current_op_id
+      .and_then(|op_id| self.op_registry.name_of(op_id))
+  }
+
+  /// Enables capturing the JavaScript call stack every time an op is
+  /// dispatched, see `current_op_call_stack()`. This has a cost for each op
+  /// call and is disabled by default.
+  pub fn set_capture_op_call_stack(&mut self, enabled: bool) {
+    self.capture_op_call_stack = enabled;
+    // The stack is captured with the limit used for uncaught exceptions,
+    // which is too small to see every caller of an op.
+    let frame_limit = if enabled {
+      OP_CALL_STACK_FRAME_LIMIT
+    } else {
+      EXCEPTION_STACK_FRAME_LIMIT
+    };
+    self
+      .v8_isolate
+      .as_mut()
+      .unwrap()
+      .set_capture_stack_trace_for_uncaught_exceptions(true, frame_limit);
+  }
+
+  /// Returns the script names of the JavaScript frames that dispatched the
+  /// current op, innermost frame first. Frames of code evaluated at runtime,
+  /// with `eval()` or `new Function()`, have an empty name, as do frames
+  /// without a script. Empty unless enabled with
+  /// `set_capture_op_call_stack()`.
+  pub fn current_op_call_stack(&self) -> &[String] {
+    &self.current_op_call_stack
+  }
+
+  /// Whether the stack returned by `current_op_call_stack()` reached the
+  /// frame limit, in which case its outermost frames are missing.
+  pub fn current_op_call_stack_truncated(&self) -> bool {
+    self.current_op_call_stack_truncated
+  }
+
   /// Allows a callback to be set whenever a V8 exception is made. This allows
   /// the caller to wrap the JSError into an error. By default this callback
   /// is set to JSError::create.
@@ -386,7 +443,17 @@ impl Isolate {
     zero_copy_buf: Option<ZeroCopyBuf>,
   ) -> Option<(OpId, Box<[u8]>)> {
     let op = if let Some(dispatcher) = self.op_registry.get(op_id) {
-      dispatcher(self, control_buf, zero_copy_buf)
+      if self.capture_op_call_stack {
+        let (call_stack, truncated) = capture_call_stack(scope);
+        self.current_op_call_stack = call_stack;
+        self.current_op_call_stack_truncated = truncated;
+      }
+      self.current_op_id = Some(op_id);
+      let op = dispatcher(self, control_buf, zero_copy_buf);
+      self.current_op_id = None;
+      self.current_op_call_stack.clear();
+      self.current_op_call_stack_truncated = false;
+      op
     } else {
       let message =
         v8::String::new(scope, &format!("Unknown op id: {}", op_id)).unwrap();
@@ -716,6 +783,39 @@ fn check_promise_exceptions<'s>(
   }
 }
 
+/// Returns the script names of the frames on the current JavaScript stack,
+/// innermost frame first, and whether V8 dropped frames beyond the limit. V8
+/// only records the stack for a message when the isolate captures stack
+/// traces, which `Isolate::setup_isolate()` enables.
+fn capture_call_stack<'s>(
+  scope: &mut impl v8::ToLocal<'s>,
+) -> (Vec<String>, bool) {
+  let message = v8::String::new(scope, "").unwrap();
+  let exception = v8::Exception::error(scope, message);
+  let message = v8::Exception::create_message(scope, exception);
+  let stack_trace = match message.get_stack_trace(scope) {
+    Some(stack_trace) => stack_trace,
+    None => return (vec![], false),
+  };
+  let frame_count = stack_trace.get_frame_count();
+  let truncated = frame_count >= OP_CALL_STACK_FRAME_LIMIT as usize;
+  let mut script_names = vec![];
+  for i in 0..frame_count {
+    let frame = match stack_trace.get_frame(scope, i) {
+      Some(frame) => frame,
+      None => continue,
+    };
+    // Evaluated code belongs to no script of its own, whatever name a
+    // `sourceURL` comment gives it.
+    let name = match frame.get_script_name(scope) {
+      Some(name) if !frame.is_eval() => name.to_rust_string_lossy(scope),
+      _ => String::new(),
+    };
+    script_names.push(name);
+  }
+  (script_names, truncated)
+}
+
 pub fn js_check<T>(r: Result<T, ErrBox>) -> T {
   if let Err(e) = r {
     panic!(e.to_string());
@@ -855,6 +955,66 @@ pub mod tests {
     assert_eq!(dispatch_count.load(Ordering::Relaxed), 2);
   }
 
+  #[test]
+  fn test_current_op() {
+    let mut isolate = Isolate::new(StartupData::None, false);
+    isolate.set_capture_op_call_stack(true);
+    isolate.register_op("test_current_op", |isolate, _control, _zero_copy| {
+      assert_eq!(isolate.current_op_name(), Some("test_current_op"));
+      assert_eq!(isolate.current_op_call_stack()[0], "current_op.js");
+      Op::Sync(Box::new([]))
+    });
+    js_check(isolate.execute(
+      "current_op.js",
+      r#"
+        Deno.core.send(1);
+        "#,
+    ));
+    assert_eq!(isolate.current_op_name(), None);
+    assert!(isolate.current_op_call_stack().is_empty());
+  }
+
+  #[test]
+  fn test_current_op_call_stack_eval() {
+    let mut isolate = Isolate::new(StartupData::None, false);
+    isolate.set_capture_op_call_stack(true);
+    isolate.register_op("test_eval", |isolate, _control, _zero_copy| {
+      assert_eq!(isolate.current_op_call_stack(), &["", "eval.js"]);
+      Op::Sync(Box::new([]))
+    });
+    js_check(isolate.execute(
+      "eval.js",
+      r#"
+        new Function("Deno.core.send(1);\n//# sourceURL=other.js")();
+        "#,
+    ));
+  }
+
+  #[test]
+  fn test_current_op_call_stack_truncated() {
+    let mut isolate = Isolate::new(StartupData::None, false);
+    isolate.set_capture_op_call_stack(true);
+    isolate.register_op("test_truncated", |isolate, control, _zero_copy| {
+      let expect_truncated = control[0] == 1;
+      assert_eq!(isolate.current_op_call_stack_truncated(), expect_truncated);
+      Op::Sync(Box::new([]))
+    });
+    js_check(isolate.execute(
+      "truncated.js",
+      r#"
+        function recurse(depth, control) {
+          if (depth === 0) {
+            Deno.core.send(1, control);
+          } else {
+            recurse(depth - 1, control);
+          }
+        }
+        recurse(1, new Uint8Array([0]));
+        recurse(100, new Uint8Array([1]));
+        "#,
+    ));
+  }
+
   #[test]
   fn test_poll_async_delayed_ops() {
     run_in_task(|cx| {
@@ -175,7 +175,7 @@ class ContentTypeHandler(QuietSimpleHTTPRequestHandler):
                 self.send_header('user-agent',
                                  self.headers.getheader('user-agent'))
             self.end_headers()
-            data_string = self.rfile.read(int(self.headers['Content-Length']))
+            data_string = read_body(self)
             self.wfile.write(bytes(data_string))
             return
         self.protocol_version = 'HTTP/1.1'
@@ -245,6 +245,19 @@ def server():
     return RunningServer(s, start(s))
 
 
+def read_body(handler):
+    if handler.headers.getheader('transfer-encoding') != 'chunked':
+        return handler.rfile.read(int(handler.headers['Content-Length']))
+    data = b''
+    while True:
+        size = int(handler.rfile.readline().strip(), 16)
+        if size == 0:
+            handler.rfile.readline()
+            return data
+        data += handler.rfile.read(size)
+        handler.rfile.readline()
+
+
 def base_redirect_server(host_port, target_port, extra_path_segment=""):
     os.chdir(root_path)
     target_host = "http://localhost:%d" % target_port
@@ -256,6 +269,14 @@ def base_redirect_server(host_port, target_port, extra_path_segment=""):
                              target_host + extra_path_segment + self.path)
             self.end_headers()
 
+        def do_POST(self):
+            read_body(self)
+            self.send_response(307)
+            self.send_header('Location',
+                             target_host + extra_path_segment + self.path)
+            self.send_header('Content-Length', '0')
+            self.end_headers()
+
     s = get_socket(host_port, RedirectHandler, False)
     if not QUIET:
         print "redirect server http://localhost:%d/ -> http://localhost:%d/" % (